@@ -0,0 +1,128 @@
+use ch8::headless::HeadlessSystem;
+use ch8::opcode::{Instruction, Opcode};
+use ch8::quirks::Quirks;
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+/// Cycles run on each iteration of the throughput benchmark
+const CYCLES: u64 = 10_000;
+
+/// Counts with `V0` and `V2` without drawing, keeping the CPU busy on
+/// arithmetic, skips and jumps
+const COUNT_LOOP: [u8; 16] = [
+    0x60, 0x00, // Sets V0 to 0
+    0x61, 0x01, // Sets V1 to 1
+    0x80, 0x14, // Adds V1 to V0
+    0x72, 0x01, // Adds 1 to V2
+    0x83, 0x15, // Subtracts V1 from V3
+    0x30, 0x00, // Skips the next instruction if V0 wrapped around to 0
+    0x12, 0x04, // Jumps back to the addition
+    0x12, 0x00, // Jumps back to the start
+];
+
+/// One opcode of each instruction group
+const OPCODES: [u16; 16] = [
+    0x00E0, 0x1234, 0x2345, 0x3456, 0x4567, 0x5670, 0x6789, 0x789A, 0x89A4, 0x9AB0, 0xABCD, 0xBCDE,
+    0xCDEF, 0xDEF5, 0xE19E, 0xF233,
+];
+
+/// Decoder matching all four nibbles at once, as `Opcode::decode` did
+/// before dispatching on the opcode group first. Kept as a baseline.
+fn decode_tuple_match(raw: u16) -> Instruction {
+    let nibbles = (
+        (raw & 0xF000) >> 12,
+        (raw & 0x0F00) >> 8,
+        (raw & 0x00F0) >> 4,
+        (raw & 0x000F) as u8,
+    );
+
+    let nnn = raw & 0x0FFF;
+    let kk = (raw & 0x00FF) as u8;
+    let vx = nibbles.1 as usize;
+    let vy = nibbles.2 as usize;
+    let n = nibbles.3;
+
+    match nibbles {
+        (0x00, 0x00, 0x0c, _) => Instruction::ScrollDown(n),
+        (0x00, 0x00, 0x0e, 0x00) => Instruction::Cls,
+        (0x00, 0x00, 0x0e, 0x0e) => Instruction::Ret,
+        (0x00, 0x00, 0x0f, 0x0b) => Instruction::ScrollRight,
+        (0x00, 0x00, 0x0f, 0x0c) => Instruction::ScrollLeft,
+        (0x00, 0x00, 0x0f, 0x0e) => Instruction::LowRes,
+        (0x00, 0x00, 0x0f, 0x0f) => Instruction::HighRes,
+        (0x00, _, _, _) => Instruction::SysAddr(nnn),
+        (0x01, _, _, _) => Instruction::Jump(nnn),
+        (0x02, _, _, _) => Instruction::CallSubroutine(nnn),
+        (0x03, _, _, _) => Instruction::CondEq(vx, kk),
+        (0x04, _, _, _) => Instruction::CondNotEq(vx, kk),
+        (0x05, _, _, 0x00) => Instruction::CondEqVxVy(vx, vy),
+        (0x06, _, _, _) => Instruction::ConstAssignVxToKk(vx, kk),
+        (0x07, _, _, _) => Instruction::ConstAddVxToKk(vx, kk),
+        (0x08, _, _, 0x00) => Instruction::AssignVxToVy(vx, vy),
+        (0x08, _, _, 0x01) => Instruction::BitOpOr(vx, vy),
+        (0x08, _, _, 0x02) => Instruction::BitOpAnd(vx, vy),
+        (0x08, _, _, 0x03) => Instruction::BitOpXor(vx, vy),
+        (0x08, _, _, 0x04) => Instruction::MathAdd(vx, vy),
+        (0x08, _, _, 0x05) => Instruction::MathSub(vx, vy),
+        (0x08, _, _, 0x06) => Instruction::BitOpShr(vx, vy),
+        (0x08, _, _, 0x07) => Instruction::MathSubVyVx(vx, vy),
+        (0x08, _, _, 0x0E) => Instruction::BitOpShl(vx, vy),
+        (0x09, _, _, 0x00) => Instruction::CondVxNotEqVy(vx, vy),
+        (0x0A, _, _, _) => Instruction::Mem(nnn),
+        (0x0B, _, _, _) => Instruction::JumpPcV0(vx, nnn),
+        (0x0C, _, _, _) => Instruction::Rand(vx, kk),
+        (0x0D, _, _, _) => Instruction::Draw(vx, vy, n),
+        (0x0E, _, 0x09, 0x0E) => Instruction::SkipIfKeyPressed(vx),
+        (0x0E, _, 0x0A, 0x01) => Instruction::KeyOpVxNotPressed(vx),
+        (0x0F, _, 0x00, 0x07) => Instruction::SetVxEqToDt(vx),
+        (0x0F, _, 0x00, 0x0A) => Instruction::WaitKeyPressAndStoreOnVx(vx),
+        (0x0F, _, 0x01, 0x05) => Instruction::SetDtEqToVx(vx),
+        (0x0F, _, 0x01, 0x08) => Instruction::SetStEqToVx(vx),
+        (0x0F, _, 0x01, 0x0E) => Instruction::SetIEqToIPlusVx(vx),
+        (0x0F, _, 0x02, 0x09) => Instruction::SetIEqToVx(vx),
+        (0x0F, _, 0x03, 0x03) => Instruction::StoreBinaryCodedDecimal(vx),
+        (0x0F, _, 0x05, 0x05) => Instruction::SetRegsInI(vx),
+        (0x0F, _, 0x06, 0x05) => Instruction::GetRegsInI(vx),
+        (0x0F, _, 0x07, 0x05) => Instruction::StoreFlags(vx),
+        (0x0F, _, 0x08, 0x05) => Instruction::LoadFlags(vx),
+        (0x0F, 0x00, 0x00, 0x02) => Instruction::LoadAudioPattern,
+        (0x0F, _, 0x03, 0x0A) => Instruction::SetPitch(vx),
+        (0x0F, 0x00..=0x03, 0x00, 0x01) => Instruction::SelectPlanes(vx as u8),
+        _ => Instruction::Unknown,
+    }
+}
+
+fn cycle(c: &mut Criterion) {
+    let mut system = HeadlessSystem::new(COUNT_LOOP.to_vec().into(), Quirks::default()).unwrap();
+    let mut group = c.benchmark_group("cycle");
+
+    group.throughput(Throughput::Elements(CYCLES));
+    group.bench_function("count_loop", |b| {
+        b.iter(|| system.run_cycles(black_box(CYCLES)).unwrap())
+    });
+    group.finish();
+}
+
+fn decode(c: &mut Criterion) {
+    let opcodes = OPCODES.map(Opcode::from);
+    let mut group = c.benchmark_group("decode");
+
+    group.throughput(Throughput::Elements(opcodes.len() as u64));
+    group.bench_function("nibble_dispatch", |b| {
+        b.iter(|| {
+            for opcode in black_box(&opcodes) {
+                black_box(opcode.decode());
+            }
+        })
+    });
+    group.bench_function("tuple_match", |b| {
+        b.iter(|| {
+            for raw in black_box(&OPCODES) {
+                black_box(decode_tuple_match(*raw));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, cycle, decode);
+criterion_main!(benches);
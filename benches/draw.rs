@@ -0,0 +1,42 @@
+use ch8::cpu::Cpu;
+use ch8::display::buffer::DisplayBuffer;
+use ch8::keypad::KeypadState;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// Draws the font digit 0 over and over again
+const DRAW_LOOP: [u8; 6] = [
+    0xA0, 0x00, // Sets Index Register to font digit 0
+    0xD0, 0x15, // Draws 5 bytes sprite at (V0, V1)
+    0x12, 0x02, // Jumps back to the draw instruction
+];
+
+fn draw(c: &mut Criterion) {
+    let mut cpu = Cpu::new();
+
+    cpu.load(DRAW_LOOP.to_vec().into()).unwrap();
+
+    c.bench_function("draw", |b| {
+        b.iter(|| {
+            // Runs both the draw and the jump back to it
+            black_box(cpu.cycle(KeypadState::default()).unwrap());
+            black_box(cpu.cycle(KeypadState::default()).unwrap());
+        })
+    });
+}
+
+/// Font digit 0, as stored in memory
+const SPRITE: [u8; 5] = [0xF0, 0x90, 0x90, 0x90, 0xF0];
+
+fn draw_sprite(c: &mut Criterion) {
+    let mut buff = DisplayBuffer::default();
+
+    c.bench_function("draw_sprite", |b| {
+        b.iter(|| {
+            // Crosses the right and bottom edges to cover wrapping
+            black_box(buff.draw_sprite(black_box(62), black_box(30), &SPRITE, true));
+        })
+    });
+}
+
+criterion_group!(benches, draw, draw_sprite);
+criterion_main!(benches);
@@ -0,0 +1,514 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::memory::USER_SPACE_STR;
+use crate::opcode::Instruction;
+
+/// Mnemonics understood by the assembler, `DB` being the directive to emit
+/// raw bytes (e.g. sprite data).
+const MNEMONICS: [&str; 30] = [
+    "CLS", "RET", "SCD", "SCR", "SCL", "EXIT", "LOW", "HIGH", "SYS", "JP", "CALL", "SE", "SNE",
+    "LD", "ADD", "OR", "AND", "XOR", "SUB", "SUBN", "SHR", "SHL", "RND", "DRW", "SKP", "SKNP",
+    "AUDIO", "PITCH", "PLANE", "DB",
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AsmError {
+    /// The mnemonic doesn't match any instruction or directive
+    UnknownMnemonic {
+        /// Line number, starting at 1
+        line: usize,
+        mnemonic: String,
+    },
+    /// The operands don't match any form of the instruction, or are out of
+    /// range.
+    InvalidOperands {
+        /// Line number, starting at 1
+        line: usize,
+        /// Statement with the invalid operands
+        statement: String,
+    },
+    /// A label was defined more than once
+    DuplicateLabel {
+        /// Line number, starting at 1
+        line: usize,
+        label: String,
+    },
+    /// An operand references a label that is not defined
+    UnknownLabel {
+        /// Line number, starting at 1
+        line: usize,
+        label: String,
+    },
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsmError::UnknownMnemonic { line, mnemonic } => {
+                write!(f, "Unknown mnemonic \"{}\" on line {}", mnemonic, line)
+            }
+            AsmError::InvalidOperands { line, statement } => {
+                write!(f, "Invalid operands in \"{}\" on line {}", statement, line)
+            }
+            AsmError::DuplicateLabel { line, label } => {
+                write!(f, "Label \"{}\" redefined on line {}", label, line)
+            }
+            AsmError::UnknownLabel { line, label } => {
+                write!(f, "Unknown label \"{}\" on line {}", label, line)
+            }
+        }
+    }
+}
+
+impl Error for AsmError {}
+
+/// Instruction operand, as written after the mnemonic
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operand {
+    /// General purpose register, `V0` through `VF`
+    Register(usize),
+    /// Number or label address
+    Value(u16),
+    /// Index register, `I`
+    I,
+    /// Memory pointed by the index register, `[I]`
+    IndirectI,
+    /// Delay timer, `DT`
+    Dt,
+    /// Sound timer, `ST`
+    St,
+    /// Key press, `K`
+    K,
+    /// Font sprite location, `F`
+    F,
+    /// Large font sprite location, `HF`
+    Hf,
+    /// BCD representation, `B`
+    B,
+    /// RPL user flags, `R`
+    R,
+}
+
+/// A line of source with its labels removed
+struct Statement<'a> {
+    line: usize,
+    source: &'a str,
+    mnemonic: String,
+    operands: Vec<&'a str>,
+}
+
+/// Assembles source code into ROM bytes, loadable with `Rom::from`.
+///
+/// Each line holds an instruction in the form printed by the disassembler
+/// (e.g. `LD V3, 0x1F`, `JP 0x2A4` or `DRW V1, V2, 5`), optionally preceded
+/// by `label:` definitions. Labels can be used in place of addresses and
+/// resolve to the address the next statement is loaded at, starting from
+/// the user space (0x200). Numbers are decimal, or hexadecimal and binary
+/// when prefixed by `0x` and `0b`. The `DB` directive emits the provided
+/// bytes as they are, and `;` starts a comment.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    let mut labels = HashMap::new();
+    let mut statements = Vec::new();
+    let mut addr = USER_SPACE_STR as u16;
+
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let mut text = text.split(';').next().unwrap_or_default().trim();
+
+        while let Some((label, rest)) = text.split_once(':') {
+            let label = label.trim();
+
+            if !is_identifier(label) {
+                break;
+            }
+
+            if labels.insert(label.to_string(), addr).is_some() {
+                return Err(AsmError::DuplicateLabel {
+                    line,
+                    label: label.to_string(),
+                });
+            }
+
+            text = rest.trim();
+        }
+
+        if text.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, operands) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let operands: Vec<&str> = operands
+            .split(',')
+            .map(str::trim)
+            .filter(|operand| !operand.is_empty())
+            .collect();
+        let statement = Statement {
+            line,
+            source: text,
+            mnemonic: mnemonic.to_ascii_uppercase(),
+            operands,
+        };
+
+        addr += if statement.mnemonic == "DB" {
+            statement.operands.len() as u16
+        } else {
+            2
+        };
+        statements.push(statement);
+    }
+
+    let mut bytes = Vec::new();
+
+    for statement in statements {
+        let operands = statement
+            .operands
+            .iter()
+            .map(|operand| parse_operand(operand, &labels, statement.line))
+            .collect::<Result<Vec<Operand>, AsmError>>()?;
+        let invalid_operands = || AsmError::InvalidOperands {
+            line: statement.line,
+            statement: statement.source.to_string(),
+        };
+
+        if statement.mnemonic == "DB" {
+            for operand in operands {
+                match operand {
+                    Operand::Value(byte) if byte <= 0xFF => bytes.push(byte as u8),
+                    _ => return Err(invalid_operands()),
+                }
+            }
+
+            continue;
+        }
+
+        if !MNEMONICS.contains(&statement.mnemonic.as_str()) {
+            return Err(AsmError::UnknownMnemonic {
+                line: statement.line,
+                mnemonic: statement.mnemonic,
+            });
+        }
+
+        let instr = instruction(&statement.mnemonic, &operands).ok_or_else(invalid_operands)?;
+        let opcode = encode(&instr).expect("Assembled instructions are known");
+
+        bytes.extend_from_slice(&opcode.to_be_bytes());
+    }
+
+    Ok(bytes)
+}
+
+/// Encodes an `Instruction` back into its 16-bit opcode. Returns `None` for
+/// `Instruction::Unknown` and `Instruction::LoadLongI`, which doesn't fit in
+/// a single opcode.
+pub fn encode(instr: &Instruction) -> Option<u16> {
+    let x = |vx: usize| (vx as u16 & 0xF) << 8;
+    let xy = |vx: usize, vy: usize| x(vx) | (vy as u16 & 0xF) << 4;
+
+    let opcode = match *instr {
+        Instruction::SysAddr(nnn) => nnn & 0x0FFF,
+        Instruction::ScrollDown(n) => 0x00C0 | (n as u16 & 0xF),
+        Instruction::Cls => 0x00E0,
+        Instruction::Ret => 0x00EE,
+        Instruction::ScrollRight => 0x00FB,
+        Instruction::ScrollLeft => 0x00FC,
+        Instruction::Exit => 0x00FD,
+        Instruction::LowRes => 0x00FE,
+        Instruction::HighRes => 0x00FF,
+        Instruction::Jump(nnn) => 0x1000 | (nnn & 0x0FFF),
+        Instruction::CallSubroutine(nnn) => 0x2000 | (nnn & 0x0FFF),
+        Instruction::CondEq(vx, kk) => 0x3000 | x(vx) | kk as u16,
+        Instruction::CondNotEq(vx, kk) => 0x4000 | x(vx) | kk as u16,
+        Instruction::CondEqVxVy(vx, vy) => 0x5000 | xy(vx, vy),
+        Instruction::ConstAssignVxToKk(vx, kk) => 0x6000 | x(vx) | kk as u16,
+        Instruction::ConstAddVxToKk(vx, kk) => 0x7000 | x(vx) | kk as u16,
+        Instruction::AssignVxToVy(vx, vy) => 0x8000 | xy(vx, vy),
+        Instruction::BitOpOr(vx, vy) => 0x8001 | xy(vx, vy),
+        Instruction::BitOpAnd(vx, vy) => 0x8002 | xy(vx, vy),
+        Instruction::BitOpXor(vx, vy) => 0x8003 | xy(vx, vy),
+        Instruction::MathAdd(vx, vy) => 0x8004 | xy(vx, vy),
+        Instruction::MathSub(vx, vy) => 0x8005 | xy(vx, vy),
+        Instruction::BitOpShr(vx, vy) => 0x8006 | xy(vx, vy),
+        Instruction::MathSubVyVx(vx, vy) => 0x8007 | xy(vx, vy),
+        Instruction::BitOpShl(vx, vy) => 0x800E | xy(vx, vy),
+        Instruction::CondVxNotEqVy(vx, vy) => 0x9000 | xy(vx, vy),
+        Instruction::Mem(nnn) => 0xA000 | (nnn & 0x0FFF),
+        Instruction::JumpPcV0(_, nnn) => 0xB000 | (nnn & 0x0FFF),
+        Instruction::Rand(vx, kk) => 0xC000 | x(vx) | kk as u16,
+        Instruction::Draw(vx, vy, n) => 0xD000 | xy(vx, vy) | (n as u16 & 0xF),
+        Instruction::SkipIfKeyPressed(vx) => 0xE09E | x(vx),
+        Instruction::KeyOpVxNotPressed(vx) => 0xE0A1 | x(vx),
+        Instruction::SetVxEqToDt(vx) => 0xF007 | x(vx),
+        Instruction::WaitKeyPressAndStoreOnVx(vx) => 0xF00A | x(vx),
+        Instruction::SetDtEqToVx(vx) => 0xF015 | x(vx),
+        Instruction::SetStEqToVx(vx) => 0xF018 | x(vx),
+        Instruction::SetIEqToIPlusVx(vx) => 0xF01E | x(vx),
+        Instruction::SetIEqToVx(vx) => 0xF029 | x(vx),
+        Instruction::SetIEqToLargeFont(vx) => 0xF030 | x(vx),
+        Instruction::StoreBinaryCodedDecimal(vx) => 0xF033 | x(vx),
+        Instruction::SetRegsInI(vx) => 0xF055 | x(vx),
+        Instruction::GetRegsInI(vx) => 0xF065 | x(vx),
+        Instruction::StoreFlags(vx) => 0xF075 | x(vx),
+        Instruction::LoadFlags(vx) => 0xF085 | x(vx),
+        Instruction::LoadAudioPattern => 0xF002,
+        Instruction::SetPitch(vx) => 0xF03A | x(vx),
+        Instruction::SelectPlanes(n) => 0xF001 | x(n as usize),
+        Instruction::LoadLongI(_) | Instruction::Unknown => return None,
+    };
+
+    Some(opcode)
+}
+
+/// Maps a mnemonic and its operands to the matching `Instruction`, if any
+fn instruction(mnemonic: &str, operands: &[Operand]) -> Option<Instruction> {
+    use Operand::*;
+
+    let addr = |nnn: u16| nnn <= 0x0FFF;
+    let byte = |kk: u16| kk <= 0xFF;
+    let nibble = |n: u16| n <= 0xF;
+
+    let instr = match (mnemonic, operands) {
+        ("CLS", []) => Instruction::Cls,
+        ("RET", []) => Instruction::Ret,
+        ("SCR", []) => Instruction::ScrollRight,
+        ("SCL", []) => Instruction::ScrollLeft,
+        ("EXIT", []) => Instruction::Exit,
+        ("LOW", []) => Instruction::LowRes,
+        ("HIGH", []) => Instruction::HighRes,
+        ("AUDIO", []) => Instruction::LoadAudioPattern,
+        ("SCD", [Value(n)]) if nibble(*n) => Instruction::ScrollDown(*n as u8),
+        ("SYS", [Value(nnn)]) if addr(*nnn) => Instruction::SysAddr(*nnn),
+        ("JP", [Value(nnn)]) if addr(*nnn) => Instruction::Jump(*nnn),
+        ("JP", [Register(0), Value(nnn)]) if addr(*nnn) => {
+            Instruction::JumpPcV0((*nnn >> 8) as usize, *nnn)
+        }
+        ("CALL", [Value(nnn)]) if addr(*nnn) => Instruction::CallSubroutine(*nnn),
+        ("SE", [Register(vx), Value(kk)]) if byte(*kk) => Instruction::CondEq(*vx, *kk as u8),
+        ("SE", [Register(vx), Register(vy)]) => Instruction::CondEqVxVy(*vx, *vy),
+        ("SNE", [Register(vx), Value(kk)]) if byte(*kk) => Instruction::CondNotEq(*vx, *kk as u8),
+        ("SNE", [Register(vx), Register(vy)]) => Instruction::CondVxNotEqVy(*vx, *vy),
+        ("LD", [Register(vx), Value(kk)]) if byte(*kk) => {
+            Instruction::ConstAssignVxToKk(*vx, *kk as u8)
+        }
+        ("LD", [Register(vx), Register(vy)]) => Instruction::AssignVxToVy(*vx, *vy),
+        ("LD", [I, Value(nnn)]) if addr(*nnn) => Instruction::Mem(*nnn),
+        ("LD", [Register(vx), Dt]) => Instruction::SetVxEqToDt(*vx),
+        ("LD", [Register(vx), K]) => Instruction::WaitKeyPressAndStoreOnVx(*vx),
+        ("LD", [Dt, Register(vx)]) => Instruction::SetDtEqToVx(*vx),
+        ("LD", [St, Register(vx)]) => Instruction::SetStEqToVx(*vx),
+        ("LD", [F, Register(vx)]) => Instruction::SetIEqToVx(*vx),
+        ("LD", [Hf, Register(vx)]) => Instruction::SetIEqToLargeFont(*vx),
+        ("LD", [B, Register(vx)]) => Instruction::StoreBinaryCodedDecimal(*vx),
+        ("LD", [IndirectI, Register(vx)]) => Instruction::SetRegsInI(*vx),
+        ("LD", [Register(vx), IndirectI]) => Instruction::GetRegsInI(*vx),
+        ("LD", [R, Register(vx)]) => Instruction::StoreFlags(*vx),
+        ("LD", [Register(vx), R]) => Instruction::LoadFlags(*vx),
+        ("ADD", [Register(vx), Value(kk)]) if byte(*kk) => {
+            Instruction::ConstAddVxToKk(*vx, *kk as u8)
+        }
+        ("ADD", [Register(vx), Register(vy)]) => Instruction::MathAdd(*vx, *vy),
+        ("ADD", [I, Register(vx)]) => Instruction::SetIEqToIPlusVx(*vx),
+        ("OR", [Register(vx), Register(vy)]) => Instruction::BitOpOr(*vx, *vy),
+        ("AND", [Register(vx), Register(vy)]) => Instruction::BitOpAnd(*vx, *vy),
+        ("XOR", [Register(vx), Register(vy)]) => Instruction::BitOpXor(*vx, *vy),
+        ("SUB", [Register(vx), Register(vy)]) => Instruction::MathSub(*vx, *vy),
+        ("SUBN", [Register(vx), Register(vy)]) => Instruction::MathSubVyVx(*vx, *vy),
+        ("SHR", [Register(vx)]) => Instruction::BitOpShr(*vx, 0),
+        ("SHR", [Register(vx), Register(vy)]) => Instruction::BitOpShr(*vx, *vy),
+        ("SHL", [Register(vx)]) => Instruction::BitOpShl(*vx, 0),
+        ("SHL", [Register(vx), Register(vy)]) => Instruction::BitOpShl(*vx, *vy),
+        ("RND", [Register(vx), Value(kk)]) if byte(*kk) => Instruction::Rand(*vx, *kk as u8),
+        ("DRW", [Register(vx), Register(vy), Value(n)]) if nibble(*n) => {
+            Instruction::Draw(*vx, *vy, *n as u8)
+        }
+        ("SKP", [Register(vx)]) => Instruction::SkipIfKeyPressed(*vx),
+        ("SKNP", [Register(vx)]) => Instruction::KeyOpVxNotPressed(*vx),
+        ("PITCH", [Register(vx)]) => Instruction::SetPitch(*vx),
+        ("PLANE", [Value(n)]) if *n <= 0x3 => Instruction::SelectPlanes(*n as u8),
+        _ => return None,
+    };
+
+    Some(instr)
+}
+
+/// Parses a single operand, resolving labels to their addresses
+fn parse_operand(
+    operand: &str,
+    labels: &HashMap<String, u16>,
+    line: usize,
+) -> Result<Operand, AsmError> {
+    let upper = operand.to_ascii_uppercase();
+    let parsed = match upper.as_str() {
+        "I" => Some(Operand::I),
+        "[I]" => Some(Operand::IndirectI),
+        "DT" => Some(Operand::Dt),
+        "ST" => Some(Operand::St),
+        "K" => Some(Operand::K),
+        "F" => Some(Operand::F),
+        "HF" => Some(Operand::Hf),
+        "B" => Some(Operand::B),
+        "R" => Some(Operand::R),
+        _ => match upper.strip_prefix('V') {
+            Some(vx) if vx.len() == 1 => usize::from_str_radix(vx, 16).ok().map(Operand::Register),
+            _ => parse_number(operand).map(Operand::Value),
+        },
+    };
+
+    if let Some(operand) = parsed {
+        return Ok(operand);
+    }
+
+    if let Some(addr) = labels.get(operand) {
+        return Ok(Operand::Value(*addr));
+    }
+
+    if is_identifier(operand) {
+        return Err(AsmError::UnknownLabel {
+            line,
+            label: operand.to_string(),
+        });
+    }
+
+    Err(AsmError::InvalidOperands {
+        line,
+        statement: operand.to_string(),
+    })
+}
+
+/// Parses a decimal number, or a hexadecimal or binary one when prefixed by
+/// `0x` or `0b`
+fn parse_number(value: &str) -> Option<u16> {
+    let lower = value.to_ascii_lowercase();
+
+    if let Some(hex) = lower.strip_prefix("0x") {
+        u16::from_str_radix(hex, 16).ok()
+    } else if let Some(bin) = lower.strip_prefix("0b") {
+        u16::from_str_radix(bin, 2).ok()
+    } else {
+        lower.parse().ok()
+    }
+}
+
+/// Whether `value` can be used as a label name
+fn is_identifier(value: &str) -> bool {
+    let mut chars = value.chars();
+
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::disassembler::disassemble;
+    use crate::opcode::{Instruction, Opcode};
+
+    use super::{assemble, encode, AsmError};
+
+    const PROGRAM: &str = "
+        ; Draws a smiley at the center of the screen
+        start:  CLS
+                LD V1, 28       ; X
+                LD V2, 0x0C     ; Y
+                LD I, smiley
+                DRW V1, V2, 4
+        loop:   JP loop
+        smiley: DB 0b00100100, 0x00, 0x81, 0x7E
+    ";
+
+    #[test]
+    fn assembles_program() {
+        assert_eq!(
+            assemble(PROGRAM).unwrap(),
+            vec![
+                0x00, 0xE0, 0x61, 0x1C, 0x62, 0x0C, 0xA2, 0x0C, 0xD1, 0x24, 0x12, 0x0A, 0x24, 0x00,
+                0x81, 0x7E,
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trips_through_disassembler() {
+        let bytes = assemble(PROGRAM).unwrap();
+        let listing = disassemble(&bytes[..12])
+            .into_iter()
+            .map(|(_, _, instr)| instr.to_string())
+            .collect::<Vec<String>>();
+
+        assert_eq!(
+            listing,
+            vec![
+                "CLS",
+                "LD V1, 0x1C",
+                "LD V2, 0x0C",
+                "LD I, 0x20C",
+                "DRW V1, V2, 4",
+                "JP 0x20A"
+            ]
+        );
+        assert_eq!(assemble(&listing.join("\n")).unwrap(), bytes[..12]);
+    }
+
+    #[test]
+    fn round_trips_shifts_reading_vy() {
+        let bytes = vec![0x81, 0x26, 0x8A, 0x3E];
+        let listing = disassemble(&bytes)
+            .into_iter()
+            .map(|(_, _, instr)| instr.to_string())
+            .collect::<Vec<String>>();
+
+        assert_eq!(listing, vec!["SHR V1, V2", "SHL VA, V3"]);
+        assert_eq!(assemble(&listing.join("\n")).unwrap(), bytes);
+        assert_eq!(assemble("SHR V1").unwrap(), vec![0x81, 0x06]);
+    }
+
+    #[test]
+    fn assembles_every_decoded_opcode() {
+        for hexa in 0..=u16::MAX {
+            let instr = Opcode::from(hexa).decode();
+
+            if matches!(instr, Instruction::Unknown) {
+                continue;
+            }
+
+            assert_eq!(encode(&instr), Some(hexa), "Encodes {:#06x}", hexa);
+            assert_eq!(
+                assemble(&instr.to_string()),
+                Ok(hexa.to_be_bytes().to_vec()),
+                "Assembles \"{}\"",
+                instr
+            );
+        }
+    }
+
+    #[test]
+    fn reports_errors_with_line_numbers() {
+        assert_eq!(
+            assemble("CLS\nMOV V1, V2"),
+            Err(AsmError::UnknownMnemonic {
+                line: 2,
+                mnemonic: "MOV".to_string()
+            })
+        );
+        assert_eq!(
+            assemble("LD V1, 0x100"),
+            Err(AsmError::InvalidOperands {
+                line: 1,
+                statement: "LD V1, 0x100".to_string()
+            })
+        );
+        assert_eq!(
+            assemble("JP end"),
+            Err(AsmError::UnknownLabel {
+                line: 1,
+                label: "end".to_string()
+            })
+        );
+        assert_eq!(
+            assemble("a: CLS\na: RET").unwrap_err().to_string(),
+            "Label \"a\" redefined on line 2"
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use log::warn;
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired, AudioStatus};
+use sdl2::Sdl;
+
+use super::AudioPattern;
+
+/// Plays the bits of an `AudioPattern` in a loop, a set bit outputs a high
+/// level and a clear bit a low level.
+pub struct PatternWave {
+    pattern: AudioPattern,
+    /// Position in bits within the pattern
+    position: f32,
+    /// Bits advanced per sample
+    step: f32,
+}
+
+impl PatternWave {
+    pub fn new(pattern: AudioPattern, sample_rate: i32) -> Self {
+        Self {
+            pattern,
+            position: 0.0,
+            step: pattern.playback_rate() / sample_rate as f32,
+        }
+    }
+
+    /// Level for the next sample, `true` when high
+    fn next_level(&mut self) -> bool {
+        let bit = self.position as usize;
+        let level = self.pattern.buffer[bit / 8] & (0x80 >> (bit % 8)) != 0;
+
+        self.position = (self.position + self.step) % 128.0;
+        level
+    }
+}
+
+/// Phase increment per sample for a wave of `tone` Hz played at
+/// `sample_rate` samples per second.
+pub fn phase_inc(tone: f32, sample_rate: i32) -> f32 {
+    tone / sample_rate as f32
+}
+
+/// A wave which amplitude alternates at a steady frequency.
+/// Useful for wwitching cirtuits with two-level logic (0/1).
+///
+/// When an XO-CHIP audio pattern is set, the pattern is played instead.
+pub struct SquareWave {
+    phase: f32,
+    phase_inc: f32,
+    volume: f32,
+    /// Samples per second of the playback device
+    sample_rate: i32,
+    pattern: Option<PatternWave>,
+}
+
+impl AudioCallback for SquareWave {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [Self::Channel]) {
+        if let Some(pattern) = self.pattern.as_mut() {
+            for x in out.iter_mut() {
+                *x = if pattern.next_level() {
+                    self.volume
+                } else {
+                    -self.volume
+                };
+            }
+
+            return;
+        }
+
+        for x in out.iter_mut() {
+            if self.phase <= 0.5 {
+                *x = self.volume;
+            } else {
+                *x = -self.volume;
+            }
+
+            self.phase = (self.phase + self.phase_inc) % 1.0;
+        }
+    }
+}
+
+pub struct Audio {
+    /// Playback device, `None` if it couldn't be opened and the beeper is
+    /// silent
+    device: Option<AudioDevice<SquareWave>>,
+}
+
+impl Audio {
+    /// Opens the playback device, `volume` goes from 0.0 (muted) to 1.0 and
+    /// `tone` is the beep frequency in Hz.
+    ///
+    /// Sound is optional, the beeper stays silent if there's no device to
+    /// play it on.
+    pub fn new(sdl: &Sdl, volume: f32, tone: f32) -> Self {
+        let spec = AudioSpecDesired {
+            freq: Some(44100),
+            channels: Some(1),
+            samples: None,
+        };
+        let device = sdl.audio().and_then(|subsystem| {
+            subsystem.open_playback(None, &spec, |spec| SquareWave {
+                phase: 0.0,
+                phase_inc: phase_inc(tone, spec.freq),
+                volume: volume.clamp(0.0, 1.0),
+                sample_rate: spec.freq,
+                pattern: None,
+            })
+        });
+
+        match device {
+            Ok(device) => Self {
+                device: Some(device),
+            },
+            Err(err) => {
+                warn!("Audio unavailable, running without sound: {}", err);
+                Self::silent()
+            }
+        }
+    }
+
+    /// Creates an `Audio` with no playback device, every operation does
+    /// nothing
+    pub fn silent() -> Self {
+        Self { device: None }
+    }
+
+    pub fn play(&self) {
+        if let Some(device) = self.device.as_ref() {
+            let status = device.status();
+
+            if status == AudioStatus::Stopped || status == AudioStatus::Paused {
+                device.resume();
+            }
+        }
+    }
+
+    pub fn stop(&self) {
+        if let Some(device) = self.device.as_ref() {
+            device.pause();
+        }
+    }
+
+    /// Sets the beeper volume, from 0.0 (muted) to 1.0
+    pub fn set_volume(&mut self, volume: f32) {
+        // Locking the device pauses the audio callback while its state is
+        // updated
+        if let Some(device) = self.device.as_mut() {
+            device.lock().volume = volume.clamp(0.0, 1.0);
+        }
+    }
+
+    /// Sets the beep frequency in Hz. `System` only sets it on startup,
+    /// from `--tone`, this is meant for embedders.
+    pub fn set_tone(&mut self, tone: f32) {
+        if let Some(device) = self.device.as_mut() {
+            let mut wave = device.lock();
+
+            wave.phase_inc = phase_inc(tone, wave.sample_rate);
+        }
+    }
+
+    /// Plays the XO-CHIP audio `pattern` instead of the square wave, or goes
+    /// back to the square wave if `None`.
+    pub fn set_pattern(&mut self, pattern: Option<AudioPattern>) {
+        if let Some(device) = self.device.as_mut() {
+            let mut wave = device.lock();
+
+            wave.pattern = pattern.map(|pattern| PatternWave::new(pattern, wave.sample_rate));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{phase_inc, Audio, PatternWave};
+    use crate::audio::{AudioPattern, DEFAULT_PITCH};
+
+    #[test]
+    fn converts_tone_to_phase_inc() {
+        assert_eq!(phase_inc(440.0, 44100), 440.0 / 44100.0);
+        assert_eq!(phase_inc(1000.0, 48000), 1.0 / 48.0);
+        assert_eq!(
+            phase_inc(50.0, 44100) * 44100.0,
+            50.0,
+            "Completes `tone` periods per second"
+        );
+    }
+
+    #[test]
+    fn plays_pattern_bits() {
+        let mut buffer = [0; 16];
+
+        buffer[0] = 0b1010_0000;
+        buffer[15] = 0b0000_0011;
+
+        let pattern = AudioPattern {
+            buffer,
+            pitch: DEFAULT_PITCH,
+        };
+        // One sample per bit when the sample rate matches the playback rate
+        let mut wave = PatternWave::new(pattern, 4000);
+        let levels: Vec<bool> = (0..256).map(|_| wave.next_level()).collect();
+        let bits: Vec<bool> = buffer
+            .iter()
+            .flat_map(|byte| (0..8).map(move |bit| byte & (0x80 >> bit) != 0))
+            .collect();
+
+        assert_eq!(pattern.playback_rate(), 4000.0);
+        assert_eq!(&levels[..128], bits.as_slice());
+        assert_eq!(&levels[128..], bits.as_slice(), "Pattern loops");
+    }
+
+    #[test]
+    fn silent_audio_ignores_playback() {
+        let mut audio = Audio::silent();
+
+        audio.set_volume(0.5);
+        audio.set_tone(880.0);
+        audio.set_pattern(Some(AudioPattern {
+            buffer: [0xFF; 16],
+            pitch: DEFAULT_PITCH,
+        }));
+        audio.play();
+        audio.stop();
+    }
+}
@@ -0,0 +1,45 @@
+#[cfg(feature = "native")]
+mod device;
+
+#[cfg(feature = "native")]
+pub use self::device::{phase_inc, Audio, PatternWave, SquareWave};
+
+/// Beeper volume used unless configured otherwise
+pub const DEFAULT_VOLUME: f32 = 0.2;
+
+/// Beeper tone frequency in Hz used unless configured otherwise
+pub const DEFAULT_TONE: f32 = 440.0;
+
+/// Pitch for the XO-CHIP audio pattern playing at 4000 bits per second
+pub const DEFAULT_PITCH: u8 = 64;
+
+/// XO-CHIP audio pattern, a 128 bit sample buffer played at a rate set by
+/// the pitch register.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AudioPattern {
+    pub buffer: [u8; 16],
+    pub pitch: u8,
+}
+
+impl AudioPattern {
+    /// Bits played per second
+    pub fn playback_rate(&self) -> f32 {
+        4000.0 * 2_f32.powf((self.pitch as f32 - DEFAULT_PITCH as f32) / 48.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AudioPattern;
+
+    #[test]
+    fn pitch_sets_playback_rate() {
+        let pattern = |pitch| AudioPattern {
+            buffer: [0; 16],
+            pitch,
+        };
+
+        assert_eq!(pattern(64 + 48).playback_rate(), 8000.0);
+        assert_eq!(pattern(64 - 48).playback_rate(), 2000.0);
+    }
+}
@@ -1,20 +1,699 @@
-use std::path::PathBuf;
+use log::LevelFilter;
+use sdl2::pixels::Color;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use structopt::clap;
 use structopt::StructOpt;
 
-#[derive(Debug, StructOpt, PartialEq, Eq)]
+use crate::audio::{DEFAULT_TONE, DEFAULT_VOLUME};
+use crate::cpu::{CLOCK_RATE, FRAME_RATE};
+use crate::display::terminal::Backend;
+use crate::display::Palette;
+use crate::keypad::{ControllerMap, KeyMap};
+use crate::memory::{FONT_LEN, MEMORY_SIZE, USER_SPACE_STR};
+use crate::opcode::Instruction;
+use crate::quirks::Chip8Variant;
+
+/// Command line interface, one subcommand per action
+// Parsed once on startup, the size of `Config` doesn't matter
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, StructOpt, PartialEq)]
 #[structopt(
     name = "chip8",
     author = "Esteban Borai <estebanborai@gmail.com>",
     about = "CHIP-8 Emulator"
 )]
+pub enum Cli {
+    /// Runs a ROM
+    Run(Config),
+    /// Prints the instructions in a ROM
+    Disasm {
+        /// ROM file to disassemble, `-` reads it from the standard input
+        #[structopt(parse(from_os_str))]
+        rom: PathBuf,
+    },
+    /// Assembles source code into a ROM
+    Asm {
+        /// Source file to assemble
+        #[structopt(parse(from_os_str))]
+        source: PathBuf,
+        /// ROM file to write
+        #[structopt(short = "o", long = "output", parse(from_os_str))]
+        output: PathBuf,
+    },
+    /// Prints facts about a ROM without running it
+    Info {
+        /// ROM file to inspect, `-` reads it from the standard input
+        #[structopt(parse(from_os_str))]
+        rom: PathBuf,
+        /// Address the ROM starts running from, in hex (e.g. 0x240)
+        /// [default: 0x200]
+        #[structopt(long = "start", parse(try_from_str = parse_start))]
+        start: Option<u16>,
+    },
+}
+
+/// First arguments handled by `Cli` itself, anything else is taken as the
+/// options of the `run` subcommand
+const CLI_COMMANDS: [&str; 9] = [
+    "run",
+    "disasm",
+    "asm",
+    "info",
+    "help",
+    "-h",
+    "--help",
+    "-V",
+    "--version",
+];
+
+impl Cli {
+    /// Parses `args` as `StructOpt::from_iter_safe` does, running the ROM
+    /// when no subcommand is given so `chip8 <rom>` keeps working.
+    pub fn parse<I, T>(args: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString>,
+    {
+        let mut args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+
+        if args
+            .get(1)
+            .is_some_and(|arg| !CLI_COMMANDS.iter().any(|command| arg == command))
+        {
+            args.insert(1, OsString::from("run"));
+        }
+
+        Self::from_iter_safe(args)
+    }
+}
+
+/// Options to run a ROM with
+#[derive(Debug, StructOpt, PartialEq)]
 pub struct Config {
-    /// ROM file to load
+    /// ROM file to load, `-` reads it from the standard input
     #[structopt(parse(from_os_str))]
     pub rom: PathBuf,
     /// Debug mode
     #[structopt(short = "d", long = "debug")]
     pub debug: bool,
-    /// Inspect instructions from ROM
-    #[structopt(short = "i", long = "inspect")]
-    pub inspect: bool,
+    /// Logs more details, -v for debug messages and -vv for every executed
+    /// instruction
+    #[structopt(short = "v", long = "verbose", parse(from_occurrences))]
+    pub verbose: u8,
+    /// Maximum number of sprite draws per frame, extra draws are deferred to
+    /// the next frame
+    #[structopt(long = "max-draws-per-frame")]
+    pub max_draws_per_frame: Option<u32>,
+    /// CPU clock rate in instructions per second [default: 600]
+    #[structopt(short = "c", long = "clock", parse(try_from_str = parse_clock))]
+    pub clock: Option<f32>,
+    /// Display refreshes per second, draws in between are rendered together
+    /// on the next refresh [default: 60]
+    #[structopt(long = "refresh-rate", parse(try_from_str = parse_refresh_rate))]
+    pub refresh_rate: Option<f32>,
+    /// CHIP-8 variant to emulate quirks for (chip8, schip, xochip), detected
+    /// from the ROM if not provided
+    #[structopt(long = "variant")]
+    pub variant: Option<Chip8Variant>,
+    /// Foreground (pixels on) color as RRGGBB or RGB hex [default: ffffff]
+    #[structopt(long = "fg", parse(try_from_str = parse_color))]
+    pub fg: Option<Color>,
+    /// Background (pixels off) color as RRGGBB or RGB hex [default: 000000]
+    #[structopt(long = "bg", parse(try_from_str = parse_color))]
+    pub bg: Option<Color>,
+    /// Color for XO-CHIP pixels set in the second plane only, as RRGGBB or
+    /// RGB hex [default: aaaaaa]
+    #[structopt(long = "fg2", parse(try_from_str = parse_color))]
+    pub fg2: Option<Color>,
+    /// Color for XO-CHIP pixels set in both planes, as RRGGBB or RGB hex
+    /// [default: 555555]
+    #[structopt(long = "blend", parse(try_from_str = parse_color))]
+    pub blend: Option<Color>,
+    /// Where to render the display (sdl, terminal), the terminal backend
+    /// doesn't create a window and reads keys from the terminal
+    /// [default: sdl]
+    #[structopt(long = "backend")]
+    pub backend: Option<Backend>,
+    /// Window scale, each CHIP-8 pixel takes `scale` by `scale` screen
+    /// pixels [default: 12, or smaller if the window doesn't fit the screen]
+    #[structopt(long = "scale", parse(try_from_str = parse_scale))]
+    pub scale: Option<u32>,
+    /// Starts in fullscreen mode, F11 switches back to windowed mode
+    #[structopt(long = "fullscreen")]
+    pub fullscreen: bool,
+    /// Phosphor decay factor (between 0 and 1) applied to cleared pixels on
+    /// each frame, makes pixels fade out instead of vanishing
+    #[structopt(long = "fade", parse(try_from_str = parse_decay))]
+    pub fade: Option<f32>,
+    /// Silence the beeper
+    #[structopt(long = "mute")]
+    pub mute: bool,
+    /// Beeper volume from 0.0 to 1.0, out of range values are clamped
+    /// [default: 0.2]
+    #[structopt(long = "volume", parse(try_from_str = parse_volume))]
+    pub volume: Option<f32>,
+    /// Beep tone frequency in Hz, from 50 to 8000 [default: 440]
+    #[structopt(long = "tone", parse(try_from_str = parse_tone))]
+    pub tone: Option<f32>,
+    /// Writes every executed instruction to the provided file
+    #[structopt(long = "trace", parse(from_os_str))]
+    pub trace: Option<PathBuf>,
+    /// Keyboard keys for the keypad, 16 characters or key names laid out as
+    /// the keypad rows (1 2 3 C, 4 5 6 D, 7 8 9 E, A 0 B F), or a file
+    /// holding them. Keys are taken by their position on a US QWERTY
+    /// keyboard, whatever the layout [default: 1234qwerasdfzxcv]
+    #[structopt(long = "keymap", parse(try_from_str = parse_keymap))]
+    pub keymap: Option<KeyMap>,
+    /// Game controller buttons for the keypad, 16 SDL button names (a, b,
+    /// start, dpup...) or `-` laid out as the keypad rows, or a file
+    /// holding them [default: d-pad for 2 4 6 8 and a for 5]
+    #[structopt(long = "controller-map", parse(try_from_str = parse_controller_map))]
+    pub controller_map: Option<ControllerMap>,
+    /// Cycles after a key press is read by `Fx0A` during which the
+    /// following reads ignore key presses, for keys that bounce [default: 0]
+    #[structopt(long = "key-debounce")]
+    pub key_debounce: Option<u64>,
+    /// Seed for the random number generator, makes runs reproducible
+    #[structopt(long = "seed")]
+    pub seed: Option<u64>,
+    /// Address to load the font sprites at, in hex (e.g. 0x50), for ROMs
+    /// expecting them outside of the default location [default: 0x000]
+    #[structopt(long = "font-base", alias = "entrypoint-fonts", parse(try_from_str = parse_font_base))]
+    pub font_base: Option<u16>,
+    /// Address to start running the ROM from, in hex (e.g. 0x240)
+    /// [default: 0x200]
+    #[structopt(long = "start", parse(try_from_str = parse_start))]
+    pub start: Option<u16>,
+    /// Waits for the next frame after drawing a sprite, as the COSMAC VIP
+    /// does, for ROMs timing their animations on it
+    #[structopt(long = "display-wait")]
+    pub display_wait: bool,
+    /// Stops the ROM on unknown opcodes instead of skipping them
+    #[structopt(long = "strict")]
+    pub strict: bool,
+    /// Comma separated mnemonics or opcode patterns of the instructions
+    /// (e.g. DRW,Fx55) to stop the ROM at instead of running them, a
+    /// mnemonic stands for every instruction named so
+    #[structopt(long = "deny", use_delimiter = true, parse(try_from_str = parse_instruction_kinds))]
+    pub deny: Vec<Vec<Instruction>>,
+    /// Records the keypad state on every cycle, along with the seed, to the
+    /// provided file
+    #[structopt(long = "record", parse(from_os_str), conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+    /// Replays the inputs recorded with `--record` instead of reading the
+    /// keyboard, stops once all of them are used
+    #[structopt(long = "replay", parse(from_os_str))]
+    pub replay: Option<PathBuf>,
+    /// Reloads and restarts the ROM whenever its file changes
+    #[structopt(long = "watch", conflicts_with_all = &["record", "replay"])]
+    pub watch: bool,
+}
+
+impl Config {
+    /// Maximum level of the log messages shown, depending on the verbosity
+    pub fn log_level(&self) -> LevelFilter {
+        match self.verbose {
+            0 => LevelFilter::Info,
+            1 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        }
+    }
+
+    /// CPU clock rate in instructions per second
+    pub fn clock_rate(&self) -> f32 {
+        self.clock.unwrap_or(CLOCK_RATE)
+    }
+
+    /// Display refreshes per second
+    pub fn refresh_rate(&self) -> f32 {
+        self.refresh_rate.unwrap_or(FRAME_RATE)
+    }
+
+    /// Beeper volume, zero when muted
+    pub fn volume(&self) -> f32 {
+        if self.mute {
+            return 0.0;
+        }
+
+        self.volume.unwrap_or(DEFAULT_VOLUME)
+    }
+
+    /// Beep tone frequency in Hz
+    pub fn tone(&self) -> f32 {
+        self.tone.unwrap_or(DEFAULT_TONE)
+    }
+
+    /// Colors for the display, the defaults replaced by the ones provided
+    pub fn palette(&self) -> Palette {
+        let Palette(defaults) = Palette::default();
+        let colors = [self.bg, self.fg, self.fg2, self.blend];
+
+        Palette(std::array::from_fn(|index| {
+            colors[index].unwrap_or(defaults[index])
+        }))
+    }
+
+    /// Keyboard keys mapped to the keypad
+    pub fn keymap(&self) -> KeyMap {
+        self.keymap.unwrap_or_default()
+    }
+
+    pub fn controller_map(&self) -> ControllerMap {
+        self.controller_map.unwrap_or_default()
+    }
+}
+
+fn parse_clock(value: &str) -> Result<f32, String> {
+    let clock = value.parse::<f32>().map_err(|err| err.to_string())?;
+
+    if !clock.is_finite() || clock <= 0.0 {
+        return Err(String::from("Clock rate must be a positive number"));
+    }
+
+    Ok(clock)
+}
+
+fn parse_refresh_rate(value: &str) -> Result<f32, String> {
+    let refresh_rate = value.parse::<f32>().map_err(|err| err.to_string())?;
+
+    // Rates close to 0 would make the refresh period overflow
+    if !refresh_rate.is_finite() || refresh_rate < 1.0 {
+        return Err(String::from("Refresh rate must be at least 1"));
+    }
+
+    Ok(refresh_rate)
+}
+
+fn parse_scale(value: &str) -> Result<u32, String> {
+    let scale = value.parse::<u32>().map_err(|err| err.to_string())?;
+
+    if scale == 0 {
+        return Err(String::from("Scale must be at least 1"));
+    }
+
+    Ok(scale)
+}
+
+fn parse_volume(value: &str) -> Result<f32, String> {
+    let volume = value.parse::<f32>().map_err(|err| err.to_string())?;
+
+    if volume.is_nan() {
+        return Err(String::from("Volume must be a number"));
+    }
+
+    Ok(volume.clamp(0.0, 1.0))
+}
+
+fn parse_tone(value: &str) -> Result<f32, String> {
+    let tone = value.parse::<f32>().map_err(|err| err.to_string())?;
+
+    if !(50.0..=8000.0).contains(&tone) {
+        return Err(String::from("Tone must be between 50 and 8000 Hz"));
+    }
+
+    Ok(tone)
+}
+
+fn parse_decay(value: &str) -> Result<f32, String> {
+    let decay = value.parse::<f32>().map_err(|err| err.to_string())?;
+
+    if !(decay > 0.0 && decay < 1.0) {
+        return Err(String::from("Decay factor must be between 0 and 1"));
+    }
+
+    Ok(decay)
+}
+
+/// Parses a start address in hex, optionally prefixed by `0x`
+fn parse_start(value: &str) -> Result<u16, String> {
+    let hex = value.strip_prefix("0x").unwrap_or(value);
+    let addr = u16::from_str_radix(hex, 16).map_err(|err| err.to_string())?;
+
+    if addr as usize >= MEMORY_SIZE {
+        return Err(format!("Start address must be below {:#06x}", MEMORY_SIZE));
+    }
+
+    if !addr.is_multiple_of(2) {
+        return Err(String::from("Start address must be even"));
+    }
+
+    Ok(addr)
+}
+
+/// Parses a font base address in hex, optionally prefixed by `0x`
+fn parse_font_base(value: &str) -> Result<u16, String> {
+    let hex = value.strip_prefix("0x").unwrap_or(value);
+    let addr = u16::from_str_radix(hex, 16).map_err(|err| err.to_string())?;
+
+    if addr as usize + FONT_LEN > USER_SPACE_STR {
+        return Err(format!(
+            "Fonts must fit below {:#06x}, the base address can't be past {:#06x}",
+            USER_SPACE_STR,
+            USER_SPACE_STR - FONT_LEN
+        ));
+    }
+
+    Ok(addr)
+}
+
+/// Parses a mnemonic (e.g. `DRW`) or the opcode pattern of a kind of
+/// instruction (e.g. `Fx55`), case insensitive, into an instruction of each
+/// kind named. Mnemonics name every kind sharing them.
+fn parse_instruction_kinds(value: &str) -> Result<Vec<Instruction>, String> {
+    let value = value.trim();
+
+    if let Some(instr) = Instruction::from_pattern(value) {
+        return Ok(vec![instr]);
+    }
+
+    Some(Instruction::kinds_with_mnemonic(value))
+        .filter(|kinds| !kinds.is_empty())
+        .ok_or_else(|| {
+            format!(
+                "Unknown instruction mnemonic or opcode pattern \"{}\"",
+                value
+            )
+        })
+}
+
+/// Parses a `KeyMap` from the file at `value` if there's one, from `value`
+/// itself otherwise.
+fn parse_keymap(value: &str) -> Result<KeyMap, String> {
+    parse_layout(value)
+}
+
+/// Parses a `ControllerMap` as `parse_keymap` does
+fn parse_controller_map(value: &str) -> Result<ControllerMap, String> {
+    parse_layout(value)
+}
+
+/// Parses a keypad layout from the file at `value` if there's one, from
+/// `value` itself otherwise.
+fn parse_layout<T: FromStr<Err = String>>(value: &str) -> Result<T, String> {
+    if Path::new(value).is_file() {
+        let layout = fs::read_to_string(value).map_err(|err| err.to_string())?;
+
+        return layout.parse();
+    }
+
+    value.parse()
+}
+
+/// Parses a hex color in the `RRGGBB` or `RGB` shorthand forms, optionally
+/// prefixed by `#`.
+fn parse_color(value: &str) -> Result<Color, String> {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    let invalid = || format!("Invalid color \"{}\", expected RRGGBB or RGB hex", value);
+
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).map_err(|_| invalid());
+
+    match hex.len() {
+        3 => {
+            let r = channel(&hex[0..1])?;
+            let g = channel(&hex[1..2])?;
+            let b = channel(&hex[2..3])?;
+
+            // Each digit is repeated, `f80` stands for `ff8800`
+            Ok(Color::RGB(r * 0x11, g * 0x11, b * 0x11))
+        }
+        6 => Ok(Color::RGB(
+            channel(&hex[0..2])?,
+            channel(&hex[2..4])?,
+            channel(&hex[4..6])?,
+        )),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::pixels::Color;
+
+    use std::fs;
+    use std::path::PathBuf;
+
+    use sdl2::keyboard::Scancode;
+    use structopt::StructOpt;
+
+    use super::{parse_clock, parse_color, parse_decay, parse_keymap, parse_scale, parse_tone};
+    use super::{
+        parse_font_base, parse_instruction_kinds, parse_refresh_rate, parse_start, parse_volume,
+    };
+    use super::{Cli, Config};
+    use crate::cpu::{Cpu, CpuError};
+    use crate::display::Palette;
+    use crate::keypad::KeypadState;
+    use crate::opcode::Instruction;
+
+    #[test]
+    fn parses_refresh_rate() {
+        assert_eq!(parse_refresh_rate("30"), Ok(30.0));
+        assert_eq!(parse_refresh_rate("1"), Ok(1.0));
+        assert!(parse_refresh_rate("0").is_err());
+        assert!(parse_refresh_rate("0.5").is_err());
+        assert!(parse_refresh_rate("1e-39").is_err());
+        assert!(parse_refresh_rate("inf").is_err());
+    }
+
+    #[test]
+    fn parses_clock_rate() {
+        assert_eq!(parse_clock("1000"), Ok(1000.0));
+        assert!(parse_clock("0").is_err());
+        assert!(parse_clock("-500").is_err());
+        assert!(parse_clock("fast").is_err());
+    }
+
+    #[test]
+    fn parses_scale() {
+        assert_eq!(parse_scale("1"), Ok(1));
+        assert_eq!(parse_scale("20"), Ok(20));
+        assert!(parse_scale("0").is_err());
+        assert!(parse_scale("-2").is_err());
+        assert!(parse_scale("big").is_err());
+    }
+
+    #[test]
+    fn clamps_volume() {
+        assert_eq!(parse_volume("0.5"), Ok(0.5));
+        assert_eq!(parse_volume("1.5"), Ok(1.0));
+        assert_eq!(parse_volume("-0.3"), Ok(0.0));
+        assert!(parse_volume("NaN").is_err());
+        assert!(parse_volume("loud").is_err());
+    }
+
+    #[test]
+    fn parses_start_address() {
+        assert_eq!(parse_start("0x240"), Ok(0x240));
+        assert_eq!(parse_start("2a0"), Ok(0x2A0));
+        assert!(parse_start("0x241").is_err());
+        assert!(parse_start("0x1000").is_err());
+        assert!(parse_start("start").is_err());
+    }
+
+    #[test]
+    fn parses_font_base() {
+        assert_eq!(parse_font_base("0x50"), Ok(0x50));
+        assert_eq!(parse_font_base("110"), Ok(0x110));
+        assert!(parse_font_base("0x111").is_err(), "Overlaps the user space");
+        assert!(parse_font_base("fonts").is_err());
+    }
+
+    #[test]
+    fn parses_tone() {
+        assert_eq!(parse_tone("220"), Ok(220.0));
+        assert!(parse_tone("20").is_err());
+        assert!(parse_tone("12000").is_err());
+    }
+
+    #[test]
+    fn parses_decay_factor() {
+        assert_eq!(parse_decay("0.6"), Ok(0.6));
+        assert!(parse_decay("0").is_err());
+        assert!(parse_decay("1").is_err());
+        assert!(parse_decay("NaN").is_err());
+    }
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!(parse_color("ffb000"), Ok(Color::RGB(0xFF, 0xB0, 0x00)));
+        assert_eq!(parse_color("#33FF66"), Ok(Color::RGB(0x33, 0xFF, 0x66)));
+        assert_eq!(parse_color("f80"), Ok(Color::RGB(0xFF, 0x88, 0x00)));
+        assert!(parse_color("").is_err());
+        assert!(parse_color("ff00").is_err());
+        assert!(parse_color("gg0000").is_err());
+        assert!(parse_color("+f+f+f").is_err());
+    }
+
+    #[test]
+    fn parses_denied_instruction_kinds() {
+        assert_eq!(
+            parse_instruction_kinds("Dxyn"),
+            Ok(vec![Instruction::Draw(0, 0, 0)])
+        );
+        assert_eq!(
+            parse_instruction_kinds("fx55"),
+            Ok(vec![Instruction::SetRegsInI(0)])
+        );
+        assert_eq!(
+            parse_instruction_kinds("JP"),
+            Ok(vec![Instruction::Jump(0), Instruction::JumpPcV0(0, 0)])
+        );
+        assert_eq!(
+            parse_instruction_kinds("ld").map(|kinds| kinds.len()),
+            Ok(15),
+            "Every kind named LD"
+        );
+        assert!(parse_instruction_kinds("Fx56").is_err());
+        assert!(parse_instruction_kinds("MOV").is_err());
+
+        match Cli::from_iter_safe(["chip8", "run", "roms/PONG", "--deny", "dxyn,Fx55"]).unwrap() {
+            Cli::Run(config) => assert_eq!(
+                config.deny,
+                vec![
+                    vec![Instruction::Draw(0, 0, 0)],
+                    vec![Instruction::SetRegsInI(0)]
+                ]
+            ),
+            cli => panic!("Expected the run subcommand, found {:?}", cli),
+        }
+    }
+
+    #[test]
+    fn denied_mnemonics_stop_the_rom() {
+        let config = match Cli::parse(["chip8", "roms/PONG", "--deny", "DRW"]).unwrap() {
+            Cli::Run(config) => config,
+            cli => panic!("Expected the run subcommand, found {:?}", cli),
+        };
+        let mut cpu = Cpu::new();
+
+        for instr in config.deny.iter().flatten() {
+            cpu.deny(instr);
+        }
+
+        // 0x200: LD V0, 1
+        // 0x202: DRW V0, V0, 5
+        cpu.load(vec![0x60, 0x01, 0xD0, 0x05].into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        assert_eq!(
+            cpu.cycle(KeypadState::default()).unwrap_err(),
+            CpuError::Denied {
+                addr: 0x202,
+                raw: 0xD005,
+                mnemonic: "DRW"
+            }
+        );
+        assert!(cpu.is_halted());
+    }
+
+    #[test]
+    fn parses_keymap_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("custom.keymap");
+
+        fs::write(&path, "1 2 3 4\n' , . p\na o e u\n; q j k\n").unwrap();
+
+        let keymap = parse_keymap(path.to_str().unwrap()).unwrap();
+
+        assert_eq!(keymap.key_for(Scancode::Apostrophe), Some(0x4));
+        assert_eq!(keymap.key_for(Scancode::Semicolon), Some(0xA));
+        assert_eq!(parse_keymap("1234qwerasdfzxcv"), Ok(Default::default()));
+    }
+
+    #[test]
+    fn palette_overrides_default_colors() {
+        let config =
+            match Cli::from_iter_safe(["chip8", "run", "rom", "--fg", "f80", "--blend", "123"]) {
+                Ok(Cli::Run(config)) => config,
+                cli => panic!("Expected the run subcommand, found {:?}", cli),
+            };
+        let Palette(colors) = config.palette();
+        let Palette(defaults) = Palette::default();
+
+        assert_eq!(colors[0], defaults[0]);
+        assert_eq!(colors[1], Color::RGB(0xFF, 0x88, 0x00));
+        assert_eq!(colors[2], defaults[2]);
+        assert_eq!(colors[3], Color::RGB(0x11, 0x22, 0x33));
+    }
+
+    #[test]
+    fn parses_subcommands() {
+        let parse = |args: &[&str]| Cli::from_iter_safe(args).unwrap();
+
+        match parse(&["chip8", "run", "roms/PONG", "--clock", "1000", "-d"]) {
+            Cli::Run(Config {
+                rom,
+                debug,
+                clock,
+                strict,
+                verbose,
+                ..
+            }) => {
+                assert_eq!(rom, PathBuf::from("roms/PONG"));
+                assert!(debug);
+                assert_eq!(clock, Some(1000.0));
+                assert!(!strict);
+                assert_eq!(verbose, 0);
+            }
+            cli => panic!("Expected the run subcommand, found {:?}", cli),
+        }
+
+        assert_eq!(
+            parse(&["chip8", "disasm", "roms/PONG"]),
+            Cli::Disasm {
+                rom: PathBuf::from("roms/PONG")
+            }
+        );
+        assert_eq!(
+            parse(&["chip8", "asm", "pong.asm", "-o", "roms/PONG"]),
+            Cli::Asm {
+                source: PathBuf::from("pong.asm"),
+                output: PathBuf::from("roms/PONG"),
+            }
+        );
+        assert_eq!(
+            parse(&["chip8", "info", "roms/PONG"]),
+            Cli::Info {
+                rom: PathBuf::from("roms/PONG"),
+                start: None,
+            }
+        );
+        assert_eq!(
+            parse(&["chip8", "info", "roms/PONG", "--start", "0x240"]),
+            Cli::Info {
+                rom: PathBuf::from("roms/PONG"),
+                start: Some(0x240),
+            }
+        );
+        assert!(Cli::from_iter_safe(["chip8", "asm", "pong.asm"]).is_err());
+    }
+
+    #[test]
+    fn runs_roms_without_subcommand() {
+        let run = |args: &[&str]| match Cli::parse(args).unwrap() {
+            Cli::Run(config) => config,
+            cli => panic!("Expected the run subcommand, found {:?}", cli),
+        };
+
+        assert_eq!(run(&["chip8", "roms/PONG"]).rom, PathBuf::from("roms/PONG"));
+        assert_eq!(
+            run(&["chip8", "-v", "roms/PONG", "--clock", "1000"]),
+            run(&["chip8", "run", "-v", "roms/PONG", "--clock", "1000"])
+        );
+        assert_eq!(
+            Cli::parse(["chip8", "info", "roms/PONG"]).unwrap(),
+            Cli::Info {
+                rom: PathBuf::from("roms/PONG"),
+                start: None,
+            }
+        );
+        assert!(Cli::parse(["chip8"]).is_err());
+    }
 }
@@ -0,0 +1,101 @@
+use crate::cpu::{Cpu, CpuError, LoadError};
+use crate::keypad::KeypadState;
+use crate::quirks::Quirks;
+use crate::rom::Rom;
+
+/// Emulator core with no SDL dependency, to be driven by another frontend
+/// such as a WebAssembly build exported with `wasm-bindgen`.
+///
+/// The frontend runs `cycle` `CLOCK_RATE` times per second and `vblank`
+/// `FRAME_RATE` times per second, drawing `frame` after each vertical
+/// blank. Only primitive types cross the API.
+pub struct Core {
+    cpu: Cpu,
+}
+
+impl Core {
+    /// Creates a `Core` emulating the provided `quirks`, with no ROM loaded
+    pub fn new(quirks: Quirks) -> Self {
+        let mut cpu = Cpu::new();
+
+        cpu.quirks = quirks;
+
+        Self { cpu }
+    }
+
+    /// Loads the `rom` bytes and starts running them from a clean state
+    pub fn load(&mut self, rom: &[u8]) -> Result<(), LoadError> {
+        self.cpu.reload(Rom::from(rom.to_vec()))
+    }
+
+    /// Runs a CPU cycle with `keys` held down, indexed by COSMAC VIP key
+    pub fn cycle(&mut self, keys: [bool; 16]) -> Result<(), CpuError> {
+        self.cpu.cycle(KeypadState::from(keys)).map(|_| ())
+    }
+
+    /// Signals the vertical blank, ticking the timers
+    pub fn vblank(&mut self) {
+        self.cpu.vblank();
+    }
+
+    /// Pixels of the display output row by row, `width` by `height` cells
+    /// with a bit per XO-CHIP bitplane
+    pub fn frame(&self) -> &[u8] {
+        self.cpu.display_buffer.pixels()
+    }
+
+    /// Width of the display output in pixels
+    pub fn width(&self) -> u32 {
+        self.cpu.display_buffer.width()
+    }
+
+    /// Height of the display output in pixels
+    pub fn height(&self) -> u32 {
+        self.cpu.display_buffer.height()
+    }
+
+    /// Whether the beeper is sounding
+    pub fn beeping(&self) -> bool {
+        self.cpu.st > 0
+    }
+}
+
+impl Default for Core {
+    fn default() -> Self {
+        Self::new(Quirks::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Core;
+
+    #[test]
+    fn runs_roms_and_exposes_the_frame() {
+        let mut core = Core::default();
+
+        // Draws the font digit for the key pressed, then waits
+        core.load(&[0xF0, 0x0A, 0xF0, 0x29, 0xD1, 0x15, 0x12, 0x06])
+            .unwrap();
+
+        let mut keys = [false; 16];
+
+        core.cycle(keys).unwrap();
+        keys[0x1] = true;
+
+        for _ in 0..4 {
+            core.cycle(keys).unwrap();
+        }
+
+        core.vblank();
+
+        let frame = core.frame();
+
+        assert_eq!((core.width(), core.height()), (64, 32));
+        assert_eq!(frame.len(), 64 * 32);
+        // Top row of the digit 1 is 0x20
+        assert_eq!(&frame[..8], [0, 0, 1, 0, 0, 0, 0, 0]);
+        assert!(!core.beeping());
+        assert!(core.load(&[0; 0x1000]).is_err(), "ROM doesn't fit");
+    }
+}
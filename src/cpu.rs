@@ -1,16 +1,167 @@
-use rand::random;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::mem::{self, Discriminant};
 
+use log::{trace, warn};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::audio::{AudioPattern, DEFAULT_PITCH};
+use crate::debugger::{Watch, WatchHit};
 use crate::display::buffer::DisplayBuffer;
-use crate::display::{SCREEN_HEIGHT, SCREEN_WIDTH};
 use crate::keypad::KeypadState;
-use crate::memory::{Memory, USER_SPACE_STR};
+use crate::memory::{Memory, MemoryError};
 use crate::opcode::{Instruction, Opcode};
-use crate::register_set::RegisterSet;
+use crate::quirks::Quirks;
+use crate::register_set::{RegisterSet, REGISTER_COUNT};
 use crate::rom::Rom;
-use crate::stack::Stack;
+use crate::stack::{Stack, StackError};
+use crate::state::{RewindBuffer, SaveState};
+use crate::trace::Tracer;
 
 pub const CLOCK_RATE: f32 = 600.0;
 
+/// Frames per second, the rate at which `Cpu::vblank` is expected to be
+/// signaled.
+pub const FRAME_RATE: f32 = 60.0;
+
+/// Mask of the 12-bit address space jump targets wrap around in, see
+/// `Quirks::wrap_jumps`
+const ADDRESS_MASK: u16 = 0x0FFF;
+
+/// XO-CHIP bitplanes selected on start, the first one only
+pub const DEFAULT_PLANES: u8 = 0b01;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CpuError {
+    /// An opcode that doesn't match any instruction was fetched while
+    /// running in strict mode.
+    UnknownOpcode {
+        /// Memory address the opcode was fetched from
+        addr: u16,
+        /// Raw opcode value
+        raw: u16,
+        /// Return addresses in the call stack when the opcode was fetched,
+        /// from the outermost to the innermost call.
+        frames: Vec<u16>,
+    },
+    /// A machine code routine call (`0nnn`) was found with the
+    /// `trap_sys_addr` quirk enabled.
+    SysAddr {
+        /// Memory address of the instruction
+        addr: u16,
+        /// Address of the machine code routine
+        target: u16,
+    },
+    /// The PC points past the last complete opcode in memory
+    PcOutOfBounds {
+        /// Address the PC points to
+        addr: u16,
+    },
+    /// The PC points to an odd address while the `trap_misaligned_pc` quirk
+    /// is enabled, usually due to a bad jump.
+    MisalignedPc {
+        /// Address the PC points to
+        addr: u16,
+    },
+    /// A subroutine call or return overflowed or underflowed the stack
+    Stack {
+        /// Memory address of the instruction
+        addr: u16,
+        error: StackError,
+    },
+    /// An instruction accessed memory out of bounds
+    Memory {
+        /// Memory address of the instruction
+        addr: u16,
+        error: MemoryError,
+    },
+    /// An instruction denied with `Cpu::deny` was fetched
+    Denied {
+        /// Memory address the instruction was fetched from
+        addr: u16,
+        /// Raw opcode value
+        raw: u16,
+        mnemonic: &'static str,
+    },
+}
+
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CpuError::UnknownOpcode { addr, raw, frames } => {
+                write!(f, "Unknown opcode {:#06x} at {:#06x}", raw, addr)?;
+
+                for frame in frames.iter().rev() {
+                    write!(f, "\n    called from {:#06x}", frame)?;
+                }
+
+                Ok(())
+            }
+            CpuError::SysAddr { addr, target } => write!(
+                f,
+                "Machine code routine call to {:#05x} at {:#06x}",
+                target, addr
+            ),
+            CpuError::PcOutOfBounds { addr } => {
+                write!(f, "Program counter out of memory at {:#06x}", addr)
+            }
+            CpuError::MisalignedPc { addr } => {
+                write!(f, "Misaligned program counter at {:#06x}", addr)
+            }
+            CpuError::Stack { addr, error } => write!(f, "{} at {:#06x}", error, addr),
+            CpuError::Memory { addr, error } => write!(f, "{} at {:#06x}", error, addr),
+            CpuError::Denied {
+                addr,
+                raw,
+                mnemonic,
+            } => write!(
+                f,
+                "Denied instruction {} ({:#06x}) at {:#06x}",
+                mnemonic, raw, addr
+            ),
+        }
+    }
+}
+
+impl Error for CpuError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CpuError::Stack { error, .. } => Some(error),
+            CpuError::Memory { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// A ROM couldn't be loaded into memory
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoadError {
+    /// The ROM is larger than the memory from the entry point onwards
+    TooLarge {
+        /// ROM size in bytes
+        size: usize,
+        /// Bytes available from the entry point to the end of memory
+        available: usize,
+    },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::TooLarge { size, available } => write!(
+                f,
+                "ROM of {} bytes doesn't fit in memory, {} bytes available",
+                size, available
+            ),
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+#[derive(Debug)]
 pub struct CycleOutput {
     pub beep: bool,
     pub display_buffer: DisplayBuffer,
@@ -20,18 +171,28 @@ pub struct CycleOutput {
 pub struct Cpu {
     /// System available memory.
     pub(crate) ram: Memory,
+    /// Loaded ROM, reloaded into memory on `reset`
+    pub(crate) rom: Rom,
     /// Program Counter
     pub(crate) pc: u16,
     /// Index reigster
     pub(crate) i: u16,
-    /// Stack of 16 8-bit spaces
+    /// Stack of 16 return addresses, keeps track of the Stack Pointer
     pub(crate) stack: Stack,
-    /// Stack Pointer
-    pub(crate) sp: u16,
     /// General Purpose Variable Registers
     ///
     /// 16 8-bit variable registers numbered from 0 through F.
     pub(crate) registers: RegisterSet,
+    /// RPL user flags, persistent storage for registers saved with `Fx75`
+    pub(crate) flags: [u8; 16],
+    /// XO-CHIP audio pattern buffer, loaded with `F002`
+    pub(crate) audio_buffer: Option<[u8; 16]>,
+    /// XO-CHIP audio pattern pitch, set with `Fx3A`
+    pub(crate) pitch: u8,
+    /// XO-CHIP bitplanes affected by drawing and clearing, selected with
+    /// `Fn01`. A bit mask, bit 0 for the first plane and bit 1 for the
+    /// second one.
+    pub(crate) planes: u8,
     /// Delay Timer (DT)
     pub(crate) dt: u8,
     /// Sound Time (ST)
@@ -42,6 +203,54 @@ pub struct Cpu {
     pub(crate) keypad_state: KeypadState,
     /// Stores the a key to expect the user to input if `Some`
     pub(crate) keypad_await: Option<usize>,
+    /// Keys held down when `Fx0A` started waiting. These don't satisfy the
+    /// wait until released and pressed again.
+    pub(crate) keypad_await_held: KeypadState,
+    /// Key pressed while waiting on `Fx0A`, stored once released when the
+    /// `wait_for_key_release` quirk is enabled
+    pub(crate) keypad_await_pressed: Option<usize>,
+    /// Cycles after resolving an `Fx0A` wait during which key presses are
+    /// ignored by the following waits, filters out bouncing keys
+    pub(crate) key_debounce: u64,
+    /// Cycle count until which key presses are ignored by `Fx0A`
+    pub(crate) keypad_debounce_until: u64,
+    /// Maximum number of `Draw` instructions executed per frame, extra draws
+    /// are deferred to the next frame. Unlimited if `None`.
+    pub(crate) max_draws_per_frame: Option<u32>,
+    /// `Draw` instructions executed since the last `vblank`
+    pub(crate) draws_in_frame: u32,
+    /// Coordinates of the pixels turned off by `Draw` since the last
+    /// `take_collisions`. Not recorded if `None`.
+    pub(crate) collisions: Option<Vec<(u32, u32)>>,
+    /// Cycles between timer decrements when the timers are locked to the
+    /// cycle count, decremented on `vblank` if `None`
+    pub(crate) cycles_per_timer_tick: Option<u64>,
+    /// Cycles run since the CPU was created or reset
+    pub(crate) cycles_executed: u64,
+    /// Set once the program exits with `00FD` or a cycle fails, cycles do
+    /// nothing until `resume`d
+    pub(crate) halted: bool,
+    /// Address the PC starts from when created or reset, the memory's entry
+    /// point unless set with `Cpu::with_start`
+    pub(crate) start: u16,
+    /// Mnemonics of the instructions to fail with `CpuError::Denied` instead
+    /// of running them
+    pub(crate) denied: HashSet<Discriminant<Instruction>>,
+    /// Registers and memory addresses whose writes are recorded as
+    /// `WatchHit`s
+    pub(crate) watchpoints: HashSet<Watch>,
+    /// Writes to watched locations since the last `take_watch_hits`
+    pub(crate) watch_hits: Vec<WatchHit>,
+    /// Interpreter behaviors to emulate
+    pub(crate) quirks: Quirks,
+    /// Receives every executed instruction if `Some`
+    pub(crate) tracer: Option<Box<dyn Tracer>>,
+    /// States taken before each cycle, to step back with `rewind`. Disabled
+    /// if `None`.
+    pub(crate) rewind_buffer: Option<RewindBuffer>,
+    /// Random number generator for `Cxkk`, seeded with `Cpu::with_seed` for
+    /// reproducible runs
+    pub(crate) rng: StdRng,
 }
 
 impl Default for Cpu {
@@ -55,106 +264,610 @@ impl Cpu {
     /// (fonts loaded), an empty stack and Program Counter (PC) pointing
     /// to memory's user space (0x200).
     pub fn new() -> Self {
+        Self::with_memory(Memory::default())
+    }
+
+    /// Initializes a CPU as `Cpu::new` does, with the provided memory layout.
+    /// The Program Counter (PC) points to the memory's entry point.
+    pub fn with_memory(ram: Memory) -> Self {
         Self {
-            ram: Memory::default(),
-            pc: USER_SPACE_STR as u16,
+            pc: ram.entry_point() as u16,
+            start: ram.entry_point() as u16,
+            ram,
+            rom: Rom::from(Vec::new()),
             registers: RegisterSet::default(),
             i: 0x0000,
             stack: Stack::default(),
-            sp: 0,
+            flags: [0; 16],
+            audio_buffer: None,
+            pitch: DEFAULT_PITCH,
+            planes: DEFAULT_PLANES,
             dt: 0,
             st: 0,
             display_buffer: DisplayBuffer::default(),
             keypad_state: KeypadState::default(),
             keypad_await: None,
+            keypad_await_held: KeypadState::default(),
+            keypad_await_pressed: None,
+            key_debounce: 0,
+            keypad_debounce_until: 0,
+            max_draws_per_frame: None,
+            draws_in_frame: 0,
+            collisions: None,
+            cycles_per_timer_tick: None,
+            cycles_executed: 0,
+            halted: false,
+            denied: HashSet::new(),
+            watchpoints: HashSet::new(),
+            watch_hits: Vec::new(),
+            quirks: Quirks::default(),
+            tracer: None,
+            rewind_buffer: None,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Initializes a CPU as `Cpu::new` does, with the random number
+    /// generator seeded from `seed`. CPUs created with the same seed produce
+    /// the same random numbers.
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            ..Self::new()
+        }
+    }
+
+    /// Initializes a CPU as `Cpu::new` does, with the Program Counter (PC)
+    /// pointing to `addr` instead of the user space. ROMs are still loaded
+    /// at the user space.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `addr` is odd or out of memory.
+    pub fn with_start(addr: u16) -> Self {
+        let mut cpu = Self::new();
+
+        cpu.set_start(addr);
+        cpu
+    }
+
+    /// Sets the address the PC starts from, see `Cpu::with_start`
+    pub(crate) fn set_start(&mut self, addr: u16) {
+        assert!(
+            addr.is_multiple_of(2) && (addr as usize) < self.ram.size(),
+            "Start address {:#06x} must be even and within memory",
+            addr
+        );
+
+        self.start = addr;
+        self.pc = addr;
+    }
+
+    /// Keeps the state before each of the last `capacity` cycles so these
+    /// can be undone with `rewind`
+    pub fn enable_rewind(&mut self, capacity: usize) {
+        self.rewind_buffer = Some(RewindBuffer::new(capacity));
+    }
+
+    /// Restores the state from before the last cycle. Returns `false` if
+    /// there's no state to go back to.
+    pub fn rewind(&mut self) -> bool {
+        match self.rewind_buffer.as_mut().and_then(RewindBuffer::pop) {
+            Some(state) => {
+                self.load_state(state);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Starts or stops recording the coordinates of the pixels turned off
+    /// by `Draw`, to be retrieved with `take_collisions`
+    pub fn record_collisions(&mut self, enabled: bool) {
+        self.collisions = enabled.then(Vec::new);
+    }
+
+    /// Removes and returns the (x, y) coordinates of the pixels turned off
+    /// since the last call, empty unless recording collisions
+    pub fn take_collisions(&mut self) -> Vec<(u32, u32)> {
+        self.collisions
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Decrements the delay and sound timers every `cycles` cycles instead
+    /// of on `vblank`, so their values only depend on the cycles run and
+    /// not on the wall-clock time. Meant for reproducible tests, `cycles`
+    /// is `CLOCK_RATE / FRAME_RATE` to match the real timing.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cycles` is 0.
+    pub fn lock_timers_to_cycles(&mut self, cycles: u64) {
+        assert!(cycles > 0, "Timers must tick every 1 or more cycles");
+        self.cycles_per_timer_tick = Some(cycles);
+    }
+
+    /// Denies the instructions of the same kind as `instr` whatever their
+    /// operands (e.g. every `Fx55` but not the other `LD` forms), these fail
+    /// with `CpuError::Denied` instead of running. Allows running untrusted
+    /// ROMs, stopping them if they use instructions not trusted.
+    pub fn deny(&mut self, instr: &Instruction) {
+        self.denied.insert(mem::discriminant(instr));
+    }
+
+    /// Records writes to `watch` as `WatchHit`s, to be retrieved with
+    /// `take_watch_hits` after each cycle
+    ///
+    /// # Panics
+    ///
+    /// Panics if `watch` is a register past VF.
+    pub fn watch(&mut self, watch: Watch) {
+        if let Watch::Register(vx) = watch {
+            assert!(vx < REGISTER_COUNT, "Invalid register V{:X}", vx);
         }
+
+        self.watchpoints.insert(watch);
+    }
+
+    /// Stops recording writes to `watch`. Returns `false` if it wasn't
+    /// watched.
+    pub fn unwatch(&mut self, watch: Watch) -> bool {
+        self.watchpoints.remove(&watch)
     }
 
-    /// Loads ROM bytes into memory
-    pub fn load(&mut self, rom: Rom) {
-        self.ram.load(rom.bytes());
+    /// Removes and returns the writes to watched locations recorded so far
+    pub fn take_watch_hits(&mut self) -> Vec<WatchHit> {
+        std::mem::take(&mut self.watch_hits)
+    }
+
+    /// Installs a `Tracer` to be called on every executed instruction,
+    /// replacing the current one
+    pub fn set_tracer(&mut self, tracer: impl Tracer + 'static) {
+        self.tracer = Some(Box::new(tracer));
+    }
+
+    /// Removes and returns the installed `Tracer`, if any
+    pub fn take_tracer(&mut self) -> Option<Box<dyn Tracer>> {
+        self.tracer.take()
+    }
+
+    /// Loads ROM bytes into memory at the entry point. Memory is left
+    /// untouched if the ROM doesn't fit.
+    pub fn load(&mut self, rom: Rom) -> Result<(), LoadError> {
+        self.ram
+            .load(rom.bytes())
+            .map_err(|_| LoadError::TooLarge {
+                size: rom.bytes().len(),
+                available: self.ram.size() - self.ram.entry_point(),
+            })?;
+        self.rom = rom;
+
+        Ok(())
+    }
+
+    /// Replaces the loaded ROM with `rom` and restarts it, see `Cpu::reset`.
+    /// Nothing changes if the ROM doesn't fit.
+    pub fn reload(&mut self, rom: Rom) -> Result<(), LoadError> {
+        self.load(rom)?;
+        self.reset();
+
+        Ok(())
+    }
+
+    /// Restarts the loaded ROM.
+    ///
+    /// Memory is restored to its initial layout with the fonts and the ROM
+    /// loaded, registers, stack, timers, display and keypad state are
+    /// cleared. The RPL flags persist, as well as the CPU settings.
+    pub fn reset(&mut self) {
+        self.ram.clear();
+        self.ram
+            .load(self.rom.bytes())
+            .expect("ROM doesn't fit in memory");
+        self.pc = self.start;
+        self.i = 0x0000;
+        self.stack = Stack::default();
+        self.registers = RegisterSet::default();
+        self.audio_buffer = None;
+        self.pitch = DEFAULT_PITCH;
+        self.planes = DEFAULT_PLANES;
+        self.dt = 0;
+        self.st = 0;
+        self.display_buffer = DisplayBuffer::default();
+        self.keypad_state = KeypadState::default();
+        self.keypad_await = None;
+        self.keypad_await_held = KeypadState::default();
+        self.keypad_await_pressed = None;
+        self.draws_in_frame = 0;
+        self.cycles_executed = 0;
+        self.keypad_debounce_until = 0;
+        self.halted = false;
+    }
+
+    /// Cycles run since the CPU was created or reset
+    pub fn cycle_count(&self) -> u64 {
+        self.cycles_executed
+    }
+
+    /// Whether the program exited or a cycle failed, see `Cpu::cycle`
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Lets a halted CPU run cycles again, from the instruction following
+    /// the one that halted it
+    pub fn resume(&mut self) {
+        self.halted = false;
+    }
+
+    /// Runs `n` cycles with the same keypad state, stopping at the first
+    /// error.
+    pub fn run_cycles(&mut self, n: u64, keys: KeypadState) -> Result<(), CpuError> {
+        for _ in 0..n {
+            self.cycle(keys)?;
+        }
+
+        Ok(())
     }
 
     /// Runs a CPU Cycle.
     ///
     /// First fetches the next instruction pointed out by the PC, then decodes
     /// the instruction and finally executes the instruction.
-    pub fn cycle(&mut self, keypad_state: KeypadState) -> CycleOutput {
+    ///
+    /// The CPU halts once the program runs `00FD` or a cycle fails, the
+    /// following cycles leave the state untouched until `Cpu::resume` is
+    /// called.
+    pub fn cycle(&mut self, keypad_state: KeypadState) -> Result<CycleOutput, CpuError> {
+        if self.halted {
+            return Ok(CycleOutput {
+                beep: self.st > 0,
+                display_buffer: self.display_buffer,
+                display_update: false,
+            });
+        }
+
+        let output = self.run_cycle(keypad_state);
+
+        self.halted |= output.is_err();
+        output
+    }
+
+    fn run_cycle(&mut self, keypad_state: KeypadState) -> Result<CycleOutput, CpuError> {
         let mut display_update = false;
 
+        if self.rewind_buffer.is_some() {
+            let state = self.save_state();
+
+            if let Some(rewind_buffer) = self.rewind_buffer.as_mut() {
+                rewind_buffer.push(state);
+            }
+        }
+
         self.keypad_state = keypad_state;
+        self.cycles_executed += 1;
+
+        if self
+            .cycles_per_timer_tick
+            .is_some_and(|cycles| self.cycles_executed.is_multiple_of(cycles))
+        {
+            self.tick_timers();
+        }
+
+        let cycle_pc = self.pc;
+        let watching = !self.watchpoints.is_empty();
+
+        if watching {
+            self.registers.take_written();
+        }
+
+        self.ram.track_writes(watching);
+
+        if self.quirks.display_wait && self.draws_in_frame > 0 {
+            // Waits for the vertical blank after a draw
+            return Ok(CycleOutput {
+                beep: self.st > 0,
+                display_buffer: self.display_buffer,
+                display_update,
+            });
+        }
 
         if let Some(register) = self.keypad_await {
-            for index in 0..16_usize {
-                if keypad_state[index] {
-                    self.keypad_await = None;
-                    self.registers[register] = index as u8;
-                    break;
+            if let Some(key) = self.keypad_await_pressed {
+                if !keypad_state[key] {
+                    self.resolve_keypad_await(register, key);
+                }
+            } else {
+                // Only keys pressed since the wait started count, presses
+                // within the debounce window are taken as held
+                let pressed = keypad_state.pressed_since(&self.keypad_await_held);
+                let debouncing = self.cycles_executed <= self.keypad_debounce_until;
+
+                match pressed.iter().position(|pressed| *pressed) {
+                    Some(key) if !debouncing => {
+                        if self.quirks.wait_for_key_release {
+                            self.keypad_await_pressed = Some(key);
+                        } else {
+                            self.resolve_keypad_await(register, key);
+                        }
+                    }
+                    _ => self.keypad_await_held = keypad_state,
                 }
             }
         } else {
-            if self.dt > 0 {
-                self.dt -= 1;
+            let addr = self.pc;
+            let opcode = &self.fetch_opcode()?;
+            let instr = self.decode(opcode)?;
+
+            trace!("{:#06x}  {}  {}", addr, opcode, instr);
+
+            if let Some(tracer) = self.tracer.as_mut() {
+                tracer.trace(addr, opcode, &instr, &self.registers, self.i);
+            }
+
+            if self.denied.contains(&mem::discriminant(&instr)) {
+                return Err(CpuError::Denied {
+                    addr,
+                    raw: opcode.raw(),
+                    mnemonic: instr.mnemonic(),
+                });
             }
 
-            if self.st > 0 {
-                self.st -= 1;
+            if matches!(instr, Instruction::Draw(_, _, _)) {
+                if self
+                    .max_draws_per_frame
+                    .is_some_and(|max| self.draws_in_frame >= max)
+                {
+                    // Defers the draw to the next frame by fetching it again
+                    // on the next cycle
+                    self.pc -= 2;
+
+                    return Ok(CycleOutput {
+                        beep: self.st > 0,
+                        display_buffer: self.display_buffer,
+                        display_update,
+                    });
+                }
+
+                self.draws_in_frame += 1;
             }
 
-            let opcode = &self.fetch_opcode();
-            let instr = opcode.decode();
+            if self.quirks.strict_opcodes && matches!(instr, Instruction::Unknown) {
+                return Err(CpuError::UnknownOpcode {
+                    addr: self.pc - 2,
+                    raw: opcode.raw(),
+                    frames: self.stack.frames().to_vec(),
+                });
+            }
 
-            if matches!(instr, Instruction::Cls) || matches!(instr, Instruction::Draw(_, _, _)) {
+            if matches!(
+                instr,
+                Instruction::Cls
+                    | Instruction::Draw(_, _, _)
+                    | Instruction::ScrollDown(_)
+                    | Instruction::ScrollRight
+                    | Instruction::ScrollLeft
+                    | Instruction::HighRes
+                    | Instruction::LowRes
+            ) {
                 display_update = true;
             }
 
-            self.execute(instr);
+            self.execute(instr)?;
+        }
+
+        if watching {
+            self.record_watch_hits(cycle_pc);
         }
 
-        CycleOutput {
+        Ok(CycleOutput {
             beep: self.st > 0,
-            display_buffer: self.display_buffer.clone(),
+            display_buffer: self.display_buffer,
             display_update,
+        })
+    }
+
+    /// Records the writes to watched locations made by the cycle running
+    /// the instruction at `pc`
+    fn record_watch_hits(&mut self, pc: u16) {
+        let registers = self.registers.take_written();
+        let memory = self.ram.take_written();
+
+        for watch in &self.watchpoints {
+            let value = match *watch {
+                Watch::Register(vx) if registers & (1 << vx) != 0 => self.registers[vx],
+                Watch::Memory(addr)
+                    if memory
+                        .iter()
+                        .any(|region| region.contains(&(addr as usize))) =>
+                {
+                    self.ram[addr as usize]
+                }
+                _ => continue,
+            };
+
+            self.watch_hits.push(WatchHit {
+                watch: *watch,
+                value,
+                pc,
+            });
+        }
+    }
+
+    /// Decodes up to `count` instructions from the PC onwards, along with
+    /// their addresses, without running them. Stops early at the end of
+    /// memory.
+    pub fn peek_instructions(&self, count: usize) -> Vec<(u16, Instruction)> {
+        let word = |addr: usize| -> Option<u16> {
+            Some(u16::from_be_bytes([
+                self.ram.peek(addr).ok()?,
+                self.ram.peek(addr + 1).ok()?,
+            ]))
+        };
+        let mut instructions = Vec::with_capacity(count);
+        let mut addr = self.pc as usize;
+
+        while instructions.len() < count {
+            let Some(opcode) = word(addr).map(Opcode::from) else {
+                break;
+            };
+            let (instr, len) = if opcode.raw() == 0xF000 {
+                match word(addr + 2) {
+                    Some(nnnn) => (Instruction::LoadLongI(nnnn), 4),
+                    None => break,
+                }
+            } else {
+                (opcode.decode(), 2)
+            };
+
+            instructions.push((addr as u16, instr));
+            addr += len;
+        }
+
+        instructions
+    }
+
+    /// Signals the start of a new frame (vertical blank), expected to happen
+    /// `FRAME_RATE` times per second.
+    ///
+    /// Delay and sound timers count down on each frame, independently of
+    /// the clock rate.
+    ///
+    /// Timers locked to the cycle count with `lock_timers_to_cycles` are
+    /// left alone.
+    pub fn vblank(&mut self) {
+        if self.cycles_per_timer_tick.is_none() {
+            self.tick_timers();
+        }
+
+        self.draws_in_frame = 0;
+    }
+
+    fn tick_timers(&mut self) {
+        if self.dt > 0 {
+            self.dt -= 1;
+        }
+
+        if self.st > 0 {
+            self.st -= 1;
         }
     }
 
-    pub fn load_and_exec(&mut self, opcode: u16) {
-        self.load(vec![(opcode >> 8) as u8, (opcode & 0xff) as u8].into());
-        self.cycle(KeypadState::default());
+    /// XO-CHIP audio pattern to play while the sound timer is active, `None`
+    /// until a pattern is loaded with `F002`.
+    pub fn audio_pattern(&self) -> Option<AudioPattern> {
+        self.audio_buffer.map(|buffer| AudioPattern {
+            buffer,
+            pitch: self.pitch,
+        })
+    }
+
+    /// Takes a snapshot of the CPU state
+    pub fn save_state(&self) -> SaveState {
+        SaveState {
+            ram: self.ram.clone(),
+            pc: self.pc,
+            i: self.i,
+            stack: self.stack.clone(),
+            registers: self.registers.clone(),
+            flags: self.flags,
+            audio_buffer: self.audio_buffer,
+            pitch: self.pitch,
+            planes: self.planes,
+            dt: self.dt,
+            st: self.st,
+            display_buffer: self.display_buffer,
+            keypad_state: self.keypad_state,
+            keypad_await: self.keypad_await,
+            keypad_await_held: self.keypad_await_held,
+            keypad_await_pressed: self.keypad_await_pressed,
+            draws_in_frame: self.draws_in_frame,
+        }
+    }
+
+    /// Restores the CPU state from a snapshot taken with `Cpu::save_state`
+    pub fn load_state(&mut self, state: SaveState) {
+        self.ram = state.ram;
+        self.pc = state.pc;
+        self.i = state.i;
+        self.stack = state.stack;
+        self.registers = state.registers;
+        self.flags = state.flags;
+        self.audio_buffer = state.audio_buffer;
+        self.pitch = state.pitch;
+        self.planes = state.planes;
+        self.dt = state.dt;
+        self.st = state.st;
+        self.display_buffer = state.display_buffer;
+        self.keypad_state = state.keypad_state;
+        self.keypad_await = state.keypad_await;
+        self.keypad_await_held = state.keypad_await_held;
+        self.keypad_await_pressed = state.keypad_await_pressed;
+        self.keypad_debounce_until = 0;
+        self.draws_in_frame = state.draws_in_frame;
+        self.halted = false;
+    }
+
+    pub fn load_and_exec(&mut self, opcode: u16) -> Result<CycleOutput, CpuError> {
+        self.load(vec![(opcode >> 8) as u8, (opcode & 0xff) as u8].into())
+            .expect("Opcode doesn't fit in memory");
+        self.cycle(KeypadState::default())
     }
 
     /// Executes the provided instruction
-    pub fn execute(&mut self, instr: Instruction) {
+    pub fn execute(&mut self, instr: Instruction) -> Result<(), CpuError> {
         match instr {
-            Instruction::Cls => self.display_buffer.reset(),
+            Instruction::Cls => self.display_buffer.clear_planes(self.planes),
             Instruction::Ret => {
-                self.pc = self.stack.pop();
-
-                if self.sp > 0 {
-                    self.sp -= 1;
+                self.pc = self.stack.pop().map_err(|error| self.stack_error(error))?;
+            }
+            Instruction::ScrollDown(n) => self.display_buffer.scroll_down(n as u32),
+            Instruction::ScrollRight => self.display_buffer.scroll_right(4),
+            Instruction::ScrollLeft => self.display_buffer.scroll_left(4),
+            Instruction::HighRes => self.display_buffer.set_hires(true),
+            // Halts past the instruction, resuming runs the next one
+            Instruction::Exit => self.halted = true,
+            Instruction::LowRes => self.display_buffer.set_hires(false),
+            Instruction::SysAddr(target) => {
+                // Machine code routines can't be run, the instruction is
+                // skipped as modern interpreters do (PC already points to the
+                // next instruction).
+                if self.quirks.trap_sys_addr {
+                    return Err(CpuError::SysAddr {
+                        addr: self.pc - 2,
+                        target,
+                    });
                 }
+
+                warn!(
+                    "Skipping machine code routine call to {:#05x} at {:#06x}",
+                    target,
+                    self.pc - 2
+                );
             }
-            Instruction::SysAddr => println!("WARN: COSMAC VIP Only Instruction. Skipping."),
-            Instruction::Jump(address) => self.pc = address,
+            Instruction::Jump(address) => self.pc = self.jump_target(address),
             Instruction::CallSubroutine(address) => {
-                self.sp += 1;
-                self.stack.push(self.pc);
-                self.pc = address;
+                self.stack
+                    .push(self.pc)
+                    .map_err(|error| self.stack_error(error))?;
+                self.pc = self.jump_target(address);
             }
-            Instruction::Rand(vx, kk) => self.registers[vx] = kk & random::<u8>(),
+            Instruction::Rand(vx, kk) => self.registers[vx] = kk & self.rng.gen::<u8>(),
             Instruction::CondEq(vx, kk) => {
                 if self.registers[vx] == kk {
-                    self.pc += 2;
+                    self.advance_pc(2)?;
                 }
             }
             Instruction::CondNotEq(vx, kk) => {
                 if self.registers[vx] != kk {
-                    self.pc += 2;
+                    self.advance_pc(2)?;
                 }
             }
             Instruction::CondEqVxVy(vx, vy) => {
                 if self.registers[vx] == self.registers[vy] {
-                    self.pc += 2;
+                    self.advance_pc(2)?;
                 }
             }
             Instruction::ConstAssignVxToKk(vx, kk) => self.registers[vx] = kk,
@@ -163,140 +876,256 @@ impl Cpu {
             }
             Instruction::AssignVxToVy(vx, vy) => self.registers[vx] = self.registers[vy],
             Instruction::BitOpOr(vx, vy) => {
-                self.registers[vx] = self.registers[vx] | self.registers[vy];
-                self.pc += 2;
+                self.registers[vx] |= self.registers[vy];
+                self.reset_vf_after_logic();
             }
             Instruction::BitOpAnd(vx, vy) => {
-                self.registers[vx] = self.registers[vx] & self.registers[vy];
-                self.pc += 2;
+                self.registers[vx] &= self.registers[vy];
+                self.reset_vf_after_logic();
             }
             Instruction::BitOpXor(vx, vy) => {
-                self.registers[vx] = self.registers[vx] ^ self.registers[vy]
+                self.registers[vx] ^= self.registers[vy];
+                self.reset_vf_after_logic();
             }
+            // The flag is written after the result, so it wins when VF is the
+            // destination register as in reference interpreters
             Instruction::MathAdd(vx, vy) => {
                 let (result, overflows) = self.registers[vx].overflowing_add(self.registers[vy]);
 
-                self.registers[0xF] = overflows as u8;
                 self.registers[vx] = result;
+                self.registers[0xF] = overflows as u8;
             }
             Instruction::MathSub(vx, vy) => {
-                let (result, overflows) = self.registers[vx].overflowing_sub(self.registers[vy]);
+                let (result, borrows) = self.registers[vx].overflowing_sub(self.registers[vy]);
+
+                self.registers[vx] = result;
+                // VF is set when there's no borrow
+                self.registers[0xF] = !borrows as u8;
+            }
+            Instruction::BitOpShr(vx, _) => {
+                let shifted_out = self.registers[vx] & 0x01;
+
+                self.registers[vx] >>= 1;
+                self.registers[0xF] = shifted_out;
+            }
+            Instruction::MathSubVyVx(vx, vy) => {
+                let (result, borrows) = self.registers[vy].overflowing_sub(self.registers[vx]);
 
-                self.registers[0xF] = overflows as u8;
                 self.registers[vx] = result;
+                self.registers[0xF] = !borrows as u8;
             }
-            Instruction::BitOpShr(vx) => self.registers[vx] = self.registers[vx] >> 1,
-            Instruction::MathSubVyVx(vx, vy) => self.registers[vx] = self.registers[vx] - vy as u8,
-            Instruction::BitOpShl(vx) => {
-                self.registers[vx] = self.registers[vx] << 1;
+            Instruction::BitOpShl(vx, _) => {
+                let shifted_out = self.registers[vx] >> 7;
+
+                self.registers[vx] <<= 1;
+                self.registers[0xF] = shifted_out;
             }
             Instruction::CondVxNotEqVy(vx, vy) => {
                 if self.registers[vx] != self.registers[vy] {
-                    self.pc += 2;
+                    self.advance_pc(2)?;
                 }
             }
             Instruction::Mem(nnn) => {
                 self.i = nnn;
             }
             Instruction::Draw(vx, vy, n) => {
-                // Set the X coordinate to the value in VX modulo 64 (or,
-                // equivalently, VX & 63, where & is the binary AND operation)
-                let x = self.registers[vx] & 63;
-                // Set the Y coordinate to the value in VY modulo 32
-                // (or VY & 31)
-                let y = self.registers[vy] & 31;
-
-                // Set VF to 0
-                self.registers[0x0F] = 0x0;
-
-                for row in 0..n {
-                    let bits = self.ram[(self.i + row as u16) as usize];
-                    let this_y = (y + row as u8) as u32 % SCREEN_HEIGHT;
-
-                    for col in 0..8 {
-                        let this_x = (x + col as u8) as u32 % SCREEN_WIDTH;
-                        let current_color =
-                            self.display_buffer[(this_y * SCREEN_WIDTH + this_x) as usize];
-                        let mask = 0x01 << 7 - col;
-                        let color = bits & mask;
-
-                        if color > 0 {
-                            if current_color > 0 {
-                                self.display_buffer[(this_y * SCREEN_WIDTH + this_x) as usize] = 0;
-                                self.registers[0x0F] = 1;
-                            } else {
-                                self.display_buffer[(this_y * SCREEN_WIDTH + this_x) as usize] = 1;
-                            }
-                        }
-
-                        if this_x == SCREEN_WIDTH - 1 {
-                            break;
-                        }
+                let x = self.registers[vx];
+                let y = self.registers[vy];
+                // Sprites are either clipped at the right and bottom edges of
+                // the screen or wrapped around to the opposite edges
+                let wrap = !self.quirks.clip_sprites;
+                let mut sprite = [0; 15];
+                let mut collision = false;
+                // Each selected plane takes its own sprite data, one after
+                // the other starting at I
+                let selected = [0b01, 0b10]
+                    .into_iter()
+                    .filter(|plane| self.planes & plane != 0);
+
+                for (k, plane) in selected.enumerate() {
+                    let addr = self.i as usize + k * n as usize;
+
+                    for (row, byte) in sprite[..n as usize].iter_mut().enumerate() {
+                        *byte = self
+                            .ram
+                            .peek(addr + row)
+                            .map_err(|error| self.memory_error(error))?;
                     }
 
-                    if this_y == SCREEN_HEIGHT - 1 {
-                        break;
-                    }
+                    let sprite = &sprite[..n as usize];
+
+                    collision |= match self.collisions.as_mut() {
+                        Some(collisions) => self.display_buffer.draw_sprite_reporting(
+                            plane,
+                            x,
+                            y,
+                            sprite,
+                            wrap,
+                            |x, y| collisions.push((x, y)),
+                        ),
+                        None => self
+                            .display_buffer
+                            .draw_sprite_on_plane(plane, x, y, sprite, wrap),
+                    };
                 }
+
+                // Set VF to 1 if any pixel was turned off
+                self.registers[0x0F] = collision as u8;
             }
             Instruction::SetDtEqToVx(vx) => self.dt = self.registers[vx],
-            Instruction::SetStEqToVx(vx) => {
-                self.st = self.registers[vx];
-                self.pc += 2;
-            }
+            Instruction::SetStEqToVx(vx) => self.st = self.registers[vx],
             Instruction::SetIEqToIPlusVx(vx) => {
-                self.i = self.i + self.registers[vx] as u16;
+                let size = self.ram.size();
+                let sum = self.i as usize + self.registers[vx] as usize;
+
+                self.i = (sum % size) as u16;
+
+                if self.quirks.add_i_sets_vf {
+                    self.registers[0xF] = (sum >= size) as u8;
+                }
             }
             Instruction::SetIEqToVx(vx) => {
-                self.i = self.registers[vx] as u16 * 0x05;
+                self.i = self.ram.font_addr(self.registers[vx]);
+            }
+            Instruction::SetIEqToLargeFont(vx) => {
+                self.i = self.ram.large_font_addr(self.registers[vx]);
             }
             Instruction::StoreBinaryCodedDecimal(vx) => {
                 let value = self.registers[vx];
                 let h = value / 100;
                 let t = (value - h * 100) / 10;
                 let o = value - h * 100 - t * 10;
-                let i = self.i as usize;
 
-                self.ram[i] = h;
-                self.ram[i + 1] = t;
-                self.ram[i + 2] = o;
+                self.ram
+                    .load_at(self.i as usize, &[h, t, o], true)
+                    .map_err(|error| self.memory_error(error))?;
             }
             Instruction::SetRegsInI(vx) => {
+                let bytes: Vec<u8> = (0..vx + 1).map(|reg| self.registers[reg]).collect();
+
+                self.ram
+                    .load_at(self.i as usize, &bytes, true)
+                    .map_err(|error| self.memory_error(error))?;
+            }
+            Instruction::GetRegsInI(vx) => {
                 for reg in 0..vx + 1 {
-                    self.ram[self.i as usize + reg] = self.registers[reg];
+                    self.registers[reg] = self
+                        .ram
+                        .peek(self.i as usize + reg)
+                        .map_err(|error| self.memory_error(error))?;
                 }
             }
-            Instruction::GetRegsInI(vx) => {
+            Instruction::StoreFlags(vx) => {
+                for reg in 0..vx + 1 {
+                    self.flags[reg] = self.registers[reg];
+                }
+            }
+            Instruction::LoadFlags(vx) => {
                 for reg in 0..vx + 1 {
-                    self.registers[reg] = self.ram[self.i as usize + reg];
+                    self.registers[reg] = self.flags[reg];
                 }
             }
+            Instruction::LoadAudioPattern => {
+                let mut buffer = [0; 16];
+
+                for (offset, byte) in buffer.iter_mut().enumerate() {
+                    *byte = self
+                        .ram
+                        .peek(self.i as usize + offset)
+                        .map_err(|error| self.memory_error(error))?;
+                }
+
+                self.audio_buffer = Some(buffer);
+            }
+            Instruction::SetPitch(vx) => self.pitch = self.registers[vx],
+            Instruction::LoadLongI(nnnn) => self.i = nnnn,
+            Instruction::SelectPlanes(n) => self.planes = n,
             Instruction::SetVxEqToDt(vx) => {
                 self.registers[vx] = self.dt;
             }
             Instruction::WaitKeyPressAndStoreOnVx(vx) => {
+                // The wait is resolved by the next polls, keys already held
+                // at this point are ignored until released.
                 self.keypad_await = Some(vx);
-                self.pc += 2;
+                self.keypad_await_held = self.keypad_state;
             }
             Instruction::SkipIfKeyPressed(vx) => {
                 if self.keypad_state[self.registers[vx] as usize] {
-                    self.pc += 4;
+                    self.advance_pc(2)?;
                 }
-
-                self.pc += 2;
             }
             Instruction::KeyOpVxNotPressed(vx) => {
-                if self.keypad_state[self.registers[vx] as usize] {
-                    self.pc += 2;
-                    return;
+                if !self.keypad_state[self.registers[vx] as usize] {
+                    self.advance_pc(2)?;
                 }
-
-                self.pc += 4;
             }
-            Instruction::JumpPcV0(nnn) => self.pc = nnn + (self.registers[0x0] as u16),
-            Instruction::Unknown => {
-                self.pc += 2;
+            Instruction::JumpPcV0(vx, nnn) => {
+                let offset = if self.quirks.jump_uses_vx {
+                    self.registers[vx]
+                } else {
+                    self.registers[0x0]
+                };
+
+                self.pc = self.jump_target(nnn + offset as u16);
             }
+            // Skipped leniently, PC already points to the next instruction
+            Instruction::Unknown => {}
+        }
+
+        Ok(())
+    }
+
+    /// Wraps a `StackError` raised by the instruction just fetched
+    fn stack_error(&self, error: StackError) -> CpuError {
+        CpuError::Stack {
+            addr: self.pc - 2,
+            error,
+        }
+    }
+
+    /// Moves the PC `bytes` forward. Fails with `CpuError::PcOutOfBounds`
+    /// instead of wrapping around if the PC would overflow, which may only
+    /// happen with 64 KiB of memory.
+    fn advance_pc(&mut self, bytes: u16) -> Result<(), CpuError> {
+        self.pc = self
+            .pc
+            .checked_add(bytes)
+            .ok_or(CpuError::PcOutOfBounds { addr: self.pc })?;
+
+        Ok(())
+    }
+
+    /// Clears `VF` after a logical operation if the `logic_resets_vf` quirk
+    /// is enabled
+    fn reset_vf_after_logic(&mut self) {
+        if self.quirks.logic_resets_vf {
+            self.registers[0xF] = 0;
+        }
+    }
+
+    /// Address the PC is set to jumping to `address`, masked to the 12-bit
+    /// address space unless the `wrap_jumps` quirk is disabled
+    fn jump_target(&self, address: u16) -> u16 {
+        if self.quirks.wrap_jumps {
+            address & ADDRESS_MASK
+        } else {
+            address
+        }
+    }
+
+    /// Stores `key` in the register `Fx0A` waits on, resuming execution
+    fn resolve_keypad_await(&mut self, register: usize, key: usize) {
+        self.keypad_await = None;
+        self.keypad_await_pressed = None;
+        self.registers[register] = key as u8;
+        self.keypad_debounce_until = self.cycles_executed + self.key_debounce;
+    }
+
+    /// Wraps a `MemoryError` raised by the instruction just fetched
+    fn memory_error(&self, error: MemoryError) -> CpuError {
+        CpuError::Memory {
+            addr: self.pc - 2,
+            error,
         }
     }
 
@@ -309,28 +1138,68 @@ impl Cpu {
     /// build a 16-bit `OpCode`.
     ///
     /// 1. The value at memory address pointed by the PC is shifted 8-bits
-    /// to the left and stored in a 16-bit variable.
+    ///    to the left and stored in a 16-bit variable.
     ///
     /// 2. The value at memory address pointed by the PC + 1 is merged with
-    /// the value created at step 1 using the OR operator.
-    fn fetch_opcode(&mut self) -> Opcode {
+    ///    the value created at step 1 using the OR operator.
+    ///
+    /// Fails if the PC doesn't point to a complete opcode in memory, or if
+    /// it's odd and the `trap_misaligned_pc` quirk is enabled.
+    fn fetch_opcode(&mut self) -> Result<Opcode, CpuError> {
         let pc = self.pc as usize;
+
+        if pc + 1 >= self.ram.size() {
+            return Err(CpuError::PcOutOfBounds { addr: self.pc });
+        }
+
+        if self.quirks.trap_misaligned_pc && !pc.is_multiple_of(2) {
+            return Err(CpuError::MisalignedPc { addr: self.pc });
+        }
+
         let hexa: u16 = (self.ram[pc] as u16) << 8 | (self.ram[pc + 1] as u16);
 
-        self.pc += 2;
-        Opcode::from(hexa)
+        self.advance_pc(2)?;
+        Ok(Opcode::from(hexa))
+    }
+
+    /// Decodes the fetched `opcode`. The `F000` prefix reads the address for
+    /// `Instruction::LoadLongI` from the following 2 bytes, moving the PC
+    /// past them.
+    fn decode(&mut self, opcode: &Opcode) -> Result<Instruction, CpuError> {
+        if opcode.raw() != 0xF000 {
+            return Ok(opcode.decode());
+        }
+
+        let pc = self.pc as usize;
+
+        if pc + 1 >= self.ram.size() {
+            return Err(CpuError::PcOutOfBounds { addr: self.pc });
+        }
+
+        let nnnn = (self.ram[pc] as u16) << 8 | (self.ram[pc + 1] as u16);
+
+        self.advance_pc(2)?;
+        Ok(Instruction::LoadLongI(nnnn))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::cell::RefCell;
+    use std::sync::Once;
+
+    use log::{Level, LevelFilter, Log, Metadata, Record};
+    use rand::random;
+
+    use crate::debugger::{Watch, WatchHit};
     use crate::display::buffer::DisplayBuffer;
     use crate::keypad::KeypadState;
-    use crate::memory::{Memory, USER_SPACE_STR};
+    use crate::memory::{Memory, MemoryError, ETI660_USER_SPACE_STR, MEMORY_SIZE, USER_SPACE_STR};
+    use crate::opcode::Instruction;
     use crate::register_set::RegisterSet;
-    use crate::stack::Stack;
+    use crate::stack::{Stack, StackError};
 
-    use super::Cpu;
+    use super::{Cpu, CpuError, LoadError};
 
     #[test]
     fn new_instance() {
@@ -340,13 +1209,53 @@ mod tests {
         assert_eq!(cpu.pc, USER_SPACE_STR as u16);
         assert_eq!(cpu.i, 0);
         assert_eq!(cpu.stack, Stack::default());
-        assert_eq!(cpu.sp, 0);
         assert_eq!(cpu.registers, RegisterSet::default());
         assert_eq!(cpu.dt, 0);
         assert_eq!(cpu.st, 0);
         assert_eq!(cpu.display_buffer, DisplayBuffer::default());
         assert_eq!(cpu.keypad_state, KeypadState::default());
         assert_eq!(cpu.keypad_await, None);
+        assert_eq!(cpu.keypad_await_held, KeypadState::default());
+        assert_eq!(cpu.max_draws_per_frame, None);
+        assert_eq!(cpu.draws_in_frame, 0);
+        assert!(!cpu.quirks.strict_opcodes);
+    }
+
+    #[test]
+    fn starts_at_the_provided_address() {
+        let mut cpu = Cpu::with_start(0x204);
+
+        cpu.load(vec![0x60, 0x11, 0x61, 0x22, 0x62, 0x33].into())
+            .unwrap();
+
+        assert_eq!(cpu.pc, 0x204);
+        assert_eq!(cpu.fetch_opcode().unwrap().0, 0x6233);
+
+        cpu.reset();
+
+        assert_eq!(cpu.pc, 0x204, "Resets to the start address");
+    }
+
+    #[test]
+    #[should_panic(expected = "must be even and within memory")]
+    fn rejects_odd_start_addresses() {
+        Cpu::with_start(0x203);
+    }
+
+    #[test]
+    fn load_oversize_rom_fails() {
+        let mut cpu = Cpu::new();
+        let available = MEMORY_SIZE - USER_SPACE_STR;
+
+        assert_eq!(
+            cpu.load(vec![0xAA; available + 1].into()),
+            Err(LoadError::TooLarge {
+                size: available + 1,
+                available,
+            })
+        );
+        assert_eq!(cpu.ram[USER_SPACE_STR], 0x00, "Memory is left untouched");
+        assert!(cpu.load(vec![0xAA; available].into()).is_ok());
     }
 
     #[test]
@@ -354,7 +1263,7 @@ mod tests {
         let mut cpu = Cpu::new();
         let rom = vec![0x001, 0x002, 0x003, 0x004];
 
-        cpu.load(rom.into());
+        cpu.load(rom.into()).unwrap();
 
         assert_eq!(cpu.ram[USER_SPACE_STR], 0x001);
         assert_eq!(cpu.ram[USER_SPACE_STR + 1], 0x002);
@@ -362,6 +1271,52 @@ mod tests {
         assert_eq!(cpu.ram[USER_SPACE_STR + 3], 0x004);
     }
 
+    #[test]
+    fn eti660_cpu_starts_at_its_entry_point() {
+        let mut cpu = Cpu::with_memory(Memory::eti660());
+
+        // 0x600: LD V1, 0x2A
+        cpu.load(vec![0x61, 0x2A].into()).unwrap();
+
+        assert_eq!(cpu.pc, ETI660_USER_SPACE_STR as u16);
+        assert_eq!(cpu.ram.dump(ETI660_USER_SPACE_STR, 2), &[0x61, 0x2A]);
+
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        assert_eq!(cpu.registers[0x1], 0x2A);
+
+        cpu.reset();
+
+        assert_eq!(
+            cpu.pc, ETI660_USER_SPACE_STR as u16,
+            "Resets to the entry point"
+        );
+    }
+
+    #[test]
+    fn instr_load_long_i() {
+        let mut cpu = Cpu::new();
+
+        cpu.load(vec![0xF0, 0x00, 0x12, 0x34].into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        assert_eq!(cpu.i, 0x1234);
+        assert_eq!(cpu.pc, (USER_SPACE_STR + 4) as u16);
+    }
+
+    #[test]
+    fn instr_load_long_i_past_memory_fails() {
+        let mut cpu = Cpu::new();
+
+        cpu.ram[MEMORY_SIZE - 2] = 0xF0;
+        cpu.pc = (MEMORY_SIZE - 2) as u16;
+
+        assert!(matches!(
+            cpu.cycle(KeypadState::default()),
+            Err(CpuError::PcOutOfBounds { addr }) if addr as usize == MEMORY_SIZE
+        ));
+    }
+
     #[test]
     fn instr_cls() {
         let mut cpu = Cpu::new();
@@ -372,31 +1327,30 @@ mod tests {
             0x00, 0xE0,
         ];
 
-        cpu.load(rom.into());
+        cpu.load(rom.into()).unwrap();
 
         // Runs first cycle of CPU with 0xDFB8
-        cpu.cycle(KeypadState::default());
+        cpu.cycle(KeypadState::default()).unwrap();
 
         let written_display_buffer = cpu.display_buffer;
 
         // Runs second cycle of CPU with 0x00E0
-        cpu.cycle(KeypadState::default());
+        cpu.cycle(KeypadState::default()).unwrap();
 
         let cleared_display_buffer = cpu.display_buffer;
 
         assert!(
-            initial_display_buffer.0.iter().all(|x| *x == 0),
+            initial_display_buffer.pixels().iter().all(|x| *x == 0),
             "Initially all bytes are 0"
         );
 
-        assert_ne!(
-            written_display_buffer.0.iter().fold(0, |acc, x| acc + x),
-            0,
+        assert!(
+            written_display_buffer.pixels().iter().any(|x| *x > 0),
             "Bytes were written"
         );
 
         assert!(
-            cleared_display_buffer.0.iter().all(|x| *x == 0),
+            cleared_display_buffer.pixels().iter().all(|x| *x == 0),
             "Bytes were cleared"
         );
     }
@@ -405,19 +1359,119 @@ mod tests {
     fn instr_ret() {
         let mut cpu = Cpu::new();
 
-        cpu.stack.push(0x1234);
-        cpu.sp = 0x0003;
-        cpu.load_and_exec(0x00EE);
+        cpu.stack.push(0x0ABC).unwrap();
+        cpu.stack.push(0x1234).unwrap();
+        cpu.load_and_exec(0x00EE).unwrap();
 
-        assert_eq!(cpu.sp, 2);
+        assert_eq!(cpu.stack.sp(), 1);
         assert_eq!(cpu.pc, 0x1234);
     }
 
+    #[test]
+    fn nested_calls_return_in_order() {
+        let mut cpu = Cpu::new();
+
+        // 0x200: CALL 0x206; JP 0x204 (halt)
+        // 0x206: CALL 0x20A; RET
+        // 0x20A: CALL 0x20E; RET
+        // 0x20E: RET
+        cpu.load(
+            vec![
+                0x22, 0x06, 0x12, 0x04, 0x12, 0x04, 0x22, 0x0A, 0x00, 0xEE, 0x22, 0x0E, 0x00, 0xEE,
+                0x00, 0xEE,
+            ]
+            .into(),
+        )
+        .unwrap();
+
+        for _ in 0..3 {
+            cpu.cycle(KeypadState::default()).unwrap();
+        }
+
+        assert_eq!(cpu.pc, 0x20E);
+        assert_eq!(cpu.stack.frames(), &[0x202, 0x208, 0x20C]);
+
+        for (pc, sp) in [(0x20C, 2), (0x208, 1), (0x202, 0)] {
+            cpu.cycle(KeypadState::default()).unwrap();
+
+            assert_eq!(cpu.pc, pc);
+            assert_eq!(cpu.stack.sp(), sp);
+        }
+    }
+
+    #[test]
+    fn ret_without_call_underflows() {
+        let mut cpu = Cpu::new();
+
+        assert_eq!(
+            cpu.load_and_exec(0x00EE).unwrap_err(),
+            CpuError::Stack {
+                addr: 0x200,
+                error: StackError::Underflow
+            }
+        );
+    }
+
+    #[test]
+    fn out_of_bounds_memory_access_fails() {
+        let mut cpu = Cpu::new();
+
+        cpu.i = (MEMORY_SIZE - 2) as u16;
+        cpu.registers[0x5] = 0xAB;
+
+        // LD [I], V5 writes past the end of memory
+        assert_eq!(
+            cpu.load_and_exec(0xF555).unwrap_err(),
+            CpuError::Memory {
+                addr: 0x200,
+                error: MemoryError::OutOfBounds { addr: MEMORY_SIZE }
+            }
+        );
+        assert_eq!(
+            cpu.ram.dump(MEMORY_SIZE - 2, 2),
+            &[0x00, 0x00],
+            "Nothing is written"
+        );
+
+        let mut cpu = Cpu::new();
+
+        cpu.i = 0xFFFF;
+
+        // DRW V0, V1, 1 reads the sprite past the end of memory
+        assert!(matches!(
+            cpu.load_and_exec(0xD011).unwrap_err(),
+            CpuError::Memory {
+                error: MemoryError::OutOfBounds { addr: 0xFFFF },
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn runaway_calls_overflow() {
+        let mut cpu = Cpu::new();
+
+        // CALL 0x200
+        cpu.load(vec![0x22, 0x00].into()).unwrap();
+
+        for _ in 0..16 {
+            cpu.cycle(KeypadState::default()).unwrap();
+        }
+
+        assert_eq!(
+            cpu.cycle(KeypadState::default()).unwrap_err(),
+            CpuError::Stack {
+                addr: 0x200,
+                error: StackError::Overflow
+            }
+        );
+    }
+
     #[test]
     fn instr_jump() {
         let mut cpu = Cpu::new();
 
-        cpu.load_and_exec(0x12CD);
+        cpu.load_and_exec(0x12CD).unwrap();
 
         assert_eq!(cpu.pc, 0x02CD, "Jump to address on NNN");
     }
@@ -426,13 +1480,13 @@ mod tests {
     fn instr_call_subroutine() {
         let mut cpu = Cpu::new();
 
-        cpu.load_and_exec(0x2123);
+        cpu.load_and_exec(0x2123).unwrap();
 
         assert_eq!(cpu.pc, 0x0123, "The value of PC is the one set by NNN");
-        assert_eq!(cpu.sp, 1, "Stack Pointer is back to 1");
+        assert_eq!(cpu.stack.sp(), 1, "Stack Pointer is back to 1");
         assert_eq!(
             cpu.stack.pop(),
-            0x200 + 2,
+            Ok(0x200 + 2),
             "The PC (which starts on 0x200) is popped out of the stack"
         );
     }
@@ -441,7 +1495,7 @@ mod tests {
     fn instr_cond_eq() {
         let mut cpu = Cpu::new();
 
-        cpu.load_and_exec(0x3200);
+        cpu.load_and_exec(0x3200).unwrap();
         assert_eq!(cpu.pc, 0x200 + 4, "Skips if condition is equal");
     }
 
@@ -449,7 +1503,7 @@ mod tests {
     fn instr_cond_not_eq() {
         let mut cpu = Cpu::new();
 
-        cpu.load_and_exec(0x3212);
+        cpu.load_and_exec(0x3212).unwrap();
         assert_eq!(cpu.pc, 0x200 + 2, "Doesn't skips if condition is not equal");
     }
 
@@ -463,9 +1517,9 @@ mod tests {
             0x5B, 0xA0,
         ];
 
-        cpu.load(rom.into());
-        cpu.cycle(KeypadState::default());
-        cpu.cycle(KeypadState::default());
+        cpu.load(rom.into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
 
         assert_eq!(
             cpu.pc,
@@ -482,8 +1536,8 @@ mod tests {
             0x6B, 0x0B,
         ];
 
-        cpu.load(rom.into());
-        cpu.cycle(KeypadState::default());
+        cpu.load(rom.into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
 
         assert_eq!(
             cpu.registers[0x0b], 0x0b,
@@ -500,9 +1554,9 @@ mod tests {
             0x8B, 0xA0,
         ];
 
-        cpu.load(rom.into());
-        cpu.cycle(KeypadState::default());
-        cpu.cycle(KeypadState::default());
+        cpu.load(rom.into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
 
         assert_eq!(
             cpu.registers[0x0A], 0x08,
@@ -524,10 +1578,10 @@ mod tests {
             0x8A, 0xB1,
         ];
 
-        cpu.load(rom.into());
-        cpu.cycle(KeypadState::default());
-        cpu.cycle(KeypadState::default());
-        cpu.cycle(KeypadState::default());
+        cpu.load(rom.into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
 
         assert_eq!(
             cpu.registers[0x0a], 0x06,
@@ -535,6 +1589,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn instr_bit_op_or_resets_vf_when_enabled() {
+        let mut cpu = Cpu::new();
+
+        cpu.quirks.logic_resets_vf = true;
+        cpu.registers[0xF] = 0x01;
+        cpu.execute(Instruction::BitOpOr(0x1, 0x2)).unwrap();
+
+        assert_eq!(cpu.registers[0xF], 0x00, "VF is reset");
+
+        cpu.quirks.logic_resets_vf = false;
+        cpu.registers[0xF] = 0x01;
+        cpu.execute(Instruction::BitOpOr(0x1, 0x2)).unwrap();
+
+        assert_eq!(cpu.registers[0xF], 0x01, "VF is left untouched");
+    }
+
+    #[test]
+    fn instr_bit_op_or_and_and_move_to_next_instruction() {
+        for opcode in [0x8121, 0x8122] {
+            let mut cpu = Cpu::new();
+
+            cpu.load_and_exec(opcode).unwrap();
+
+            assert_eq!(cpu.pc, 0x202, "{:#06x} doesn't skip", opcode);
+        }
+    }
+
     #[test]
     fn instr_bit_op_and() {
         let mut cpu = Cpu::new();
@@ -545,10 +1627,10 @@ mod tests {
             0x8A, 0xB2,
         ];
 
-        cpu.load(rom.into());
-        cpu.cycle(KeypadState::default());
-        cpu.cycle(KeypadState::default());
-        cpu.cycle(KeypadState::default());
+        cpu.load(rom.into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
 
         assert_eq!(
             cpu.registers[0x0a], 0x0a,
@@ -566,10 +1648,10 @@ mod tests {
             0x8A, 0xB3,
         ];
 
-        cpu.load(rom.into());
-        cpu.cycle(KeypadState::default());
-        cpu.cycle(KeypadState::default());
-        cpu.cycle(KeypadState::default());
+        cpu.load(rom.into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
 
         assert_eq!(
             cpu.registers[0x0a], 0x0c,
@@ -587,10 +1669,10 @@ mod tests {
             0x8A, 0xB4,
         ];
 
-        cpu.load(rom.into());
-        cpu.cycle(KeypadState::default());
-        cpu.cycle(KeypadState::default());
-        cpu.cycle(KeypadState::default());
+        cpu.load(rom.into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
 
         assert_eq!(
             cpu.registers[0x0a], 0x17,
@@ -612,18 +1694,18 @@ mod tests {
             0x8D, 0xE5,
         ];
 
-        cpu.load(rom.into());
-        cpu.cycle(KeypadState::default());
-        cpu.cycle(KeypadState::default());
-        cpu.cycle(KeypadState::default());
+        cpu.load(rom.into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
 
         assert_eq!(
             cpu.registers[0x0d], 0x03,
             "Register on 0x0A is set to 0x03 due to the result from 13 - 10"
         );
         assert_eq!(
-            cpu.registers[0xF], 0,
-            "Register VF is set to 0 due to lack of overflow"
+            cpu.registers[0xF], 1,
+            "Register VF is set to 1 due to lack of borrow"
         );
     }
 
@@ -637,10 +1719,10 @@ mod tests {
             0x81, 0x24,
         ];
 
-        cpu.load(rom.into());
-        cpu.cycle(KeypadState::default());
-        cpu.cycle(KeypadState::default());
-        cpu.cycle(KeypadState::default());
+        cpu.load(rom.into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
 
         assert_eq!(
             cpu.registers[0x1], 0xFE,
@@ -662,18 +1744,18 @@ mod tests {
             0x81, 0x25,
         ];
 
-        cpu.load(rom.into());
-        cpu.cycle(KeypadState::default());
-        cpu.cycle(KeypadState::default());
-        cpu.cycle(KeypadState::default());
+        cpu.load(rom.into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
 
         assert_eq!(
             cpu.registers[0x1], 0xF4,
             "Register on 0x01 is set to 0xF4 due to the overflow from 1 - 13"
         );
         assert_eq!(
-            cpu.registers[0xF], 1,
-            "Register VF is set to 1 due to the overflow"
+            cpu.registers[0xF], 0,
+            "Register VF is set to 0 due to the borrow"
         );
     }
 
@@ -686,9 +1768,9 @@ mod tests {
             0x8A, 0xB6,
         ];
 
-        cpu.load(rom.into());
-        cpu.cycle(KeypadState::default());
-        cpu.cycle(KeypadState::default());
+        cpu.load(rom.into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
 
         assert_eq!(
             cpu.registers[0x0a], 0x05,
@@ -705,14 +1787,18 @@ mod tests {
             0x8A, 0xA7,
         ];
 
-        cpu.load(rom.into());
-        cpu.cycle(KeypadState::default());
-        cpu.cycle(KeypadState::default());
+        cpu.load(rom.into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
 
         assert_eq!(
             cpu.registers[0x0a], 0x00,
             "Register on 0x0A is set to 0x00 due to the result from 10 - 10"
         );
+        assert_eq!(
+            cpu.registers[0xF], 1,
+            "Register VF is set to 1 due to lack of borrow"
+        );
     }
 
     #[test]
@@ -724,9 +1810,9 @@ mod tests {
             0x8A, 0xBE,
         ];
 
-        cpu.load(rom.into());
-        cpu.cycle(KeypadState::default());
-        cpu.cycle(KeypadState::default());
+        cpu.load(rom.into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
 
         assert_eq!(
             cpu.registers[0x0a], 0x14,
@@ -734,6 +1820,59 @@ mod tests {
         );
     }
 
+    #[test]
+    fn instr_math_add_into_vf_keeps_the_carry() {
+        let mut cpu = Cpu::new();
+
+        cpu.registers[0xF] = 0xFF;
+        cpu.registers[0x1] = 0x02;
+        cpu.execute(Instruction::MathAdd(0xF, 0x1)).unwrap();
+
+        assert_eq!(cpu.registers[0xF], 1, "VF holds the carry, not the sum");
+
+        cpu.registers[0xF] = 0x10;
+        cpu.execute(Instruction::MathAdd(0xF, 0x1)).unwrap();
+
+        assert_eq!(cpu.registers[0xF], 0, "VF holds the carry, not the sum");
+    }
+
+    #[test]
+    fn instr_math_sub_into_vf_keeps_the_flag() {
+        let mut cpu = Cpu::new();
+
+        cpu.registers[0xF] = 0x01;
+        cpu.registers[0x1] = 0x02;
+        cpu.execute(Instruction::MathSub(0xF, 0x1)).unwrap();
+
+        assert_eq!(cpu.registers[0xF], 0, "VF holds the flag, not 0xFF");
+
+        cpu.registers[0xF] = 0x00;
+        cpu.execute(Instruction::MathSubVyVx(0xF, 0x1)).unwrap();
+
+        assert_eq!(cpu.registers[0xF], 1, "VF holds the flag, not 0x02");
+    }
+
+    #[test]
+    fn instr_shifts_into_vf_keep_the_shifted_out_bit() {
+        let mut cpu = Cpu::new();
+
+        cpu.registers[0xF] = 0b1000_0001;
+        cpu.execute(Instruction::BitOpShr(0xF, 0x0)).unwrap();
+
+        assert_eq!(cpu.registers[0xF], 1);
+
+        cpu.registers[0xF] = 0b1000_0001;
+        cpu.execute(Instruction::BitOpShl(0xF, 0x0)).unwrap();
+
+        assert_eq!(cpu.registers[0xF], 1);
+
+        cpu.registers[0x1] = 0b0100_0010;
+        cpu.execute(Instruction::BitOpShl(0x1, 0x0)).unwrap();
+
+        assert_eq!(cpu.registers[0x1], 0b1000_0100);
+        assert_eq!(cpu.registers[0xF], 0);
+    }
+
     #[test]
     fn instr_cond_vx_not_eq_vy() {
         let mut cpu = Cpu::new();
@@ -745,10 +1884,10 @@ mod tests {
             0x9A, 0xB0,
         ];
 
-        cpu.load(rom.into());
-        cpu.cycle(KeypadState::default());
-        cpu.cycle(KeypadState::default());
-        cpu.cycle(KeypadState::default());
+        cpu.load(rom.into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
 
         assert_eq!(
             cpu.pc,
@@ -765,12 +1904,107 @@ mod tests {
             0xA1, 0x23,
         ];
 
-        cpu.load(rom.into());
-        cpu.cycle(KeypadState::default());
+        cpu.load(rom.into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
 
         assert_eq!(cpu.i, 0x0123, "Index register is set to 0x0123");
     }
 
+    #[test]
+    fn instr_jump_pc_v0() {
+        let mut cpu = Cpu::new();
+
+        cpu.registers[0x0] = 0x10;
+        cpu.registers[0x3] = 0x20;
+        cpu.load_and_exec(0xB340).unwrap();
+
+        assert_eq!(cpu.pc, 0x340 + 0x10, "Jumps to nnn + V0");
+    }
+
+    #[test]
+    fn instr_jump_pc_vx_when_enabled() {
+        let mut cpu = Cpu::new();
+
+        cpu.quirks.jump_uses_vx = true;
+        cpu.registers[0x0] = 0x10;
+        cpu.registers[0x3] = 0x20;
+        cpu.load_and_exec(0xB340).unwrap();
+
+        assert_eq!(cpu.pc, 0x340 + 0x20, "Jumps to xnn + Vx");
+    }
+
+    #[test]
+    fn jump_targets_wrap_to_12_bits() {
+        let mut cpu = Cpu::new();
+
+        cpu.execute(Instruction::Jump(0x1FFF)).unwrap();
+        assert_eq!(cpu.pc, 0x0FFF);
+
+        cpu.execute(Instruction::CallSubroutine(0x1FFF)).unwrap();
+        assert_eq!(cpu.pc, 0x0FFF);
+
+        cpu.pc = 0x200;
+        cpu.registers[0x0] = 0x10;
+        cpu.load_and_exec(0xBFF8).unwrap();
+        assert_eq!(cpu.pc, 0x0008, "Wraps around past 0xFFF");
+
+        cpu.quirks.wrap_jumps = false;
+        cpu.execute(Instruction::Jump(0x1FFF)).unwrap();
+        assert_eq!(cpu.pc, 0x1FFF, "Not masked when disabled");
+    }
+
+    #[test]
+    fn instr_set_i_eq_to_i_plus_vx_wraps() {
+        let mut cpu = Cpu::new();
+
+        cpu.i = 0x0FFF;
+        cpu.registers[0x3] = 0x02;
+        cpu.registers[0xF] = 0xAA;
+        cpu.load_and_exec(0xF31E).unwrap();
+
+        assert_eq!(cpu.i, 0x0001, "Wraps around the end of memory");
+        assert_eq!(cpu.registers[0xF], 0xAA, "VF is left untouched");
+    }
+
+    #[test]
+    fn instr_set_i_eq_to_i_plus_vx_sets_vf_when_enabled() {
+        let mut cpu = Cpu::new();
+
+        cpu.quirks.add_i_sets_vf = true;
+        cpu.i = 0x0FFF;
+        cpu.registers[0x3] = 0x02;
+        cpu.load_and_exec(0xF31E).unwrap();
+
+        assert_eq!(cpu.i, 0x0001);
+        assert_eq!(cpu.registers[0xF], 1, "VF is set on overflow");
+
+        let mut cpu = Cpu::new();
+
+        cpu.quirks.add_i_sets_vf = true;
+        cpu.i = 0x0F00;
+        cpu.registers[0x3] = 0x02;
+        cpu.registers[0xF] = 0xAA;
+        cpu.load_and_exec(0xF31E).unwrap();
+
+        assert_eq!(cpu.i, 0x0F02);
+        assert_eq!(cpu.registers[0xF], 0, "VF is cleared otherwise");
+    }
+
+    #[test]
+    fn instr_rand_is_reproducible_with_seed() {
+        let mut cpu = Cpu::with_seed(0xC8);
+        let mut other = Cpu::with_seed(0xC8);
+        let rom = vec![0xC0, 0xFF, 0xC1, 0xFF, 0xC2, 0x0F];
+
+        cpu.load(rom.clone().into()).unwrap();
+        other.load(rom.into()).unwrap();
+        cpu.run_cycles(3, KeypadState::default()).unwrap();
+        other.run_cycles(3, KeypadState::default()).unwrap();
+
+        assert_eq!(cpu.registers, other.registers);
+        assert_eq!(cpu.registers[0x2] & 0xF0, 0, "Random byte is masked by kk");
+    }
+
     #[test]
     fn instr_set_vx_eq_to_dt() {
         let mut cpu = Cpu::new();
@@ -782,8 +2016,8 @@ mod tests {
             0xFA, 0x07,
         ];
 
-        cpu.load(rom.into());
-        cpu.cycle(KeypadState::default());
+        cpu.load(rom.into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
 
         assert_eq!(
             cpu.dt, cpu.registers[0x0A],
@@ -791,6 +2025,77 @@ mod tests {
         );
     }
 
+    #[test]
+    fn instr_store_and_load_flags() {
+        for vx in 0..8_usize {
+            let mut cpu = Cpu::new();
+            for reg in 0..16 {
+                cpu.registers[reg] = 0x10 + reg as u8;
+            }
+
+            // Store V0..Vx in the flags, clobber every register, restore
+            // V0..Vx from the flags
+            cpu.execute(Instruction::StoreFlags(vx)).unwrap();
+            for reg in 0..16 {
+                cpu.registers[reg] = 0xFF;
+            }
+            cpu.execute(Instruction::LoadFlags(vx)).unwrap();
+
+            for reg in 0..16 {
+                let expected = if reg <= vx { 0x10 + reg as u8 } else { 0xFF };
+
+                assert_eq!(cpu.registers[reg], expected, "V{:X} with vx = {}", reg, vx);
+            }
+        }
+    }
+
+    #[test]
+    fn decodes_flags_instructions() {
+        let mut cpu = Cpu::new();
+
+        cpu.registers[0x3] = 0x2A;
+        cpu.load(vec![0xF3, 0x75, 0x63, 0x00, 0xF3, 0x85].into())
+            .unwrap();
+
+        for _ in 0..3 {
+            cpu.cycle(KeypadState::default()).unwrap();
+        }
+
+        assert_eq!(cpu.flags[0x3], 0x2A);
+        assert_eq!(cpu.registers[0x3], 0x2A);
+    }
+
+    #[test]
+    fn instr_load_audio_pattern_and_pitch() {
+        let mut cpu = Cpu::new();
+
+        for offset in 0..16 {
+            cpu.ram[0x300 + offset] = offset as u8;
+        }
+
+        assert_eq!(
+            cpu.audio_pattern(),
+            None,
+            "Plays the square wave by default"
+        );
+
+        // LD I, 0x300; AUDIO; LD V2, 0x70; PITCH V2
+        cpu.load(vec![0xA3, 0x00, 0xF0, 0x02, 0x62, 0x70, 0xF2, 0x3A].into())
+            .unwrap();
+
+        for _ in 0..4 {
+            cpu.cycle(KeypadState::default()).unwrap();
+        }
+
+        let pattern = cpu.audio_pattern().unwrap();
+
+        assert_eq!(
+            pattern.buffer,
+            [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]
+        );
+        assert_eq!(pattern.pitch, 0x70);
+    }
+
     #[test]
     fn instr_set_dt_eq_to_vx() {
         let mut cpu = Cpu::new();
@@ -802,8 +2107,8 @@ mod tests {
             0xFA, 0x15,
         ];
 
-        cpu.load(rom.into());
-        cpu.cycle(KeypadState::default());
+        cpu.load(rom.into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
 
         assert_eq!(
             cpu.dt, cpu.registers[0x0A],
@@ -819,29 +2124,1190 @@ mod tests {
 
         let rom = vec![0xF3, 0x18];
 
-        cpu.load(rom.into());
-        cpu.cycle(KeypadState::default());
+        cpu.load(rom.into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
 
         assert_eq!(cpu.st, 0x10);
+        assert_eq!(cpu.pc, 0x202, "Doesn't skip the next instruction");
     }
 
     #[test]
-    fn instr_key_op_vx_not_pressed_no_skip() {
+    fn instr_skip_if_key_pressed_skip() {
         let mut cpu = Cpu::new();
 
-        cpu.keypad_state[9] = true;
+        cpu.keypad_state = KeypadState::from_keys(&[0x9]);
         cpu.registers[0x5] = 9;
-        cpu.execute(crate::opcode::Instruction::KeyOpVxNotPressed(0x5));
+        cpu.execute(crate::opcode::Instruction::SkipIfKeyPressed(0x5))
+            .unwrap();
 
         assert_eq!(cpu.pc, (USER_SPACE_STR + 2) as u16)
     }
 
+    #[test]
+    fn instr_skip_if_key_pressed_no_skip() {
+        let mut cpu = Cpu::new();
+
+        cpu.execute(crate::opcode::Instruction::SkipIfKeyPressed(0x5))
+            .unwrap();
+
+        assert_eq!(cpu.pc, USER_SPACE_STR as u16)
+    }
+
+    #[test]
+    fn instr_key_op_vx_not_pressed_no_skip() {
+        let mut cpu = Cpu::new();
+
+        cpu.keypad_state = KeypadState::from_keys(&[0x9]);
+        cpu.registers[0x5] = 9;
+        cpu.execute(crate::opcode::Instruction::KeyOpVxNotPressed(0x5))
+            .unwrap();
+
+        assert_eq!(cpu.pc, USER_SPACE_STR as u16)
+    }
+
     #[test]
     fn instr_key_op_vx_not_pressed_skip() {
         let mut cpu = Cpu::new();
 
-        cpu.execute(crate::opcode::Instruction::KeyOpVxNotPressed(0x5));
+        cpu.execute(crate::opcode::Instruction::KeyOpVxNotPressed(0x5))
+            .unwrap();
+
+        assert_eq!(cpu.pc, (USER_SPACE_STR + 2) as u16)
+    }
+
+    #[test]
+    fn instr_wait_key_press_ignores_held_key() {
+        let mut cpu = Cpu::new();
+        let held = KeypadState::from_keys(&[0x7]);
+
+        cpu.load(vec![0xF3, 0x0A].into()).unwrap();
+        cpu.cycle(held).unwrap();
+
+        assert_eq!(cpu.keypad_await, Some(0x3), "Waits for a key press");
+
+        cpu.cycle(held).unwrap();
+
+        assert_eq!(
+            cpu.keypad_await,
+            Some(0x3),
+            "Key held before Fx0A doesn't resolve the wait"
+        );
+
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(held).unwrap();
+
+        assert_eq!(
+            cpu.keypad_await, None,
+            "Key pressed again resolves the wait"
+        );
+        assert_eq!(cpu.registers[0x3], 0x7);
+    }
+
+    #[test]
+    fn instr_wait_key_press_captures_held_key_once() {
+        let mut cpu = Cpu::new();
+        let held = KeypadState::from_keys(&[0x5]);
+
+        // WAIT V0, WAIT V1
+        cpu.load(vec![0xF0, 0x0A, 0xF1, 0x0A].into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.run_cycles(10, held).unwrap();
+
+        assert_eq!(cpu.registers[0x0], 0x5);
+        assert_eq!(cpu.registers[0x1], 0x0);
+        assert_eq!(
+            cpu.keypad_await,
+            Some(0x1),
+            "Key held across both waits is captured once"
+        );
+    }
+
+    #[test]
+    fn instr_wait_key_press_debounces_presses() {
+        let mut cpu = Cpu::new();
+        let pressed = KeypadState::from_keys(&[0x5]);
+
+        cpu.key_debounce = 4;
+        // WAIT V0, WAIT V1
+        cpu.load(vec![0xF0, 0x0A, 0xF1, 0x0A].into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(pressed).unwrap();
+
+        assert_eq!(cpu.registers[0x0], 0x5);
+
+        // The key bounces within the debounce window
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(pressed).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        assert_eq!(cpu.keypad_await, Some(0x1), "Bounced presses are ignored");
+
+        cpu.run_cycles(2, KeypadState::default()).unwrap();
+        cpu.cycle(pressed).unwrap();
+
+        assert_eq!(cpu.keypad_await, None, "Presses past the window count");
+        assert_eq!(cpu.registers[0x1], 0x5);
+    }
+
+    #[test]
+    fn instr_wait_key_release_when_enabled() {
+        let mut cpu = Cpu::new();
+        let pressed = KeypadState::from_keys(&[0x7]);
+
+        cpu.quirks.wait_for_key_release = true;
+        cpu.load(vec![0xF3, 0x0A].into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(pressed).unwrap();
+        cpu.cycle(pressed).unwrap();
+
+        assert_eq!(
+            cpu.keypad_await,
+            Some(0x3),
+            "Pressed key is not stored while held"
+        );
+        assert_eq!(cpu.registers[0x3], 0x0);
+
+        cpu.cycle(KeypadState::from_keys(&[0x2])).unwrap();
+
+        assert_eq!(
+            cpu.keypad_await, None,
+            "Releasing the key resolves the wait"
+        );
+        assert_eq!(cpu.registers[0x3], 0x7, "Stores the released key");
+    }
+
+    #[test]
+    fn instr_high_res_and_low_res() {
+        let mut cpu = Cpu::new();
+        let rom = vec![
+            // Enables high resolution mode
+            0x00, 0xFF, // Disables high resolution mode
+            0x00, 0xFE,
+        ];
+
+        cpu.load(rom.into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        assert!(cpu.display_buffer.is_hires(), "Switches to high resolution");
+        assert_eq!(cpu.display_buffer.width(), 128);
+        assert_eq!(cpu.display_buffer.height(), 64);
+
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        assert!(!cpu.display_buffer.is_hires(), "Switches to low resolution");
+        assert_eq!(cpu.display_buffer.width(), 64);
+        assert_eq!(cpu.display_buffer.height(), 32);
+    }
+
+    #[test]
+    fn instr_draw_wraps_at_active_resolution() {
+        let rom = vec![
+            // Enables high resolution mode
+            0x00, 0xFF, // Assigns V0 to 100
+            0x60, 0x64, // Assigns V1 to 40
+            0x61, 0x28, // Draws font digit 0 (I = 0) at (V0, V1)
+            0xD0, 0x15,
+        ];
+        let mut cpu = Cpu::new();
+
+        cpu.load(rom.into()).unwrap();
+
+        for _ in 0..4 {
+            cpu.cycle(KeypadState::default()).unwrap();
+        }
+
+        assert_eq!(cpu.display_buffer[40 * 128 + 100], 1, "Draws at (100, 40)");
+
+        let mut cpu = Cpu::new();
+
+        cpu.load(vec![0x00, 0xFE, 0x60, 0x64, 0x61, 0x28, 0xD0, 0x15].into())
+            .unwrap();
+
+        for _ in 0..4 {
+            cpu.cycle(KeypadState::default()).unwrap();
+        }
+
+        assert_eq!(
+            cpu.display_buffer[8 * 64 + 36],
+            1,
+            "Wraps coordinates to (36, 8) in low resolution mode"
+        );
+    }
+
+    #[test]
+    fn instr_draw_clips_sprites_at_right_edge() {
+        let mut cpu = Cpu::new();
+
+        cpu.ram[0x300] = 0xFF;
+        cpu.i = 0x300;
+        cpu.registers[0x0] = 60;
+        cpu.registers[0x1] = 1;
+        cpu.execute(Instruction::Draw(0x0, 0x1, 1)).unwrap();
+
+        assert_eq!(&cpu.display_buffer.pixels()[64 + 60..2 * 64], &[1; 4]);
+        assert!(
+            cpu.display_buffer.pixels()[64..64 + 60]
+                .iter()
+                .all(|x| *x == 0),
+            "Clipped pixels are not drawn on the left edge"
+        );
+        assert_eq!(
+            cpu.display_buffer[2 * 64],
+            0,
+            "Pixels don't spill into next row"
+        );
+    }
+
+    #[test]
+    fn instr_draw_wraps_sprites_at_right_edge() {
+        let mut cpu = Cpu::new();
+
+        cpu.quirks.clip_sprites = false;
+        cpu.ram[0x300] = 0xFF;
+        cpu.i = 0x300;
+        cpu.registers[0x0] = 60;
+        cpu.registers[0x1] = 1;
+        cpu.execute(Instruction::Draw(0x0, 0x1, 1)).unwrap();
+
+        assert_eq!(&cpu.display_buffer.pixels()[64 + 60..2 * 64], &[1; 4]);
+        assert_eq!(
+            &cpu.display_buffer.pixels()[64..64 + 4],
+            &[1; 4],
+            "Pixels past the right edge wrap to the left edge of the same row"
+        );
+        assert_eq!(
+            cpu.display_buffer[2 * 64],
+            0,
+            "Pixels don't spill into next row"
+        );
+    }
+
+    #[test]
+    fn instr_draw_wraps_sprites_across_both_axes() {
+        let mut cpu = Cpu::new();
+
+        cpu.quirks.clip_sprites = false;
+        cpu.ram[0x300] = 0xFF;
+        cpu.ram[0x301] = 0xFF;
+        cpu.i = 0x300;
+        cpu.registers[0x0] = 60;
+        cpu.registers[0x1] = 31;
+        cpu.execute(Instruction::Draw(0x0, 0x1, 2)).unwrap();
+
+        for row in [31, 0] {
+            let pixels = &cpu.display_buffer.pixels()[row * 64..(row + 1) * 64];
+            let columns: Vec<usize> = (0..64).filter(|col| pixels[*col] == 1).collect();
+
+            assert_eq!(columns, vec![0, 1, 2, 3, 60, 61, 62, 63], "Row {}", row);
+        }
+
+        assert_eq!(cpu.registers[0x0F], 0);
+        assert_eq!(
+            cpu.display_buffer
+                .pixels()
+                .iter()
+                .filter(|x| **x == 1)
+                .count(),
+            16,
+            "Draws all 8 columns of both rows"
+        );
+    }
+
+    #[test]
+    fn instr_draw_wraps_sprites_at_bottom_edge() {
+        let mut cpu = Cpu::new();
+
+        cpu.quirks.clip_sprites = false;
+        cpu.ram[0x300] = 0x80;
+        cpu.ram[0x301] = 0x80;
+        cpu.i = 0x300;
+        cpu.registers[0x0] = 3;
+        cpu.registers[0x1] = 31;
+        cpu.execute(Instruction::Draw(0x0, 0x1, 2)).unwrap();
+
+        assert_eq!(cpu.display_buffer[31 * 64 + 3], 1);
+        assert_eq!(cpu.display_buffer[3], 1, "Second row wraps to the top");
+    }
+
+    #[test]
+    fn instr_draw_twice_erases_sprite_and_sets_vf() {
+        for clip_sprites in [true, false] {
+            let mut cpu = Cpu::new();
+
+            cpu.quirks.clip_sprites = clip_sprites;
+            cpu.ram[0x300] = 0b1010_0101;
+            cpu.ram[0x301] = 0b0101_1010;
+            cpu.i = 0x300;
+            cpu.registers[0x0] = 60;
+            cpu.registers[0x1] = 31;
+
+            cpu.execute(Instruction::Draw(0x0, 0x1, 2)).unwrap();
+            assert_eq!(cpu.registers[0x0F], 0, "No pixel was turned off");
+            assert!(cpu.display_buffer.pixels().contains(&1));
+
+            cpu.execute(Instruction::Draw(0x0, 0x1, 2)).unwrap();
+            assert_eq!(cpu.registers[0x0F], 1, "Pixels were turned off");
+            assert!(
+                cpu.display_buffer.pixels().iter().all(|x| *x == 0),
+                "Drawing the same sprite twice XORs it away"
+            );
+        }
+    }
+
+    #[test]
+    fn instr_draw_detects_collision_on_wrapped_pixels() {
+        let mut cpu = Cpu::new();
+
+        cpu.quirks.clip_sprites = false;
+        cpu.display_buffer[64 + 1] = 1;
+        cpu.ram[0x300] = 0x01;
+        cpu.i = 0x300;
+        cpu.registers[0x0] = 58;
+        cpu.registers[0x1] = 1;
+        cpu.execute(Instruction::Draw(0x0, 0x1, 1)).unwrap();
+
+        assert_eq!(cpu.display_buffer[64 + 1], 0);
+        assert_eq!(
+            cpu.registers[0x0F], 1,
+            "Collision on the last sprite column"
+        );
+    }
+
+    #[test]
+    fn instr_draw_into_second_plane_leaves_first_plane_untouched() {
+        let mut cpu = Cpu::new();
+
+        cpu.display_buffer[0] = 0b01;
+        cpu.ram[0x300] = 0b1100_0000;
+        cpu.i = 0x300;
+
+        // PLANE 2
+        cpu.load(vec![0xF2, 0x01].into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.execute(Instruction::Draw(0x0, 0x1, 1)).unwrap();
+
+        assert_eq!(cpu.planes, 0b10);
+        assert_eq!(cpu.display_buffer[0], 0b11, "Plane 1 pixel is kept");
+        assert_eq!(cpu.display_buffer[1], 0b10);
+        assert_eq!(cpu.registers[0x0F], 0, "No pixel was turned off");
+
+        cpu.execute(Instruction::Cls).unwrap();
+
+        assert_eq!(cpu.display_buffer[0], 0b01, "Only plane 2 is cleared");
+        assert_eq!(cpu.display_buffer[1], 0b00);
+    }
+
+    #[test]
+    fn instr_draw_into_both_planes_reads_a_sprite_per_plane() {
+        let mut cpu = Cpu::new();
+
+        cpu.ram[0x300] = 0b1000_0000;
+        cpu.ram[0x301] = 0b0100_0000;
+        cpu.i = 0x300;
+        cpu.planes = 0b11;
+        cpu.execute(Instruction::Draw(0x0, 0x1, 1)).unwrap();
+
+        assert_eq!(cpu.display_buffer[0], 0b01);
+        assert_eq!(cpu.display_buffer[1], 0b10);
+
+        cpu.execute(Instruction::Draw(0x0, 0x1, 1)).unwrap();
+
+        assert_eq!(cpu.registers[0x0F], 1, "Collisions on any plane");
+        assert!(cpu.display_buffer.pixels().iter().all(|x| *x == 0));
+    }
+
+    #[test]
+    fn denied_instructions_halt() {
+        let mut cpu = Cpu::new();
+
+        cpu.deny(&Instruction::Draw(0, 0, 0));
+        // 0x200: LD I, 0x000
+        // 0x202: DRW V0, V1, 5
+        cpu.load(vec![0xA0, 0x00, 0xD0, 0x15].into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        let err = cpu.cycle(KeypadState::default()).unwrap_err();
+
+        assert_eq!(
+            err,
+            CpuError::Denied {
+                addr: 0x202,
+                raw: 0xD015,
+                mnemonic: "DRW"
+            }
+        );
+        assert_eq!(err.to_string(), "Denied instruction DRW (0xd015) at 0x0202");
+        assert!(
+            cpu.display_buffer.pixels().iter().all(|x| *x == 0),
+            "The sprite is not drawn"
+        );
+    }
+
+    #[test]
+    fn denies_instructions_by_kind() {
+        let mut cpu = Cpu::new();
+
+        cpu.deny(&Instruction::SetRegsInI(0));
+        // 0x200: LD V1, [I]
+        // 0x202: LD [I], V3
+        cpu.load(vec![0xF1, 0x65, 0xF3, 0x55].into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        assert_eq!(
+            cpu.cycle(KeypadState::default()).unwrap_err(),
+            CpuError::Denied {
+                addr: 0x202,
+                raw: 0xF355,
+                mnemonic: "LD"
+            }
+        );
+    }
+
+    #[test]
+    fn instr_scroll_down() {
+        let mut cpu = Cpu::new();
+
+        cpu.display_buffer[5] = 1;
+        cpu.load(vec![0x00, 0xC2].into()).unwrap();
+
+        let cycle_output = cpu.cycle(KeypadState::default()).unwrap();
+
+        assert!(cycle_output.display_update, "Scrolling updates the display");
+        assert_eq!(cpu.display_buffer[5], 0);
+        assert_eq!(cpu.display_buffer[2 * 64 + 5], 1, "Pixel moved 2 rows down");
+    }
+
+    #[test]
+    fn instr_set_i_to_relocated_font_digit() {
+        let mut cpu = Cpu::with_memory(Memory::with_font_base(MEMORY_SIZE, USER_SPACE_STR, 0x50));
+        let rom = vec![
+            0x60, 0x03, // Loads 3 into V0
+            0xF0, 0x29, // Points I to the font sprite for the digit in V0
+        ];
+
+        cpu.load(rom.into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        assert_eq!(cpu.i, 0x50 + 15);
+    }
+
+    #[test]
+    fn instr_set_i_to_large_font_digit() {
+        let mut cpu = Cpu::new();
+        let rom = vec![
+            0x60, 0x05, // Loads 5 into V0
+            0xF0, 0x30, // Points I to the large font sprite for the digit in V0
+            0xF0, 0x29, // Points I to the small font sprite for the digit in V0
+        ];
+
+        cpu.load(rom.into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        assert_eq!(cpu.i, cpu.ram.large_font_addr(0) + 5 * 10);
+        assert_eq!(cpu.ram.dump(cpu.i as usize, 2), &[0xFF, 0xFF]);
+
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        assert_eq!(cpu.i, 5 * 5);
+    }
+
+    #[test]
+    fn instr_draw_font_digit_as_ascii() {
+        let mut cpu = Cpu::new();
+        let rom = vec![
+            // Sets Index Register to font digit 0
+            0xA0, 0x00, // Draws 5 bytes sprite at (V0, V0)
+            0xD0, 0x05,
+        ];
+
+        cpu.load(rom.into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        assert_eq!(
+            cpu.display_buffer.to_ascii(6, 6),
+            [
+                "████  \n", //
+                "█  █  \n", //
+                "█  █  \n", //
+                "█  █  \n", //
+                "████  \n", //
+                "      \n",
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn max_draws_per_frame_defers_excess_draws() {
+        let mut cpu = Cpu::new();
+        let rom = vec![
+            // Draws the same sprite 4 times
+            0xD0, 0x05, 0xD0, 0x05, 0xD0, 0x05, 0xD0, 0x05,
+        ];
+
+        cpu.max_draws_per_frame = Some(2);
+        cpu.load(rom.into()).unwrap();
+
+        for _ in 0..4 {
+            cpu.cycle(KeypadState::default()).unwrap();
+        }
+
+        assert_eq!(cpu.draws_in_frame, 2, "Only 2 draws are executed");
+        assert_eq!(cpu.pc, 0x200 + 4, "Third draw is deferred");
+
+        cpu.vblank();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        assert_eq!(cpu.draws_in_frame, 2, "Deferred draws run next frame");
+        assert_eq!(cpu.pc, 0x200 + 8);
+    }
+
+    #[test]
+    fn display_wait_stalls_until_vblank() {
+        let mut cpu = Cpu::new();
+        let rom = vec![
+            // Draws the same sprite twice, then sets V1
+            0xD0, 0x05, 0xD0, 0x05, 0x61, 0x01,
+        ];
+
+        cpu.quirks.display_wait = true;
+        cpu.load(rom.into()).unwrap();
+
+        for _ in 0..4 {
+            cpu.cycle(KeypadState::default()).unwrap();
+        }
+
+        assert_eq!(cpu.draws_in_frame, 1, "Second draw waits for vblank");
+        assert_eq!(cpu.pc, 0x200 + 2);
+
+        cpu.vblank();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        assert_eq!(cpu.draws_in_frame, 1, "Second draw runs next frame");
+        assert_eq!(cpu.pc, 0x200 + 4, "Execution stalls after the draw");
+        assert_eq!(cpu.registers[0x1], 0);
+    }
+
+    #[test]
+    fn watchpoints_record_writes() {
+        let mut cpu = Cpu::new();
+        let rom = vec![
+            0x65, 0x00, // Loads 0 into V5, the value it already holds
+            0x61, 0x01, // Loads 1 into V1
+            0xA3, 0x00, // Points I to 0x300
+            0xF5, 0x55, // Stores V0 through V5 from 0x300
+        ];
+
+        cpu.load(rom.into()).unwrap();
+        cpu.watch(Watch::Register(0x5));
+        cpu.watch(Watch::Memory(0x305));
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        assert_eq!(
+            cpu.take_watch_hits(),
+            vec![WatchHit {
+                watch: Watch::Register(0x5),
+                value: 0x00,
+                pc: 0x200,
+            }]
+        );
+        assert!(cpu.take_watch_hits().is_empty());
+
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        assert!(cpu.take_watch_hits().is_empty(), "V5 isn't written");
+
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        assert_eq!(
+            cpu.take_watch_hits(),
+            vec![WatchHit {
+                watch: Watch::Memory(0x305),
+                value: 0x00,
+                pc: 0x206,
+            }]
+        );
+        assert!(cpu.unwatch(Watch::Register(0x5)));
+        assert!(!cpu.unwatch(Watch::Register(0x5)));
+    }
+
+    #[test]
+    fn peeks_upcoming_instructions() {
+        let mut cpu = Cpu::new();
+        let rom = vec![
+            0x00, 0xE0, // CLS
+            0x61, 0x1C, // LD V1, 0x1C
+            0xF0, 0x00, 0x12, 0x34, // LD I, LONG 0x1234
+            0x12, 0x00, // JP 0x200
+        ];
+
+        cpu.load(rom.into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        assert_eq!(
+            cpu.peek_instructions(3),
+            vec![
+                (0x202, Instruction::ConstAssignVxToKk(0x1, 0x1C)),
+                (0x204, Instruction::LoadLongI(0x1234)),
+                (0x208, Instruction::Jump(0x200)),
+            ]
+        );
+        assert_eq!(cpu.pc, 0x202, "Peeking doesn't move the PC");
+
+        cpu.pc = MEMORY_SIZE as u16 - 4;
+
+        assert_eq!(cpu.peek_instructions(4).len(), 2, "Stops at memory end");
+    }
+
+    #[test]
+    fn timers_locked_to_cycles_tick_deterministically() {
+        let mut cpu = Cpu::new();
+        let rom = vec![
+            0x60, 0x32, // Loads 50 into V0
+            0xF0, 0x15, // Sets the delay timer to V0
+            0x12, 0x04, // Jumps to itself
+        ];
+
+        cpu.lock_timers_to_cycles(10);
+        cpu.load(rom.into()).unwrap();
+
+        for _ in 0..102 {
+            cpu.cycle(KeypadState::default()).unwrap();
+        }
+
+        cpu.vblank();
+
+        assert_eq!(cpu.dt, 50 - 10, "Ticks on cycles 10 to 100 only");
+    }
+
+    #[test]
+    fn pc_past_memory_end_fails_on_fetch() {
+        let mut cpu = Cpu::new();
+
+        // Skips the instruction past the last one in memory
+        cpu.ram.poke(0xFFE, 0x30).unwrap();
+        cpu.ram.poke(0xFFF, 0x00).unwrap();
+        cpu.pc = 0xFFE;
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        assert_eq!(cpu.pc, 0x1002);
+        assert_eq!(
+            cpu.cycle(KeypadState::default()).unwrap_err(),
+            CpuError::PcOutOfBounds { addr: 0x1002 }
+        );
+    }
+
+    #[test]
+    fn pc_overflow_fails_instead_of_wrapping() {
+        let mut cpu = Cpu::with_memory(Memory::new(0x10000, USER_SPACE_STR));
+
+        // Skips the last instruction in memory
+        cpu.ram.poke(0xFFFC, 0x30).unwrap();
+        cpu.ram.poke(0xFFFD, 0x00).unwrap();
+        cpu.pc = 0xFFFC;
+
+        assert_eq!(
+            cpu.cycle(KeypadState::default()).unwrap_err(),
+            CpuError::PcOutOfBounds { addr: 0xFFFE }
+        );
+
+        // Runs the last instruction in memory, the PC can't point past it
+        cpu.ram.poke(0xFFFE, 0x00).unwrap();
+        cpu.ram.poke(0xFFFF, 0xE0).unwrap();
+        cpu.pc = 0xFFFE;
+        cpu.resume();
+
+        assert_eq!(
+            cpu.cycle(KeypadState::default()).unwrap_err(),
+            CpuError::PcOutOfBounds { addr: 0xFFFE }
+        );
+    }
+
+    #[test]
+    fn records_draw_collisions() {
+        let mut cpu = Cpu::new();
+        let rom = vec![
+            0x61, 0x02, // Loads 2 into V1
+            0xD0, 0x05, // Draws the digit 0 at (0, 0)
+            0xD1, 0x15, // Draws it again at (2, 2)
+        ];
+
+        cpu.record_collisions(true);
+        cpu.load(rom.into()).unwrap();
+
+        for _ in 0..3 {
+            cpu.cycle(KeypadState::default()).unwrap();
+        }
+
+        // The second digit overlaps the right column of the first one on
+        // its top row and the bottom row of the first one on its third row
+        assert_eq!(cpu.take_collisions(), vec![(3, 2), (2, 4)]);
+        assert!(cpu.take_collisions().is_empty());
+        assert_eq!(cpu.registers[0xF], 1);
+    }
+
+    #[test]
+    fn reload_restarts_with_the_new_rom() {
+        let mut cpu = Cpu::new();
+
+        cpu.load(vec![0x60, 0x01, 0x61, 0x02].into()).unwrap();
+        cpu.run_cycles(2, KeypadState::default()).unwrap();
+        cpu.reload(vec![0x62, 0x03].into()).unwrap();
+
+        assert_eq!(cpu.pc, 0x200);
+        assert_eq!((cpu.ram[0x200], cpu.ram[0x202]), (0x62, 0x00));
+        assert_eq!(cpu.registers[0x1], 0x00, "State is reset");
+
+        cpu.cycle(KeypadState::default()).unwrap();
+        assert_eq!(cpu.registers[0x2], 0x03);
+
+        assert!(cpu.reload(vec![0; 0x1000].into()).is_err());
+        assert_eq!(cpu.rom.bytes(), [0x62, 0x03], "Keeps the ROM if too large");
+    }
+
+    #[test]
+    fn exit_halts_the_cpu() {
+        let mut cpu = Cpu::new();
+
+        cpu.load(vec![0x60, 0x01, 0x00, 0xFD, 0x60, 0x02].into())
+            .unwrap();
+
+        cpu.cycle(KeypadState::default()).unwrap();
+        assert!(!cpu.is_halted());
+        cpu.cycle(KeypadState::default()).unwrap();
+        assert!(cpu.is_halted());
+
+        cpu.run_cycles(3, KeypadState::default()).unwrap();
+        assert_eq!(cpu.pc, 0x204);
+        assert_eq!(cpu.registers[0x0], 0x01);
+        assert_eq!(cpu.cycle_count(), 2);
+
+        cpu.resume();
+        cpu.cycle(KeypadState::default()).unwrap();
+        assert_eq!(cpu.registers[0x0], 0x02);
+    }
+
+    #[test]
+    fn failed_cycle_halts_the_cpu() {
+        let mut cpu = Cpu::new();
+
+        cpu.deny(&Instruction::Draw(0, 0, 0));
+        cpu.load(vec![0xD0, 0x15, 0x61, 0x01].into()).unwrap();
+
+        assert!(cpu.cycle(KeypadState::default()).is_err());
+        assert!(cpu.is_halted());
+
+        let pc = cpu.pc;
+        let registers = cpu.registers.clone();
+
+        cpu.run_cycles(3, KeypadState::default()).unwrap();
+        assert_eq!(cpu.pc, pc);
+        assert_eq!(cpu.registers, registers);
+
+        cpu.reset();
+        assert!(!cpu.is_halted());
+    }
+
+    #[test]
+    fn unknown_opcode_error_includes_call_stack() {
+        let mut cpu = Cpu::new();
+        let rom = vec![
+            // Calls subroutine at 0x204
+            0x22, 0x04, // Never reached
+            0x00, 0xE0, // Unknown opcode
+            0x51, 0x23,
+        ];
+
+        cpu.quirks.strict_opcodes = true;
+        cpu.load(rom.into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        let error = cpu.cycle(KeypadState::default()).unwrap_err();
+
+        assert_eq!(
+            error,
+            CpuError::UnknownOpcode {
+                addr: 0x204,
+                raw: 0x5123,
+                frames: vec![0x202],
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "Unknown opcode 0x5123 at 0x0204\n    called from 0x0202"
+        );
+    }
+
+    #[test]
+    fn fetch_past_memory_fails() {
+        let mut cpu = Cpu::new();
+
+        cpu.pc = (MEMORY_SIZE - 1) as u16;
+
+        let error = cpu.cycle(KeypadState::default()).unwrap_err();
+
+        assert_eq!(error, CpuError::PcOutOfBounds { addr: 0x0FFF });
+        assert_eq!(error.to_string(), "Program counter out of memory at 0x0fff");
+        assert_eq!(cpu.pc, 0x0FFF, "PC is left untouched");
+    }
+
+    #[test]
+    fn misaligned_pc_traps_when_enabled() {
+        let mut cpu = Cpu::new();
+
+        cpu.load(vec![0x12, 0x03, 0x00, 0x00, 0xE0].into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        assert_eq!(cpu.pc, 0x205, "Misaligned opcodes run by default");
+
+        cpu.reset();
+        cpu.quirks.trap_misaligned_pc = true;
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        assert_eq!(
+            cpu.cycle(KeypadState::default()).unwrap_err(),
+            CpuError::MisalignedPc { addr: 0x203 }
+        );
+    }
+
+    #[test]
+    fn unknown_opcode_is_skipped_when_not_strict() {
+        let mut cpu = Cpu::new();
+
+        cpu.load_and_exec(0x5123).unwrap();
+
+        assert_eq!(cpu.pc, 0x200 + 2);
+    }
+
+    #[test]
+    fn unknown_opcode_fails_when_strict() {
+        let mut cpu = Cpu::new();
+
+        cpu.load(vec![0xFF, 0xFF, 0x60, 0x01].into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        assert_eq!(cpu.pc, 0x200 + 2, "Skipped when lenient");
+
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        assert_eq!(cpu.registers[0x0], 0x01, "Runs the next instruction");
+
+        let mut cpu = Cpu::new();
+
+        cpu.quirks.strict_opcodes = true;
+
+        assert_eq!(
+            cpu.load_and_exec(0xFFFF).unwrap_err(),
+            CpuError::UnknownOpcode {
+                addr: 0x200,
+                raw: 0xFFFF,
+                frames: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn sys_addr_is_skipped() {
+        let mut cpu = Cpu::new();
+
+        cpu.load_and_exec(0x0123).unwrap();
+
+        assert_eq!(cpu.pc, 0x200 + 2);
+    }
+
+    thread_local! {
+        /// Records logged from the current test thread
+        static LOG_RECORDS: RefCell<Vec<(Level, String)>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// Keeps the log records in `LOG_RECORDS`, tests run in parallel and
+    /// only see their own records
+    struct CapturingLogger;
+
+    impl Log for CapturingLogger {
+        fn enabled(&self, _metadata: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            LOG_RECORDS.with(|records| {
+                records
+                    .borrow_mut()
+                    .push((record.level(), record.args().to_string()))
+            });
+        }
+
+        fn flush(&self) {}
+    }
+
+    /// Installs the `CapturingLogger` once for every test
+    fn capture_logs() {
+        static INSTALL: Once = Once::new();
+
+        INSTALL.call_once(|| {
+            log::set_logger(&CapturingLogger).unwrap();
+            log::set_max_level(LevelFilter::Warn);
+        });
+    }
+
+    #[test]
+    fn sys_addr_logs_a_warning() {
+        let mut cpu = Cpu::new();
+
+        capture_logs();
+        cpu.load_and_exec(0x0123).unwrap();
+
+        LOG_RECORDS.with(|records| {
+            assert_eq!(
+                *records.borrow(),
+                vec![(
+                    Level::Warn,
+                    String::from("Skipping machine code routine call to 0x123 at 0x0200")
+                )]
+            )
+        });
+    }
+
+    #[test]
+    fn sys_addr_traps_when_enabled() {
+        let mut cpu = Cpu::new();
+
+        cpu.quirks.trap_sys_addr = true;
+
+        let error = cpu.load_and_exec(0x0123).unwrap_err();
+
+        assert_eq!(
+            error,
+            CpuError::SysAddr {
+                addr: 0x200,
+                target: 0x123,
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "Machine code routine call to 0x123 at 0x0200"
+        );
+    }
+
+    /// Draws a sprite pixel by pixel as the reference implementation for
+    /// the `Draw` instruction.
+    fn reference_draw(cpu: &mut Cpu, vx: usize, vy: usize, n: u8) {
+        let width = cpu.display_buffer.width();
+        let height = cpu.display_buffer.height();
+        let x = cpu.registers[vx] as u32 % width;
+        let y = cpu.registers[vy] as u32 % height;
+
+        cpu.registers[0x0F] = 0x0;
+
+        for row in 0..n as u32 {
+            let bits = cpu.ram[cpu.i as usize + row as usize];
+
+            for col in 0..8 {
+                if x + col >= width || y + row >= height {
+                    continue;
+                }
+
+                let index = ((y + row) * width + x + col) as usize;
+
+                if bits & (0x80 >> col) > 0 {
+                    if cpu.display_buffer[index] > 0 {
+                        cpu.display_buffer[index] = 0;
+                        cpu.registers[0x0F] = 1;
+                    } else {
+                        cpu.display_buffer[index] = 1;
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn instr_draw_matches_reference() {
+        for _ in 0..500 {
+            let mut cpu = Cpu::new();
+
+            cpu.display_buffer.set_hires(random::<bool>());
+
+            for index in 0..cpu.display_buffer.pixels().len() {
+                cpu.display_buffer[index] = random::<u8>() & 0x01;
+            }
+
+            let mut reference = Cpu::new();
+
+            for addr in 0x300..0x310 {
+                cpu.ram[addr] = random::<u8>();
+                reference.ram[addr] = cpu.ram[addr];
+            }
+
+            cpu.i = 0x300;
+            cpu.registers[0x1] = random::<u8>();
+            cpu.registers[0x2] = random::<u8>();
+
+            let n = random::<u8>() & 0x0F;
+
+            reference.display_buffer = cpu.display_buffer;
+            reference.i = cpu.i;
+            reference.registers[0x1] = cpu.registers[0x1];
+            reference.registers[0x2] = cpu.registers[0x2];
+
+            cpu.execute(Instruction::Draw(0x1, 0x2, n)).unwrap();
+            reference_draw(&mut reference, 0x1, 0x2, n);
+
+            assert_eq!(cpu.display_buffer, reference.display_buffer);
+            assert_eq!(cpu.registers[0x0F], reference.registers[0x0F]);
+        }
+    }
+
+    #[test]
+    fn timers_count_down_on_vblank() {
+        let mut cpu = Cpu::new();
+
+        cpu.dt = 2;
+        cpu.st = 1;
+        cpu.load(vec![0x00, 0xE0, 0x00, 0xE0].into()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        assert_eq!((cpu.dt, cpu.st), (2, 1), "Cycles don't affect timers");
+
+        cpu.vblank();
+
+        assert_eq!((cpu.dt, cpu.st), (1, 0));
+
+        cpu.vblank();
+        cpu.vblank();
+
+        assert_eq!((cpu.dt, cpu.st), (0, 0), "Timers stop at 0");
+    }
+
+    #[test]
+    fn reset_restarts_loaded_rom() {
+        let mut cpu = Cpu::new();
+        // HIGH; CALL 0x206; JP 0x204; LD V1, 0x2A; LD I, 0x300; DRW V1, V1, 5;
+        // LD DT, V1; LD [I], V1; WAIT V3
+        let rom = vec![
+            0x00, 0xFF, 0x22, 0x06, 0x12, 0x04, 0x61, 0x2A, 0xA3, 0x00, 0xD1, 0x15, 0xF1, 0x15,
+            0xF1, 0x55, 0xF3, 0x0A,
+        ];
+
+        cpu.load(rom.clone().into()).unwrap();
+
+        for _ in 0..9 {
+            cpu.cycle(KeypadState::from_keys(&[0x2])).unwrap();
+        }
+
+        assert_ne!(cpu.save_state(), Cpu::new().save_state());
+
+        cpu.reset();
+
+        let mut expected = Cpu::new();
+
+        expected.load(rom.clone().into()).unwrap();
+
+        assert_eq!(cpu.save_state(), expected.save_state());
+        assert_eq!(cpu.ram[0x200 + rom.len() - 1], 0x0A, "ROM bytes remain");
+        assert_eq!(cpu.ram[0x0000], 0xF0, "Fonts are loaded");
+    }
+
+    #[test]
+    fn counts_executed_cycles() {
+        let mut cpu = Cpu::new();
+
+        // ADD V1, 0x01; JP 0x200
+        cpu.load(vec![0x71, 0x01, 0x12, 0x00].into()).unwrap();
+
+        assert_eq!(cpu.cycle_count(), 0);
+
+        for _ in 0..3 {
+            cpu.cycle(KeypadState::default()).unwrap();
+        }
+
+        assert_eq!(cpu.cycle_count(), 3);
+
+        cpu.run_cycles(100, KeypadState::default()).unwrap();
+
+        assert_eq!(cpu.cycle_count(), 103);
+        assert_eq!(cpu.registers[0x1], 52);
+    }
+
+    #[test]
+    fn rewinds_cycles() {
+        let mut cpu = Cpu::new();
+
+        cpu.enable_rewind(8);
+        cpu.load(vec![0x60, 0x01, 0x61, 0x02, 0x62, 0x03].into())
+            .unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        let pc = cpu.pc;
+        let registers = cpu.registers.clone();
+
+        cpu.cycle(KeypadState::default()).unwrap();
+        cpu.cycle(KeypadState::default()).unwrap();
+
+        assert!(cpu.rewind());
+        assert!(cpu.rewind());
+        assert_eq!(cpu.pc, pc);
+        assert_eq!(cpu.registers, registers);
+        assert_eq!(cpu.registers[0x0], 0x01);
+        assert_eq!(cpu.registers[0x1], 0x00, "Second cycle undone");
+    }
+
+    #[test]
+    fn rewind_buffer_is_bounded() {
+        let mut cpu = Cpu::new();
+
+        assert!(!cpu.rewind(), "Rewind is disabled by default");
+
+        cpu.enable_rewind(2);
+        cpu.load(vec![0x60, 0x01, 0x61, 0x02, 0x62, 0x03].into())
+            .unwrap();
+        cpu.run_cycles(3, KeypadState::default()).unwrap();
+
+        assert!(cpu.rewind());
+        assert!(cpu.rewind());
+        assert!(!cpu.rewind(), "Oldest state was dropped");
+        assert_eq!(cpu.pc, 0x202);
+    }
+
+    #[test]
+    fn restores_saved_state() {
+        let mut cpu = Cpu::new();
+
+        // CALL 0x206; JP 0x200; LD V1, 0x2A; LD I, 0x300; DRW V1, V1, 5; RET
+        cpu.load(
+            vec![
+                0x22, 0x06, 0x12, 0x00, 0x00, 0x00, 0x61, 0x2A, 0xA3, 0x00, 0xD1, 0x15, 0x00, 0xEE,
+            ]
+            .into(),
+        )
+        .unwrap();
+
+        for _ in 0..4 {
+            cpu.cycle(KeypadState::default()).unwrap();
+        }
+
+        let state = cpu.save_state();
+
+        for _ in 0..3 {
+            cpu.cycle(KeypadState::from_keys(&[0x1])).unwrap();
+        }
+        cpu.ram[0x300] = 0xFF;
+        cpu.vblank();
+
+        assert_ne!(cpu.save_state(), state);
+
+        cpu.load_state(state.clone());
 
-        assert_eq!(cpu.pc, (USER_SPACE_STR + 4) as u16)
+        assert_eq!(cpu.save_state(), state);
+        assert_eq!(cpu.pc, 0x20C);
+        assert_eq!(cpu.stack.frames(), &[0x202]);
     }
 }
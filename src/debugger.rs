@@ -0,0 +1,452 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{stdin, stdout, Write};
+
+use crate::cpu::Cpu;
+use crate::opcode::Opcode;
+
+/// Commands available while the execution is stopped in debug mode
+pub const HELP: &str = "Debugging Mode. Commands:
+    s, ENTER    run the next instruction
+    u           undo the last instruction
+    c           continue until a breakpoint is hit
+    b <addr>    add a breakpoint at <addr>
+    d <addr>    remove the breakpoint at <addr>
+    w <loc>     stop after writes to <loc>, a register (V0-VF) or address
+    dw <loc>    remove the watchpoint on <loc>
+    l n         list the next n instructions (5 by default)
+    x <addr> n  dump n bytes of memory from <addr> (16 by default)
+    reg         show the registers
+    stack       show the call stack
+    q           quit";
+
+/// Debugger commands read while the execution is stopped
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Add a breakpoint at the address
+    Break(u16),
+    /// Remove the breakpoint at the address
+    Delete(u16),
+    /// Add a watchpoint
+    Watch(Watch),
+    /// Remove a watchpoint
+    DeleteWatch(Watch),
+    /// Dump memory from the address, the given number of bytes
+    Examine(u16, u16),
+    /// List the given number of instructions from the PC onwards
+    List(usize),
+    /// Show the registers
+    Registers,
+    /// Show the call stack
+    Stack,
+    /// Run until a breakpoint is hit
+    Continue,
+    /// Run the next instruction and stop again
+    Step,
+    /// Go back to the state before the last instruction
+    Undo,
+    /// Stop the emulation
+    Quit,
+}
+
+/// Location whose writes stop the execution after the cycle writing it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Watch {
+    /// Variable register, from 0x0 (V0) to 0xF (VF)
+    Register(usize),
+    /// Memory address
+    Memory(u16),
+}
+
+impl fmt::Display for Watch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Watch::Register(vx) => write!(f, "V{:X}", vx),
+            Watch::Memory(addr) => write!(f, "{:#06x}", addr),
+        }
+    }
+}
+
+/// Write to a watched location, recorded by `Cpu::cycle`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WatchHit {
+    pub watch: Watch,
+    /// Value written
+    pub value: u8,
+    /// Address of the instruction writing it
+    pub pc: u16,
+}
+
+impl fmt::Display for WatchHit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Watchpoint {} written with {:#04x} at {:#06x}",
+            self.watch, self.value, self.pc
+        )
+    }
+}
+
+/// How the execution goes on after the debugger prompt
+#[derive(Debug, PartialEq, Eq)]
+pub enum Resume {
+    Step,
+    Continue,
+    Quit,
+}
+
+/// Parses a debugger command from a line of input.
+///
+/// Addresses are hexadecimal, with or without the `0x` prefix. An empty line
+/// steps to the next instruction.
+pub fn parse_command(input: &str) -> Result<Command, String> {
+    let mut words = input.split_whitespace();
+    let command = words.next().unwrap_or("s");
+    let mut addr = || -> Result<u16, String> {
+        let value = words
+            .next()
+            .ok_or_else(|| format!("Missing address for \"{}\"", command))?;
+
+        parse_addr(value)
+    };
+
+    match command {
+        "b" | "break" => Ok(Command::Break(addr()?)),
+        "d" | "delete" => Ok(Command::Delete(addr()?)),
+        "w" | "watch" => Ok(Command::Watch(parse_watch(words.next(), command)?)),
+        "dw" => Ok(Command::DeleteWatch(parse_watch(words.next(), command)?)),
+        "x" => {
+            let addr = addr()?;
+            let len = match words.next() {
+                Some(len) => len
+                    .parse::<u16>()
+                    .map_err(|_| format!("Invalid length \"{}\"", len))?,
+                None => 16,
+            };
+
+            Ok(Command::Examine(addr, len))
+        }
+        "l" | "list" => match words.next() {
+            Some(count) => count
+                .parse()
+                .map(Command::List)
+                .map_err(|_| format!("Invalid count \"{}\"", count)),
+            None => Ok(Command::List(5)),
+        },
+        "reg" | "r" => Ok(Command::Registers),
+        "stack" | "bt" => Ok(Command::Stack),
+        "c" | "continue" => Ok(Command::Continue),
+        "s" | "step" => Ok(Command::Step),
+        "u" | "undo" => Ok(Command::Undo),
+        "q" | "quit" => Ok(Command::Quit),
+        _ => Err(format!("Unknown command \"{}\"", command)),
+    }
+}
+
+/// Parses a register name (`V0` to `VF`) or a memory address
+fn parse_watch(value: Option<&str>, command: &str) -> Result<Watch, String> {
+    let value = value.ok_or_else(|| format!("Missing register or address for \"{}\"", command))?;
+
+    match value.strip_prefix(['V', 'v']) {
+        Some(digit) => match u8::from_str_radix(digit, 16) {
+            Ok(vx) if digit.len() == 1 => Ok(Watch::Register(vx as usize)),
+            _ => Err(format!("Invalid register \"{}\"", value)),
+        },
+        None => parse_addr(value).map(Watch::Memory),
+    }
+}
+
+/// Parses a hexadecimal address, with or without the `0x` prefix
+fn parse_addr(value: &str) -> Result<u16, String> {
+    let digits = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value);
+
+    u16::from_str_radix(digits, 16).map_err(|_| format!("Invalid address \"{}\"", value))
+}
+
+/// Hexdump of `len` bytes of memory from `addr`, 16 bytes per line.
+/// Bytes past the end of memory are left out.
+pub fn format_memory(cpu: &Cpu, addr: u16, len: u16) -> String {
+    let mut dump = String::new();
+
+    for (index, chunk) in cpu
+        .ram
+        .dump(addr as usize, len as usize)
+        .chunks(16)
+        .enumerate()
+    {
+        let bytes: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+        dump.push_str(&format!(
+            "{:#06x}  {}\n",
+            addr as usize + index * 16,
+            bytes.join(" ")
+        ));
+    }
+
+    dump
+}
+
+/// Variable registers, I, PC and timers
+pub fn format_registers(cpu: &Cpu) -> String {
+    let mut dump = String::new();
+
+    for row in 0..2 {
+        let registers: Vec<String> = (row * 8..row * 8 + 8)
+            .map(|vx| format!("V{:X}: {:#04x}", vx, cpu.registers[vx]))
+            .collect();
+
+        dump.push_str(&registers.join("  "));
+        dump.push('\n');
+    }
+
+    dump.push_str(&format!(
+        "I: {:#06x}  PC: {:#06x}  DT: {:#04x}  ST: {:#04x}\n",
+        cpu.i, cpu.pc, cpu.dt, cpu.st
+    ));
+
+    dump
+}
+
+/// Return addresses in the call stack, from the innermost to the outermost
+/// call.
+pub fn format_stack(cpu: &Cpu) -> String {
+    let frames = cpu.stack.frames();
+
+    if frames.is_empty() {
+        return String::from("Empty stack\n");
+    }
+
+    frames
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(depth, addr)| format!("#{}  {:#06x}\n", depth, addr))
+        .collect()
+}
+
+/// Stops the execution on breakpoints or after each step and reads
+/// commands from stdin.
+pub struct Debugger {
+    breakpoints: HashSet<u16>,
+    /// Stop before the next instruction regardless of breakpoints
+    stepping: bool,
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debugger {
+    /// Creates a `Debugger` which stops before the first instruction
+    pub fn new() -> Self {
+        Self {
+            breakpoints: HashSet::new(),
+            stepping: true,
+        }
+    }
+
+    /// Stops the execution before the next instruction, as after a step
+    pub fn pause(&mut self) {
+        self.stepping = true;
+    }
+
+    /// Whether the execution must stop before running the instruction at
+    /// `pc`.
+    pub fn should_break(&self, pc: u16) -> bool {
+        self.stepping || self.breakpoints.contains(&pc)
+    }
+
+    /// Prints the next instruction and reads commands from stdin until one
+    /// of them resumes the execution.
+    ///
+    /// Undoing instructions requires the rewind buffer to be enabled on the
+    /// `cpu` with `Cpu::enable_rewind`.
+    pub fn prompt(&mut self, cpu: &mut Cpu) -> Resume {
+        print_next_instruction(cpu);
+
+        loop {
+            let mut stdout = stdout();
+            let mut line = String::new();
+
+            stdout.write_all(b"> ").expect("Failed to write to stdout.");
+            stdout.flush().expect("Failed to flush stdout.");
+
+            if stdin().read_line(&mut line).unwrap_or(0) == 0 {
+                return Resume::Quit;
+            }
+
+            match parse_command(&line) {
+                Ok(Command::Break(addr)) => {
+                    self.breakpoints.insert(addr);
+                    println!("Breakpoint set at {:#06x}", addr);
+                }
+                Ok(Command::Delete(addr)) => {
+                    if self.breakpoints.remove(&addr) {
+                        println!("Breakpoint removed from {:#06x}", addr);
+                    } else {
+                        println!("No breakpoint at {:#06x}", addr);
+                    }
+                }
+                Ok(Command::Watch(watch)) => {
+                    cpu.watch(watch);
+                    println!("Watchpoint set on {}", watch);
+                }
+                Ok(Command::DeleteWatch(watch)) => {
+                    if cpu.unwatch(watch) {
+                        println!("Watchpoint removed from {}", watch);
+                    } else {
+                        println!("No watchpoint on {}", watch);
+                    }
+                }
+                Ok(Command::Examine(addr, len)) => print!("{}", format_memory(cpu, addr, len)),
+                Ok(Command::List(count)) => {
+                    for (addr, instr) in cpu.peek_instructions(count) {
+                        println!("{:#06x}  {}", addr, instr);
+                    }
+                }
+                Ok(Command::Registers) => print!("{}", format_registers(cpu)),
+                Ok(Command::Stack) => print!("{}", format_stack(cpu)),
+                Ok(Command::Continue) => {
+                    self.stepping = false;
+                    return Resume::Continue;
+                }
+                Ok(Command::Step) => {
+                    self.stepping = true;
+                    return Resume::Step;
+                }
+                Ok(Command::Undo) => {
+                    if cpu.rewind() {
+                        print_next_instruction(cpu);
+                    } else {
+                        println!("Nothing to undo");
+                    }
+                }
+                Ok(Command::Quit) => return Resume::Quit,
+                Err(err) => eprintln!("{}", err),
+            }
+        }
+    }
+}
+
+/// Prints the instruction the PC points to
+fn print_next_instruction(cpu: &Cpu) {
+    let pc = cpu.pc as usize;
+
+    if pc + 1 >= cpu.ram.size() {
+        println!("{:#06x}  out of memory", pc);
+        return;
+    }
+
+    let opcode = Opcode::from(u16::from_be_bytes([cpu.ram[pc], cpu.ram[pc + 1]]));
+
+    println!("{:#06x}  {}  {}", pc, opcode, opcode.decode());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cpu::Cpu;
+
+    use super::{
+        format_memory, format_registers, format_stack, parse_command, Command, Debugger, Watch,
+    };
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(parse_command("b 0x2A4\n"), Ok(Command::Break(0x2A4)));
+        assert_eq!(parse_command("d 2a4"), Ok(Command::Delete(0x2A4)));
+        assert_eq!(parse_command("c"), Ok(Command::Continue));
+        assert_eq!(parse_command("s"), Ok(Command::Step));
+        assert_eq!(parse_command("\n"), Ok(Command::Step), "ENTER steps");
+        assert_eq!(parse_command("q"), Ok(Command::Quit));
+        assert_eq!(parse_command("x 0x300"), Ok(Command::Examine(0x300, 16)));
+        assert_eq!(parse_command("x 300 4"), Ok(Command::Examine(0x300, 4)));
+        assert_eq!(parse_command("reg"), Ok(Command::Registers));
+        assert_eq!(parse_command("l"), Ok(Command::List(5)));
+        assert_eq!(parse_command("l 10"), Ok(Command::List(10)));
+        assert!(parse_command("l ten").is_err(), "Invalid count");
+        assert_eq!(parse_command("stack"), Ok(Command::Stack));
+        assert_eq!(parse_command("u"), Ok(Command::Undo));
+        assert_eq!(
+            parse_command("w v5"),
+            Ok(Command::Watch(Watch::Register(0x5)))
+        );
+        assert_eq!(
+            parse_command("w 0x300"),
+            Ok(Command::Watch(Watch::Memory(0x300)))
+        );
+        assert_eq!(
+            parse_command("dw VF"),
+            Ok(Command::DeleteWatch(Watch::Register(0xF)))
+        );
+        assert!(parse_command("w V10").is_err(), "Invalid register");
+        assert!(parse_command("w").is_err(), "Missing register");
+        assert!(parse_command("b").is_err(), "Missing address");
+        assert!(parse_command("b 0xZZZ").is_err(), "Invalid address");
+        assert!(parse_command("jump 0x200").is_err(), "Unknown command");
+    }
+
+    #[test]
+    fn breaks_on_breakpoints_when_running() {
+        let mut debugger = Debugger::new();
+
+        assert!(debugger.should_break(0x200), "Stops before the first step");
+
+        debugger.stepping = false;
+        debugger.breakpoints.insert(0x2A4);
+
+        assert!(!debugger.should_break(0x200));
+        assert!(debugger.should_break(0x2A4));
+    }
+
+    #[test]
+    fn formats_memory() {
+        let mut cpu = Cpu::new();
+
+        for offset in 0..20 {
+            cpu.ram[0x300 + offset] = offset as u8;
+        }
+
+        assert_eq!(
+            format_memory(&cpu, 0x300, 20),
+            "0x0300  00 01 02 03 04 05 06 07 08 09 0a 0b 0c 0d 0e 0f\n\
+             0x0310  10 11 12 13\n"
+        );
+        assert_eq!(
+            format_memory(&cpu, 0xFFE, 16),
+            "0x0ffe  00 00\n",
+            "Stops at the end of memory"
+        );
+    }
+
+    #[test]
+    fn formats_registers() {
+        let mut cpu = Cpu::new();
+
+        cpu.registers[0xF] = 0x01;
+        cpu.i = 0x300;
+
+        assert_eq!(
+            format_registers(&cpu),
+            "V0: 0x00  V1: 0x00  V2: 0x00  V3: 0x00  V4: 0x00  V5: 0x00  V6: 0x00  V7: 0x00\n\
+             V8: 0x00  V9: 0x00  VA: 0x00  VB: 0x00  VC: 0x00  VD: 0x00  VE: 0x00  VF: 0x01\n\
+             I: 0x0300  PC: 0x0200  DT: 0x00  ST: 0x00\n"
+        );
+    }
+
+    #[test]
+    fn formats_stack() {
+        let mut cpu = Cpu::new();
+
+        assert_eq!(format_stack(&cpu), "Empty stack\n");
+
+        cpu.stack.push(0x202).unwrap();
+        cpu.stack.push(0x2A6).unwrap();
+
+        assert_eq!(format_stack(&cpu), "#0  0x02a6\n#1  0x0202\n");
+    }
+}
@@ -0,0 +1,65 @@
+use crate::memory::USER_SPACE_STR;
+use crate::opcode::{Instruction, Opcode};
+
+/// Walks ROM bytes two at a time decoding each pair as an `Opcode`.
+///
+/// Every entry holds the memory address where the opcode would live once
+/// loaded (starting at the user space, 0x200), the raw `Opcode` and the
+/// decoded `Instruction`. A trailing odd byte is decoded as the high byte of
+/// an opcode whose low byte is 0.
+pub fn disassemble(bytes: &[u8]) -> Vec<(u16, Opcode, Instruction)> {
+    bytes
+        .chunks(2)
+        .enumerate()
+        .map(|(index, chunk)| {
+            let address = (USER_SPACE_STR + index * 2) as u16;
+            let hexa = (chunk[0] as u16) << 8 | *chunk.get(1).unwrap_or(&0) as u16;
+            let opcode = Opcode::from(hexa);
+            let instr = opcode.decode();
+
+            (address, opcode, instr)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::disassemble;
+
+    #[test]
+    fn disassembles_rom_into_mnemonics() {
+        let rom = vec![
+            0x00, 0xE0, // CLS
+            0x6A, 0x02, // LD VA, 0x02
+            0xA2, 0x2A, // LD I, 0x22A
+            0xDA, 0xB5, // DRW VA, VB, 5
+            0x12, 0x00, // JP 0x200
+        ];
+        let listing = disassemble(&rom);
+        let addresses: Vec<u16> = listing.iter().map(|(addr, _, _)| *addr).collect();
+        let mnemonics: Vec<String> = listing
+            .iter()
+            .map(|(_, _, instr)| instr.to_string())
+            .collect();
+
+        assert_eq!(addresses, vec![0x200, 0x202, 0x204, 0x206, 0x208]);
+        assert_eq!(
+            mnemonics,
+            vec![
+                "CLS",
+                "LD VA, 0x02",
+                "LD I, 0x22A",
+                "DRW VA, VB, 5",
+                "JP 0x200"
+            ]
+        );
+    }
+
+    #[test]
+    fn disassembles_trailing_odd_byte() {
+        let listing = disassemble(&[0x00, 0xE0, 0x12]);
+
+        assert_eq!(listing.len(), 2);
+        assert_eq!(listing[1].1.to_string(), "0x1200");
+    }
+}
@@ -1,23 +1,239 @@
 use std::ops::{Index, IndexMut};
 use std::ptr;
 
-use super::SCREEN_AREA;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
+use super::{HIRES_SCREEN_AREA, HIRES_SCREEN_HEIGHT, HIRES_SCREEN_WIDTH};
+use super::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// Pixels mapped to the output display.
+///
+/// The buffer is sized for the SUPER-CHIP high resolution mode (128x64) and
+/// keeps track of the active resolution. Cells are indexed row by row using
+/// the active width, so in low resolution mode (64x32) only the first
+/// `SCREEN_AREA` cells are used.
+///
+/// Each cell holds a bit per XO-CHIP bitplane, bit 0 for the first plane and
+/// bit 1 for the second one. Programs drawing to the first plane only (the
+/// default) just set cells to 0 or 1.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub struct DisplayBuffer(pub(crate) [u8; SCREEN_AREA]);
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DisplayBuffer {
+    #[cfg_attr(feature = "serde", serde(with = "crate::state::bytes"))]
+    pub(crate) pixels: [u8; HIRES_SCREEN_AREA],
+    pub(crate) hires: bool,
+}
 
 impl DisplayBuffer {
     pub fn reset(&mut self) {
         unsafe {
-            let buff = self.0.as_mut_ptr();
-            ptr::write_bytes(buff, 0, SCREEN_AREA);
+            let buff = self.pixels.as_mut_ptr();
+            ptr::write_bytes(buff, 0, HIRES_SCREEN_AREA);
+        }
+    }
+
+    /// Clears the bitplanes selected by `planes` (see `Cpu::planes`),
+    /// leaving the other planes untouched.
+    pub fn clear_planes(&mut self, planes: u8) {
+        for pixel in self.pixels.iter_mut() {
+            *pixel &= !planes;
+        }
+    }
+
+    /// Whether the SUPER-CHIP high resolution mode is active
+    pub fn is_hires(&self) -> bool {
+        self.hires
+    }
+
+    /// Switches between low (64x32) and high (128x64) resolution modes,
+    /// clearing the buffer when the resolution changes.
+    pub fn set_hires(&mut self, hires: bool) {
+        if self.hires != hires {
+            self.hires = hires;
+            self.reset();
+        }
+    }
+
+    /// Width in pixels for the active resolution
+    pub fn width(&self) -> u32 {
+        if self.hires {
+            HIRES_SCREEN_WIDTH
+        } else {
+            SCREEN_WIDTH
         }
     }
+
+    /// Height in pixels for the active resolution
+    pub fn height(&self) -> u32 {
+        if self.hires {
+            HIRES_SCREEN_HEIGHT
+        } else {
+            SCREEN_HEIGHT
+        }
+    }
+
+    /// Moves every row `n` pixels down, clearing the vacated rows at the top.
+    pub fn scroll_down(&mut self, n: u32) {
+        let width = self.width() as usize;
+        let area = self.area();
+        let offset = (n as usize * width).min(area);
+
+        self.pixels.copy_within(0..area - offset, offset);
+        self.pixels[..offset].fill(0);
+    }
+
+    /// Moves every column `n` pixels right, clearing the vacated columns at
+    /// the left.
+    pub fn scroll_right(&mut self, n: u32) {
+        let width = self.width() as usize;
+        let n = (n as usize).min(width);
+        let area = self.area();
+
+        for row in self.pixels[..area].chunks_mut(width) {
+            row.copy_within(0..width - n, n);
+            row[..n].fill(0);
+        }
+    }
+
+    /// Moves every column `n` pixels left, clearing the vacated columns at
+    /// the right.
+    pub fn scroll_left(&mut self, n: u32) {
+        let width = self.width() as usize;
+        let n = (n as usize).min(width);
+        let area = self.area();
+
+        for row in self.pixels[..area].chunks_mut(width) {
+            row.copy_within(n.., 0);
+            row[width - n..].fill(0);
+        }
+    }
+
+    /// XORs `sprite` into the first bitplane with its top-left corner at
+    /// (`x`, `y`), taken modulo the active resolution. Each byte is a row of
+    /// 8 pixels, most significant bit first.
+    ///
+    /// Sprites crossing the right or bottom edges wrap around to the
+    /// opposite edges if `wrap` is set and are clipped otherwise. Returns
+    /// whether any pixel was turned off.
+    pub fn draw_sprite(&mut self, x: u8, y: u8, sprite: &[u8], wrap: bool) -> bool {
+        self.draw_sprite_on_plane(0b01, x, y, sprite, wrap)
+    }
+
+    /// Draws a sprite as `DisplayBuffer::draw_sprite` does, into the
+    /// bitplane `plane` (0b01 for the first one, 0b10 for the second one).
+    pub fn draw_sprite_on_plane(
+        &mut self,
+        plane: u8,
+        x: u8,
+        y: u8,
+        sprite: &[u8],
+        wrap: bool,
+    ) -> bool {
+        self.draw_sprite_reporting(plane, x, y, sprite, wrap, |_, _| {})
+    }
+
+    /// Draws a sprite as `DisplayBuffer::draw_sprite_on_plane` does,
+    /// calling `on_collision` with the coordinates of every pixel turned
+    /// off.
+    pub fn draw_sprite_reporting(
+        &mut self,
+        plane: u8,
+        x: u8,
+        y: u8,
+        sprite: &[u8],
+        wrap: bool,
+        mut on_collision: impl FnMut(u32, u32),
+    ) -> bool {
+        let width = self.width();
+        let height = self.height();
+        let x = x as u32 % width;
+        let y = y as u32 % height;
+        let (cols, rows) = if wrap {
+            (8, sprite.len() as u32)
+        } else {
+            ((width - x).min(8), (height - y).min(sprite.len() as u32))
+        };
+        let mut collision = false;
+
+        for (row, bits) in sprite.iter().take(rows as usize).enumerate() {
+            let pixel_y = (y + row as u32) % height;
+            let base = (pixel_y * width) as usize;
+
+            for col in 0..cols {
+                if bits << col & 0x80 > 0 {
+                    let pixel_x = (x + col) % width;
+                    let pixel = &mut self.pixels[base + pixel_x as usize];
+
+                    *pixel ^= plane;
+
+                    if *pixel & plane == 0 {
+                        collision = true;
+                        on_collision(pixel_x, pixel_y);
+                    }
+                }
+            }
+        }
+
+        collision
+    }
+
+    /// Renders the top-left `width` x `height` region of the buffer as text,
+    /// one line per row, using `█` for set pixels and spaces for clear ones.
+    ///
+    /// Dimensions beyond the active resolution are clamped.
+    pub fn to_ascii(&self, width: u32, height: u32) -> String {
+        let stride = self.width();
+        let width = width.min(stride);
+        let height = height.min(self.height());
+        let mut ascii = String::with_capacity(((width + 1) * height) as usize);
+
+        for row in 0..height {
+            for col in 0..width {
+                if self.pixels[(row * stride + col) as usize] > 0 {
+                    ascii.push('█');
+                } else {
+                    ascii.push(' ');
+                }
+            }
+
+            ascii.push('\n');
+        }
+
+        ascii
+    }
+
+    /// Cells changed from `self` to `other`, as their index and new value.
+    ///
+    /// Meant to send frames as deltas, all cells are compared whatever the
+    /// active resolution so a resolution change should be sent along.
+    pub fn diff(&self, other: &DisplayBuffer) -> Vec<(usize, u8)> {
+        self.pixels
+            .iter()
+            .zip(other.pixels.iter())
+            .enumerate()
+            .filter(|(_, (prev, next))| prev != next)
+            .map(|(index, (_, next))| (index, *next))
+            .collect()
+    }
+
+    /// Number of pixels for the active resolution
+    fn area(&self) -> usize {
+        (self.width() * self.height()) as usize
+    }
+
+    /// Pixels for the active resolution
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels[..self.area()]
+    }
 }
 
 impl Default for DisplayBuffer {
     fn default() -> Self {
-        DisplayBuffer([0x0; SCREEN_AREA])
+        DisplayBuffer {
+            pixels: [0x0; HIRES_SCREEN_AREA],
+            hires: false,
+        }
     }
 }
 
@@ -25,12 +241,174 @@ impl Index<usize> for DisplayBuffer {
     type Output = u8;
 
     fn index(&self, index: usize) -> &Self::Output {
-        &self.0[index]
+        &self.pixels[index]
     }
 }
 
 impl IndexMut<usize> for DisplayBuffer {
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        &mut self.0[index]
+        &mut self.pixels[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DisplayBuffer;
+
+    #[test]
+    fn scroll_down_moves_rows() {
+        let mut buff = DisplayBuffer::default();
+
+        buff[10] = 1;
+        buff.scroll_down(3);
+
+        assert_eq!(buff[10], 0, "Vacated pixel is cleared");
+        assert_eq!(buff[3 * 64 + 10], 1, "Pixel moved 3 rows down");
+    }
+
+    #[test]
+    fn scroll_down_drops_rows_past_the_bottom() {
+        let mut buff = DisplayBuffer::default();
+
+        buff[31 * 64] = 1;
+        buff.scroll_down(1);
+
+        assert!(buff.pixels().iter().all(|x| *x == 0));
+    }
+
+    #[test]
+    fn scroll_right_moves_columns() {
+        let mut buff = DisplayBuffer::default();
+
+        buff[64 + 2] = 1;
+        buff[64 + 62] = 1;
+        buff.scroll_right(4);
+
+        assert_eq!(buff[64 + 2], 0, "Vacated pixel is cleared");
+        assert_eq!(buff[64 + 6], 1, "Pixel moved 4 columns right");
+        assert_eq!(buff[2 * 64 + 2], 0, "Pixels don't wrap into next row");
+    }
+
+    #[test]
+    fn scroll_left_moves_columns() {
+        let mut buff = DisplayBuffer::default();
+
+        buff[64 + 10] = 1;
+        buff[64 + 1] = 1;
+        buff.scroll_left(4);
+
+        assert_eq!(buff[64 + 10], 0, "Vacated pixel is cleared");
+        assert_eq!(buff[64 + 6], 1, "Pixel moved 4 columns left");
+        assert_eq!(buff[63], 0, "Pixels don't wrap into previous row");
+    }
+
+    #[test]
+    fn scroll_uses_active_resolution() {
+        let mut buff = DisplayBuffer::default();
+
+        buff.set_hires(true);
+        buff[128 + 100] = 1;
+        buff.scroll_down(2);
+        buff.scroll_right(4);
+
+        assert_eq!(buff[3 * 128 + 104], 1);
+    }
+
+    #[test]
+    fn diff_lists_changed_cells() {
+        let prev = DisplayBuffer::default();
+        let mut next = prev;
+
+        next[0] = 1;
+        next[64 + 5] = 3;
+        next[31 * 64 + 63] = 1;
+
+        assert_eq!(
+            prev.diff(&next),
+            vec![(0, 1), (64 + 5, 3), (31 * 64 + 63, 1)]
+        );
+        assert_eq!(
+            next.diff(&prev),
+            vec![(0, 0), (64 + 5, 0), (31 * 64 + 63, 0)]
+        );
+        assert!(next.diff(&next).is_empty());
+    }
+
+    #[test]
+    fn draw_sprite_detects_collisions() {
+        let mut buff = DisplayBuffer::default();
+
+        assert!(!buff.draw_sprite(2, 1, &[0b1100_0000], false));
+        assert_eq!((buff[64 + 2], buff[64 + 3]), (1, 1));
+        assert!(
+            buff.draw_sprite(3, 1, &[0b1000_0000], false),
+            "Turning a pixel off collides"
+        );
+        assert_eq!((buff[64 + 2], buff[64 + 3]), (1, 0));
+        assert!(!buff.draw_sprite(3, 1, &[0b1000_0000], false));
+    }
+
+    #[test]
+    fn draw_sprite_wraps_around_the_edges() {
+        let mut buff = DisplayBuffer::default();
+
+        buff.draw_sprite(62, 31, &[0b1110_0000, 0b1000_0000], true);
+
+        assert_eq!(buff[31 * 64 + 62], 1);
+        assert_eq!(buff[31 * 64 + 63], 1);
+        assert_eq!(buff[31 * 64], 1, "Wraps to the left edge");
+        assert_eq!(buff[62], 1, "Wraps to the top edge");
+    }
+
+    #[test]
+    fn draw_sprite_clips_at_the_edges() {
+        let mut buff = DisplayBuffer::default();
+
+        buff.draw_sprite(62 + 64, 31, &[0b1110_0000, 0b1000_0000], false);
+
+        assert_eq!(buff[31 * 64 + 62], 1, "Coordinates are taken modulo 64");
+        assert_eq!(buff[31 * 64 + 63], 1);
+        assert_eq!(buff.pixels().iter().filter(|pixel| **pixel > 0).count(), 2);
+    }
+
+    #[test]
+    fn draw_sprite_reports_erased_pixels() {
+        let mut buff = DisplayBuffer::default();
+        let mut erased = Vec::new();
+
+        buff.draw_sprite(63, 0, &[0b1100_0000], true);
+
+        assert!(
+            buff.draw_sprite_reporting(0b01, 62, 0, &[0b1110_0000], true, |x, y| {
+                erased.push((x, y))
+            })
+        );
+        assert_eq!(erased, vec![(63, 0), (0, 0)], "Wrapped coordinates");
+    }
+
+    #[test]
+    fn draw_sprite_on_plane_leaves_other_planes_untouched() {
+        let mut buff = DisplayBuffer::default();
+
+        buff[0] = 0b01;
+
+        assert!(!buff.draw_sprite_on_plane(0b10, 0, 0, &[0b1000_0000], false));
+        assert_eq!(buff[0], 0b11);
+    }
+
+    #[test]
+    fn to_ascii_renders_rows() {
+        let mut buff = DisplayBuffer::default();
+
+        buff[0] = 1;
+        buff[64 + 1] = 1;
+
+        assert_eq!(buff.to_ascii(2, 2), "█ \n █\n");
+        assert_eq!(buff.to_ascii(64, 32).lines().count(), 32);
+        assert_eq!(
+            buff.to_ascii(128, 64).lines().count(),
+            32,
+            "Clamps to the active resolution"
+        );
     }
 }
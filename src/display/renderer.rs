@@ -0,0 +1,90 @@
+#[cfg(feature = "screenshot")]
+use std::path::Path;
+
+#[cfg(feature = "screenshot")]
+use anyhow::{anyhow, Result};
+
+use super::buffer::DisplayBuffer;
+
+/// Display backend the `DisplayBuffer` is rendered to.
+///
+/// `System` renders through a `Box<dyn Renderer>` chosen by the `--backend`
+/// option, `HeadlessSystem` accepts one with `HeadlessSystem::set_renderer`.
+pub trait Renderer {
+    /// Draws the buffer, replacing the previous frame
+    fn render(&mut self, buff: &DisplayBuffer);
+
+    /// Clears the output, the next render draws the whole buffer
+    fn clear(&mut self);
+
+    /// Signals that the following buffers are `width` x `height` pixels,
+    /// called before rendering the first buffer and on every resolution
+    /// change.
+    fn set_resolution(&mut self, width: u32, height: u32);
+
+    /// Whether the output changes on every frame, even if the buffer
+    /// doesn't
+    fn fades(&self) -> bool {
+        false
+    }
+
+    /// Switches between windowed and fullscreen modes, if supported
+    fn toggle_fullscreen(&mut self) {}
+
+    /// Writes the last rendered frame to a PNG file at `path`
+    #[cfg(feature = "screenshot")]
+    fn screenshot(&self, _path: &Path) -> Result<()> {
+        Err(anyhow!("Screenshots are not supported by this backend"))
+    }
+}
+
+/// Renders a `DisplayBuffer` through a `Renderer` on frame boundaries,
+/// only when it changed since the last render.
+pub(crate) struct Output {
+    pub(crate) renderer: Box<dyn Renderer>,
+    /// Whether the buffer changed since the last render
+    dirty: bool,
+    /// Resolution of the last rendered buffer
+    resolution: Option<(u32, u32)>,
+}
+
+impl Output {
+    pub(crate) fn new(renderer: Box<dyn Renderer>) -> Self {
+        Self {
+            renderer,
+            dirty: false,
+            resolution: None,
+        }
+    }
+
+    /// Flags the buffer as changed, to be rendered on the next `flush`
+    pub(crate) fn invalidate(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Renders the buffer if it changed or the renderer fades pixels out
+    pub(crate) fn flush(&mut self, buff: &DisplayBuffer) {
+        if self.dirty || self.renderer.fades() {
+            self.render(buff);
+        }
+    }
+
+    /// Renders the buffer right away
+    pub(crate) fn render(&mut self, buff: &DisplayBuffer) {
+        let resolution = (buff.width(), buff.height());
+
+        if self.resolution != Some(resolution) {
+            self.renderer.set_resolution(resolution.0, resolution.1);
+            self.resolution = Some(resolution);
+        }
+
+        self.renderer.render(buff);
+        self.dirty = false;
+    }
+
+    /// Clears the output, the buffer is rendered again on the next `flush`
+    pub(crate) fn clear(&mut self) {
+        self.renderer.clear();
+        self.dirty = true;
+    }
+}
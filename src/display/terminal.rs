@@ -0,0 +1,182 @@
+use std::env;
+use std::fmt;
+use std::io::{self, Stdout, Write};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+use log::warn;
+
+use super::buffer::DisplayBuffer;
+use super::renderer::Renderer;
+
+/// Moves the cursor to the top-left corner of the terminal
+const CURSOR_HOME: &str = "\x1b[H";
+/// Clears the terminal from the cursor to the end of the screen
+const CLEAR_TO_END: &str = "\x1b[J";
+/// Clears the whole terminal
+const CLEAR_SCREEN: &str = "\x1b[2J";
+
+/// Where the display output is rendered to
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// An SDL window
+    #[default]
+    Sdl,
+    /// The terminal the emulator runs in, no window is created
+    Terminal,
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Backend::Sdl => write!(f, "sdl"),
+            Backend::Terminal => write!(f, "terminal"),
+        }
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sdl" => Ok(Backend::Sdl),
+            "terminal" => Ok(Backend::Terminal),
+            _ => Err(format!(
+                "Unknown backend \"{}\", expected one of: sdl, terminal",
+                s
+            )),
+        }
+    }
+}
+
+/// Renders the display output to the terminal using ANSI escapes, packing
+/// two rows of pixels into each line with half block characters.
+pub struct TerminalDisplay {
+    out: Stdout,
+}
+
+impl TerminalDisplay {
+    /// Creates a `TerminalDisplay` writing to the standard output
+    pub fn new() -> Self {
+        let mut display = Self { out: io::stdout() };
+
+        display.clear();
+        display
+    }
+
+    /// Writes to the terminal, output errors are not worth stopping the
+    /// emulation for
+    fn write(&mut self, output: &str) {
+        let mut out = self.out.lock();
+        let _ = out.write_all(output.as_bytes()).and_then(|_| out.flush());
+    }
+}
+
+impl Renderer for TerminalDisplay {
+    /// Redraws the whole display over the previous frame
+    fn render(&mut self, buff: &DisplayBuffer) {
+        let frame = half_blocks(buff, buff.width(), buff.height());
+
+        self.write(&format!("{}{}{}", CURSOR_HOME, frame, CLEAR_TO_END));
+    }
+
+    fn clear(&mut self) {
+        self.write(&format!("{}{}", CLEAR_SCREEN, CURSOR_HOME));
+    }
+
+    /// Clears the terminal for the new frame size, warning if the terminal
+    /// is too narrow to fit it
+    fn set_resolution(&mut self, width: u32, _height: u32) {
+        if let Some(columns) = columns().filter(|columns| *columns < width) {
+            warn!(
+                "The terminal is {} columns wide, the display needs at least {}",
+                columns, width
+            );
+        }
+
+        self.clear();
+    }
+}
+
+impl Default for TerminalDisplay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Renders the top-left `width` x `height` region of the buffer as text,
+/// each line holding two rows of pixels drawn with `▀`, `▄` and `█`.
+///
+/// Dimensions beyond the active resolution are clamped.
+pub(crate) fn half_blocks(buff: &DisplayBuffer, width: u32, height: u32) -> String {
+    let stride = buff.width();
+    let width = width.min(stride);
+    let height = height.min(buff.height());
+    let pixel = |row: u32, col: u32| row < height && buff[(row * stride + col) as usize] > 0;
+    let mut text = String::with_capacity(((width * 3 + 1) * height.div_ceil(2)) as usize);
+
+    for row in (0..height).step_by(2) {
+        for col in 0..width {
+            text.push(match (pixel(row, col), pixel(row + 1, col)) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            });
+        }
+
+        text.push('\n');
+    }
+
+    text
+}
+
+/// Width of the terminal in columns, if it can be found out
+fn columns() -> Option<u32> {
+    if let Some(columns) = env::var("COLUMNS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+    {
+        return Some(columns);
+    }
+
+    let output = Command::new("tput")
+        .arg("cols")
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::display::buffer::DisplayBuffer;
+
+    use super::{half_blocks, Backend};
+
+    #[test]
+    fn packs_two_rows_per_line() {
+        let mut buff = DisplayBuffer::default();
+
+        // Column 0 is set on both rows, column 1 on the top row only and
+        // column 2 on the bottom row only
+        buff[0] = 1;
+        buff[64] = 1;
+        buff[1] = 1;
+        buff[64 + 2] = 1;
+        buff[2 * 64 + 3] = 1;
+
+        assert_eq!(half_blocks(&buff, 4, 3), "█▀▄ \n   ▀\n");
+        assert_eq!(half_blocks(&buff, 64, 32).lines().count(), 16);
+    }
+
+    #[test]
+    fn parses_backend_names() {
+        assert_eq!("sdl".parse(), Ok(Backend::Sdl));
+        assert_eq!("Terminal".parse(), Ok(Backend::Terminal));
+        assert!("wgpu".parse::<Backend>().is_err());
+    }
+}
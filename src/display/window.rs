@@ -0,0 +1,622 @@
+#[cfg(feature = "screenshot")]
+use std::fs::File;
+#[cfg(feature = "screenshot")]
+use std::io::BufWriter;
+#[cfg(feature = "screenshot")]
+use std::path::Path;
+
+#[cfg(feature = "screenshot")]
+use anyhow::{anyhow, Result};
+use log::warn;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, Texture};
+use sdl2::video::{FullscreenType, Window};
+use sdl2::Sdl;
+
+use super::buffer::DisplayBuffer;
+use super::renderer::Renderer;
+use super::{HIRES_SCREEN_AREA, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// Window scale used unless it doesn't fit the screen
+pub const DEFAULT_SCALE: u32 = 12;
+pub const BACKGROUND_COLOR: Color = Color::RGB(u8::MIN, u8::MIN, u8::MIN);
+pub const FOREGROUND_COLOR: Color = Color::RGB(u8::MAX, u8::MAX, u8::MAX);
+/// Color for pixels set in the second XO-CHIP bitplane only
+pub const PLANE_2_COLOR: Color = Color::RGB(0xAA, 0xAA, 0xAA);
+/// Color for pixels set in both XO-CHIP bitplanes
+pub const BOTH_PLANES_COLOR: Color = Color::RGB(0x55, 0x55, 0x55);
+
+/// Intensity below which a fading pixel is considered off
+const MIN_INTENSITY: f32 = 0.05;
+
+/// Colors for the combinations of XO-CHIP bitplanes a pixel is set in,
+/// indexed by the plane bits of its `DisplayBuffer` cell: none (the
+/// background), the first plane only (the foreground), the second plane
+/// only and both planes. Programs not using bitplanes only show the first
+/// two.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Palette(pub [Color; 4]);
+
+impl Palette {
+    /// Color for a `DisplayBuffer` cell
+    pub fn color(&self, cell: u8) -> Color {
+        self.0[palette_index(cell)]
+    }
+
+    /// Color for pixels turned off
+    pub fn background(&self) -> Color {
+        self.0[0b00]
+    }
+
+    /// Color for pixels set in the first plane only
+    pub fn foreground(&self) -> Color {
+        self.0[0b01]
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette([
+            BACKGROUND_COLOR,
+            FOREGROUND_COLOR,
+            PLANE_2_COLOR,
+            BOTH_PLANES_COLOR,
+        ])
+    }
+}
+
+pub struct Display {
+    pub(crate) canvas: Canvas<Window>,
+    /// Render target holding the last rendered frame. Only changed cells are
+    /// drawn into it, then it's copied to the window canvas.
+    pub(crate) frame: Texture,
+    /// Last rendered buffer, `None` forces a full redraw on the next render
+    pub(crate) last_frame: Option<DisplayBuffer>,
+    pub(crate) scale: u32,
+    pub(crate) palette: Palette,
+    /// Phosphor decay factor, cleared pixels fade out by this factor on each
+    /// render instead of vanishing. Disabled if `None`.
+    pub(crate) decay: Option<f32>,
+    /// Intensity of each pixel, from 0 (background) to 1 (foreground). Only
+    /// used when `decay` is enabled.
+    pub(crate) intensity: Vec<f32>,
+}
+
+impl Display {
+    pub fn new(context: &Sdl, title: &str, scale: u32, palette: Palette) -> Self {
+        let video = context.video().unwrap();
+        let window = video
+            .window(title, SCREEN_WIDTH * scale, SCREEN_HEIGHT * scale)
+            .position_centered()
+            .build()
+            .unwrap();
+        let canvas = window.into_canvas().build().unwrap();
+        let frame = canvas
+            .create_texture_target(None, SCREEN_WIDTH * scale, SCREEN_HEIGHT * scale)
+            .unwrap();
+
+        Self {
+            canvas,
+            frame,
+            last_frame: None,
+            scale,
+            palette,
+            decay: None,
+            intensity: vec![0.0; HIRES_SCREEN_AREA],
+        }
+    }
+
+    /// Largest scale up to `DEFAULT_SCALE` for the window to fit the primary
+    /// screen, `DEFAULT_SCALE` if the screen size is unknown.
+    pub fn fit_scale(context: &Sdl) -> u32 {
+        context
+            .video()
+            .and_then(|video| video.desktop_display_mode(0))
+            .map_or(DEFAULT_SCALE, |mode| {
+                fit_scale(mode.w.max(0) as u32, mode.h.max(0) as u32)
+            })
+    }
+
+    pub fn clear(&mut self) {
+        self.last_frame = None;
+        self.canvas.set_draw_color(self.palette.background());
+        self.canvas.clear();
+        self.canvas.present();
+    }
+
+    /// Whether the phosphor decay is enabled, fading pixels must be rendered
+    /// on every frame
+    pub fn fades(&self) -> bool {
+        self.decay.is_some()
+    }
+
+    /// Renders the `DisplayBuffer` into the window.
+    ///
+    /// Only cells that changed since the last render are drawn, a full
+    /// redraw happens on the first render, after a `clear` or when the
+    /// resolution changes.
+    pub fn render(&mut self, buff: &DisplayBuffer) {
+        let width = buff.width();
+        let scale = self.scale;
+        let faded = self
+            .decay
+            .map(|decay| update_intensity(&mut self.intensity, buff.pixels(), decay));
+        let cells = dirty_cells(self.last_frame.as_ref(), buff, faded);
+        let palette = self.palette;
+        let intensity = &self.intensity;
+        let fades = self.decay.is_some();
+
+        self.canvas
+            .with_texture_canvas(&mut self.frame, |canvas| {
+                for index in cells {
+                    let color = if fades {
+                        blend(palette.background(), palette.foreground(), intensity[index])
+                    } else {
+                        palette.color(buff[index])
+                    };
+                    let (col, row) = (index as u32 % width, index as u32 / width);
+
+                    if let Some(rect) = cell_rect(col, row, width, scale) {
+                        canvas.set_draw_color(color);
+                        canvas.fill_rect(rect).unwrap();
+                    }
+                }
+            })
+            .unwrap();
+
+        self.last_frame = Some(*buff);
+        self.present();
+    }
+
+    /// Whether the window is in fullscreen mode
+    pub fn is_fullscreen(&self) -> bool {
+        self.canvas.window().fullscreen_state() != FullscreenType::Off
+    }
+
+    /// Switches the window between windowed and desktop fullscreen modes,
+    /// the last rendered frame is presented again to fill the new size.
+    pub fn set_fullscreen(&mut self, fullscreen: bool) {
+        let mode = if fullscreen {
+            FullscreenType::Desktop
+        } else {
+            FullscreenType::Off
+        };
+
+        if let Err(err) = self.canvas.window_mut().set_fullscreen(mode) {
+            warn!("Failed to switch fullscreen mode: {}", err);
+        }
+
+        self.present();
+    }
+
+    /// Copies the frame into the window, letterboxed to keep the aspect ratio
+    fn present(&mut self) {
+        let (width, height) = self.canvas.output_size().unwrap();
+        let target = letterbox(
+            width,
+            height,
+            SCREEN_WIDTH * self.scale,
+            SCREEN_HEIGHT * self.scale,
+        );
+
+        self.canvas.set_draw_color(self.palette.background());
+        self.canvas.clear();
+        self.canvas.copy(&self.frame, None, target).unwrap();
+        self.canvas.present();
+    }
+
+    /// Writes the last rendered frame to a PNG file at `path`, scaled to the
+    /// window size.
+    #[cfg(feature = "screenshot")]
+    pub fn screenshot(&self, path: &Path) -> Result<()> {
+        let buff = self
+            .last_frame
+            .as_ref()
+            .ok_or_else(|| anyhow!("Nothing rendered yet"))?;
+        let pixels = rgb_pixels(buff, self.scale, &self.palette);
+        let mut encoder = png::Encoder::new(
+            BufWriter::new(File::create(path)?),
+            SCREEN_WIDTH * self.scale,
+            SCREEN_HEIGHT * self.scale,
+        );
+
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&pixels)?;
+
+        Ok(())
+    }
+}
+
+impl Renderer for Display {
+    fn render(&mut self, buff: &DisplayBuffer) {
+        Display::render(self, buff);
+    }
+
+    fn clear(&mut self) {
+        Display::clear(self);
+    }
+
+    /// Pixels are scaled to fill the window on every resolution, a full
+    /// redraw is all it takes
+    fn set_resolution(&mut self, _width: u32, _height: u32) {
+        self.last_frame = None;
+    }
+
+    fn fades(&self) -> bool {
+        Display::fades(self)
+    }
+
+    fn toggle_fullscreen(&mut self) {
+        self.set_fullscreen(!self.is_fullscreen());
+    }
+
+    #[cfg(feature = "screenshot")]
+    fn screenshot(&self, path: &Path) -> Result<()> {
+        Display::screenshot(self, path)
+    }
+}
+
+/// Largest rectangle with the aspect ratio of a `logical_width` by
+/// `logical_height` frame fitting a `width` by `height` window, centered
+/// with bars filling the remaining space.
+pub(crate) fn letterbox(width: u32, height: u32, logical_width: u32, logical_height: u32) -> Rect {
+    // Widths and heights are compared cross-multiplied to avoid rounding
+    let (target_width, target_height) =
+        if width as u64 * logical_height as u64 > height as u64 * logical_width as u64 {
+            (
+                (height as u64 * logical_width as u64 / logical_height as u64) as u32,
+                height,
+            )
+        } else {
+            (
+                width,
+                (width as u64 * logical_height as u64 / logical_width as u64) as u32,
+            )
+        };
+
+    Rect::new(
+        ((width - target_width) / 2) as i32,
+        ((height - target_height) / 2) as i32,
+        target_width,
+        target_height,
+    )
+}
+
+/// Rectangle of the window covered by the cell at `col` and `row` of a
+/// `width` cells wide display, the window being sized for the low resolution
+/// mode at `scale`.
+///
+/// Pixels shrink when the high resolution mode is active. Their edges are
+/// rounded down, so cells cover the window without gaps for odd scales. At
+/// scale 1 half of the cells round down to nothing, `None` is returned for
+/// these as the next cell covers them.
+pub(crate) fn cell_rect(col: u32, row: u32, width: u32, scale: u32) -> Option<Rect> {
+    let edge = |cell: u32| cell * scale * SCREEN_WIDTH / width;
+    let (x, y) = (edge(col), edge(row));
+    let (cell_width, cell_height) = (edge(col + 1) - x, edge(row + 1) - y);
+
+    if cell_width == 0 || cell_height == 0 {
+        return None;
+    }
+
+    Some(Rect::new(x as i32, y as i32, cell_width, cell_height))
+}
+
+/// Largest scale up to `DEFAULT_SCALE` for a low resolution window to fit
+/// in a `width` by `height` screen, leaving room for window decorations.
+pub(crate) fn fit_scale(width: u32, height: u32) -> u32 {
+    let fits = (width * 9 / 10 / SCREEN_WIDTH).min(height * 9 / 10 / SCREEN_HEIGHT);
+
+    fits.clamp(1, DEFAULT_SCALE)
+}
+
+/// Updates the `intensity` of each pixel for a new frame. Set pixels light up
+/// at full intensity while cleared pixels fade out by the `decay` factor.
+///
+/// Returns the indexes of the cells whose intensity changed.
+pub(crate) fn update_intensity(intensity: &mut [f32], pixels: &[u8], decay: f32) -> Vec<usize> {
+    let mut changed = Vec::new();
+
+    for (index, (intensity, pixel)) in intensity.iter_mut().zip(pixels).enumerate() {
+        let previous = *intensity;
+
+        if *pixel > 0 {
+            *intensity = 1.0;
+        } else if *intensity * decay >= MIN_INTENSITY {
+            *intensity *= decay;
+        } else {
+            *intensity = 0.0;
+        }
+
+        if *intensity != previous {
+            changed.push(index);
+        }
+    }
+
+    changed
+}
+
+/// Indexes of the cells to draw when rendering `current` after `previous`.
+///
+/// Every cell is drawn if there's no `previous` buffer or the resolution
+/// changed. Otherwise only cells that changed are drawn, these are the
+/// `faded` ones when the phosphor decay is enabled.
+pub(crate) fn dirty_cells(
+    previous: Option<&DisplayBuffer>,
+    current: &DisplayBuffer,
+    faded: Option<Vec<usize>>,
+) -> Vec<usize> {
+    match (previous, faded) {
+        (Some(previous), _) if previous.is_hires() != current.is_hires() => {
+            (0..current.pixels().len()).collect()
+        }
+        (None, _) => (0..current.pixels().len()).collect(),
+        (Some(_), Some(faded)) => faded,
+        (Some(previous), None) => previous
+            .pixels()
+            .iter()
+            .zip(current.pixels())
+            .enumerate()
+            .filter(|(_, (previous, current))| previous != current)
+            .map(|(index, _)| index)
+            .collect(),
+    }
+}
+
+/// Converts `buff` into RGB bytes, row by row, with each pixel taking
+/// `scale` by `scale` pixels in the low resolution mode as in the window.
+pub fn rgb_pixels(buff: &DisplayBuffer, scale: u32, palette: &Palette) -> Vec<u8> {
+    let (width, height) = (buff.width(), buff.height());
+    let (image_width, image_height) = (SCREEN_WIDTH * scale, SCREEN_HEIGHT * scale);
+    let mut pixels = Vec::with_capacity((image_width * image_height * 3) as usize);
+
+    for y in 0..image_height {
+        for x in 0..image_width {
+            // Inverse of `cell_rect`
+            let col = ((x + 1) * width - 1) / image_width;
+            let row = ((y + 1) * height - 1) / image_height;
+            let color = palette.color(buff[(row * width + col) as usize]);
+
+            pixels.extend_from_slice(&[color.r, color.g, color.b]);
+        }
+    }
+
+    pixels
+}
+
+/// Index of the `Palette` color for a `DisplayBuffer` cell, the bits of the
+/// two planes it's set in
+pub(crate) fn palette_index(cell: u8) -> usize {
+    (cell & 0b11) as usize
+}
+
+/// Mixes `background` and `foreground` colors by `intensity`
+fn blend(background: Color, foreground: Color, intensity: f32) -> Color {
+    let mix = |bg: u8, fg: u8| (bg as f32 + (fg as f32 - bg as f32) * intensity).round() as u8;
+
+    Color::RGB(
+        mix(background.r, foreground.r),
+        mix(background.g, foreground.g),
+        mix(background.b, foreground.b),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::pixels::Color;
+
+    use crate::display::buffer::DisplayBuffer;
+    use sdl2::rect::Rect;
+
+    use super::{blend, cell_rect, dirty_cells, fit_scale, letterbox, palette_index, rgb_pixels};
+    use super::{update_intensity, Palette, BOTH_PLANES_COLOR, PLANE_2_COLOR};
+    use crate::display::{SCREEN_AREA, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+    #[test]
+    fn draws_only_changed_cells() {
+        let previous = DisplayBuffer::default();
+        let mut current = previous;
+
+        current[10] = 1;
+        current[64 + 3] = 1;
+        current[31 * 64 + 63] = 1;
+
+        assert_eq!(
+            dirty_cells(Some(&previous), &current, None),
+            vec![10, 64 + 3, 31 * 64 + 63]
+        );
+        assert_eq!(
+            dirty_cells(None, &current, None).len(),
+            SCREEN_AREA,
+            "First render draws every cell"
+        );
+    }
+
+    #[test]
+    fn draws_every_cell_on_resolution_change() {
+        let previous = DisplayBuffer::default();
+        let mut current = previous;
+
+        current.set_hires(true);
+
+        assert_eq!(
+            dirty_cells(Some(&previous), &current, Some(Vec::new())).len(),
+            current.pixels().len()
+        );
+    }
+
+    #[test]
+    fn cleared_pixels_fade_out() {
+        let mut intensity = [0.0; 1];
+        let mut sequence = Vec::new();
+
+        update_intensity(&mut intensity, &[1], 0.5);
+        sequence.push(intensity[0]);
+
+        for _ in 0..6 {
+            update_intensity(&mut intensity, &[0], 0.5);
+            sequence.push(intensity[0]);
+        }
+
+        assert_eq!(sequence[0], 1.0, "Set pixels are at full intensity");
+        assert!(
+            sequence.windows(2).all(|w| w[1] < w[0] || w[1] == 0.0),
+            "Intensity decreases monotonically: {:?}",
+            sequence
+        );
+        assert_eq!(*sequence.last().unwrap(), 0.0, "Pixel is eventually off");
+    }
+
+    #[test]
+    fn blends_colors_by_intensity() {
+        let bg = Color::RGB(0x00, 0x00, 0x00);
+        let fg = Color::RGB(0xFF, 0xB0, 0x00);
+
+        assert_eq!(blend(bg, fg, 0.0), bg);
+        assert_eq!(blend(bg, fg, 1.0), fg);
+        assert_eq!(blend(bg, fg, 0.5), Color::RGB(0x80, 0x58, 0x00));
+    }
+
+    #[test]
+    fn converts_buffer_to_rgb_pixels() {
+        let (fg, bg) = (Color::RGB(0x33, 0xFF, 0x66), Color::RGB(0x10, 0x20, 0x30));
+        let palette = Palette([bg, fg, PLANE_2_COLOR, BOTH_PLANES_COLOR]);
+        let mut buff = DisplayBuffer::default();
+
+        buff[1] = 1;
+
+        let pixels = rgb_pixels(&buff, 2, &palette);
+        let pixel = |x: usize, y: usize| &pixels[(y * 128 + x) * 3..][..3];
+
+        assert_eq!(pixels.len(), 128 * 64 * 3);
+        assert_eq!(pixel(0, 0), [0x10, 0x20, 0x30]);
+        assert_eq!(pixel(2, 0), [0x33, 0xFF, 0x66]);
+        assert_eq!(pixel(3, 1), [0x33, 0xFF, 0x66], "Pixels are scaled");
+        assert_eq!(pixel(4, 0), [0x10, 0x20, 0x30]);
+
+        buff.set_hires(true);
+        buff[1] = 1;
+
+        let pixels = rgb_pixels(&buff, 2, &palette);
+
+        assert_eq!(pixels.len(), 128 * 64 * 3, "Same size in high resolution");
+        assert_eq!(&pixels[3..6], [0x33, 0xFF, 0x66]);
+        assert_eq!(&pixels[6..9], [0x10, 0x20, 0x30]);
+    }
+
+    #[test]
+    fn converts_high_resolution_buffer_at_any_scale() {
+        let palette = Palette([Color::BLACK, Color::WHITE, PLANE_2_COLOR, BOTH_PLANES_COLOR]);
+        let mut buff = DisplayBuffer::default();
+
+        buff.set_hires(true);
+        buff[128 * 64 - 1] = 1;
+
+        let pixels = rgb_pixels(&buff, 1, &palette);
+
+        assert_eq!(pixels.len(), 64 * 32 * 3);
+        assert_eq!(&pixels[pixels.len() - 3..], [0xFF, 0xFF, 0xFF]);
+
+        let pixels = rgb_pixels(&buff, 11, &palette);
+
+        assert_eq!(pixels.len(), 704 * 352 * 3);
+        assert_eq!(&pixels[pixels.len() - 3..], [0xFF, 0xFF, 0xFF]);
+        assert_eq!(&pixels[pixels.len() - 6 * 3..][..3], [0xFF, 0xFF, 0xFF]);
+        assert_eq!(&pixels[pixels.len() - 7 * 3..][..3], [0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn cells_cover_the_window_at_any_scale() {
+        for scale in [1, 2, 11, 12] {
+            for width in [SCREEN_WIDTH, SCREEN_WIDTH * 2] {
+                let rects: Vec<Rect> = (0..width)
+                    .filter_map(|col| cell_rect(col, 1, width, scale))
+                    .collect();
+                let last = rects[rects.len() - 1];
+
+                assert_eq!(rects[0].x(), 0);
+                assert_eq!(
+                    last.x() as u32 + last.width(),
+                    SCREEN_WIDTH * scale,
+                    "Reaches the right edge at scale {}",
+                    scale
+                );
+
+                for pair in rects.windows(2) {
+                    assert_eq!(
+                        pair[1].x(),
+                        pair[0].x() + pair[0].width() as i32,
+                        "No gaps or overlaps at scale {}",
+                        scale
+                    );
+                }
+            }
+        }
+
+        assert_eq!(cell_rect(0, 0, SCREEN_WIDTH * 2, 1), None);
+        assert_eq!(
+            cell_rect(1, 1, SCREEN_WIDTH * 2, 1),
+            Some(Rect::new(0, 0, 1, 1))
+        );
+        assert_eq!(
+            cell_rect(3, 2, SCREEN_WIDTH, 11),
+            Some(Rect::new(33, 22, 11, 11))
+        );
+    }
+
+    #[test]
+    fn fits_scale_to_the_screen() {
+        assert_eq!(fit_scale(1920, 1080), 12);
+        assert_eq!(fit_scale(800, 600), 11);
+        assert_eq!(fit_scale(640, 480), 9);
+        assert_eq!(fit_scale(32, 16), 1, "Never below 1");
+    }
+
+    #[test]
+    fn letterboxes_frame_to_keep_aspect_ratio() {
+        let letterbox = |width, height| letterbox(width, height, SCREEN_WIDTH, SCREEN_HEIGHT);
+
+        assert_eq!(letterbox(768, 384), Rect::new(0, 0, 768, 384));
+        assert_eq!(
+            letterbox(1920, 1080),
+            Rect::new(0, 60, 1920, 960),
+            "Bars above and below"
+        );
+        assert_eq!(
+            letterbox(1000, 300),
+            Rect::new(200, 0, 600, 300),
+            "Bars on the sides"
+        );
+    }
+
+    #[test]
+    fn maps_plane_bits_to_palette_entries() {
+        assert_eq!(palette_index(0b00), 0);
+        assert_eq!(palette_index(0b01), 1);
+        assert_eq!(palette_index(0b10), 2);
+        assert_eq!(palette_index(0b11), 3);
+        assert_eq!(palette_index(0b101), 1, "Only the first two planes count");
+    }
+
+    #[test]
+    fn composites_bitplanes_into_colors() {
+        let colors = [
+            Color::RGB(0x10, 0x10, 0x10),
+            Color::RGB(0xFF, 0xB0, 0x00),
+            Color::RGB(0xFF, 0x66, 0x00),
+            Color::RGB(0x66, 0x22, 0x00),
+        ];
+        let palette = Palette(colors);
+
+        for (cell, color) in colors.iter().enumerate() {
+            assert_eq!(palette.color(cell as u8), *color);
+        }
+
+        assert_eq!(palette.background(), colors[0]);
+        assert_eq!(palette.foreground(), colors[1]);
+        assert_eq!(Palette::default().color(0b10), PLANE_2_COLOR);
+        assert_eq!(Palette::default().color(0b11), BOTH_PLANES_COLOR);
+    }
+}
@@ -0,0 +1,283 @@
+use crate::cpu::{Cpu, CpuError, LoadError, CLOCK_RATE, FRAME_RATE};
+use crate::display::buffer::DisplayBuffer;
+use crate::display::renderer::{Output, Renderer};
+use crate::hooks::{BeepCallback, FrameCallback, Hooks};
+use crate::keypad::KeypadState;
+use crate::quirks::Quirks;
+use crate::rom::Rom;
+
+/// Runs a ROM without SDL, for automated tests and environments with no
+/// display or audio.
+///
+/// Keys are set by the caller instead of being polled, and the display
+/// output is kept in a `DisplayBuffer` to be queried. Time is measured in
+/// cycles rather than wall clock time, frames (vertical blanks) happen every
+/// `CLOCK_RATE / FRAME_RATE` cycles.
+pub struct HeadlessSystem {
+    cpu: Cpu,
+    keys: KeypadState,
+    cycles_per_frame: u64,
+    hooks: Hooks,
+    /// Renders the display output on every frame if `Some`
+    display: Option<Output>,
+}
+
+impl HeadlessSystem {
+    /// Creates a `HeadlessSystem` with the `rom` loaded, emulating the
+    /// provided `quirks`.
+    ///
+    /// Fails if the ROM doesn't fit in memory.
+    pub fn new(rom: Rom, quirks: Quirks) -> Result<Self, LoadError> {
+        Self::with_cpu(Cpu::new(), rom, quirks)
+    }
+
+    /// Creates a `HeadlessSystem` as `HeadlessSystem::new` does, with the
+    /// random number generator seeded from `seed` for reproducible runs.
+    pub fn with_seed(rom: Rom, quirks: Quirks, seed: u64) -> Result<Self, LoadError> {
+        Self::with_cpu(Cpu::with_seed(seed), rom, quirks)
+    }
+
+    fn with_cpu(mut cpu: Cpu, rom: Rom, quirks: Quirks) -> Result<Self, LoadError> {
+        cpu.quirks = quirks;
+        cpu.load(rom)?;
+
+        Ok(Self {
+            cpu,
+            keys: KeypadState::default(),
+            cycles_per_frame: (CLOCK_RATE / FRAME_RATE) as u64,
+            hooks: Hooks::default(),
+            display: None,
+        })
+    }
+
+    /// Renders the display output through `renderer` on every frame it
+    /// changed
+    pub fn set_renderer(&mut self, renderer: Box<dyn Renderer>) {
+        self.display = Some(Output::new(renderer));
+    }
+
+    /// Registers a callback invoked on every frame with the display output.
+    /// Keep the previous frame and `DisplayBuffer::diff` it to get the
+    /// changed cells only.
+    pub fn on_frame(&mut self, callback: FrameCallback) {
+        self.hooks.set_on_frame(callback);
+    }
+
+    /// Registers a callback invoked when the beeper starts or stops
+    pub fn on_beep(&mut self, callback: BeepCallback) {
+        self.hooks.set_on_beep(callback);
+    }
+
+    /// Sets the keys held down for the following cycles
+    pub fn set_keys(&mut self, keys: KeypadState) {
+        self.keys = keys;
+    }
+
+    /// Runs `n` cycles, signaling a vertical blank every
+    /// `CLOCK_RATE / FRAME_RATE` cycles.
+    pub fn run_cycles(&mut self, n: u64) -> Result<(), CpuError> {
+        for _ in 0..n {
+            let cycle_output = self.cpu.cycle(self.keys)?;
+
+            self.hooks.beep(cycle_output.beep);
+
+            if let Some(display) = self
+                .display
+                .as_mut()
+                .filter(|_| cycle_output.display_update)
+            {
+                display.invalidate();
+            }
+
+            if self.cpu.cycle_count().is_multiple_of(self.cycles_per_frame) {
+                self.cpu.vblank();
+                self.hooks.frame(&self.cpu.display_buffer);
+
+                if let Some(display) = self.display.as_mut() {
+                    display.flush(&self.cpu.display_buffer);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs cycles for `n` frames
+    pub fn run_frames(&mut self, n: u64) -> Result<(), CpuError> {
+        self.run_cycles(n * self.cycles_per_frame)
+    }
+
+    /// Restarts the loaded ROM, see `Cpu::reset`
+    pub fn reset(&mut self) {
+        self.cpu.reset();
+
+        if let Some(display) = self.display.as_mut() {
+            display.clear();
+        }
+    }
+
+    /// Current display output
+    pub fn display_buffer(&self) -> &DisplayBuffer {
+        &self.cpu.display_buffer
+    }
+
+    /// Whether the beeper would be sounding
+    pub fn beeping(&self) -> bool {
+        self.cpu.st > 0
+    }
+
+    pub fn cpu(&self) -> &Cpu {
+        &self.cpu
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::ptr;
+    use std::rc::Rc;
+
+    use crate::cpu::LoadError;
+    use crate::display::buffer::DisplayBuffer;
+    use crate::display::renderer::Renderer;
+    use crate::keypad::KeypadState;
+    use crate::quirks::Quirks;
+
+    use super::HeadlessSystem;
+
+    #[test]
+    fn snapshots_display_output() {
+        // Draws the key pressed as a digit in the top left corner:
+        //
+        // 0x200: WAIT V0
+        // 0x202: CLS
+        // 0x204: LD F, V0
+        // 0x206: DRW V1, V1, 5
+        // 0x208: JP 0x208
+        let rom = vec![0xF0, 0x0A, 0x00, 0xE0, 0xF0, 0x29, 0xD1, 0x15, 0x12, 0x08];
+        let mut system = HeadlessSystem::new(rom.into(), Quirks::default()).unwrap();
+
+        system.run_frames(2).unwrap();
+
+        assert_eq!(system.display_buffer().to_ascii(5, 5), "     \n".repeat(5));
+
+        system.set_keys(KeypadState::from_keys(&[0x7]));
+        system.run_cycles(100).unwrap();
+
+        assert_eq!(
+            system.display_buffer().to_ascii(5, 5),
+            "████ \n   █ \n  █  \n █   \n █   \n"
+        );
+        assert_eq!(system.cpu().cycle_count(), 120);
+    }
+
+    #[test]
+    fn reads_back_the_display_buffer() {
+        // 0x200: DRW V0, V0, 5
+        // 0x202: JP 0x202
+        let rom = vec![0xD0, 0x05, 0x12, 0x02];
+        let mut system = HeadlessSystem::new(rom.into(), Quirks::default()).unwrap();
+
+        system.run_cycles(4).unwrap();
+
+        assert!(system
+            .display_buffer()
+            .pixels()
+            .iter()
+            .any(|pixel| *pixel > 0));
+        assert!(
+            ptr::eq(system.display_buffer(), &system.cpu().display_buffer),
+            "Borrows the CPU display buffer"
+        );
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    enum Call {
+        Render,
+        Clear,
+        SetResolution(u32, u32),
+    }
+
+    /// Records the calls it gets
+    struct MockRenderer(Rc<RefCell<Vec<Call>>>);
+
+    impl Renderer for MockRenderer {
+        fn render(&mut self, _buff: &DisplayBuffer) {
+            self.0.borrow_mut().push(Call::Render);
+        }
+
+        fn clear(&mut self) {
+            self.0.borrow_mut().push(Call::Clear);
+        }
+
+        fn set_resolution(&mut self, width: u32, height: u32) {
+            self.0.borrow_mut().push(Call::SetResolution(width, height));
+        }
+    }
+
+    #[test]
+    fn renders_changed_frames() {
+        // 0x200: CLS
+        // 0x202: DRW V0, V0, 5
+        // 0x204: HIGH
+        // 0x206: JP 0x206
+        let rom = vec![0x00, 0xE0, 0xD0, 0x05, 0x00, 0xFF, 0x12, 0x06];
+        let mut system = HeadlessSystem::new(rom.into(), Quirks::default()).unwrap();
+        let calls = Rc::new(RefCell::new(Vec::new()));
+
+        system.set_renderer(Box::new(MockRenderer(Rc::clone(&calls))));
+        system.run_frames(3).unwrap();
+        system.reset();
+        system.run_frames(2).unwrap();
+
+        assert_eq!(
+            *calls.borrow(),
+            vec![
+                Call::SetResolution(128, 64),
+                Call::Render,
+                Call::Clear,
+                Call::Render,
+            ],
+            "Unchanged frames are not rendered"
+        );
+    }
+
+    #[test]
+    fn invokes_frame_and_beep_callbacks() {
+        // 0x200: LD V0, 2
+        // 0x202: LD ST, V0
+        // 0x206: JP 0x206
+        let rom = vec![0x60, 0x02, 0xF0, 0x18, 0x00, 0x00, 0x12, 0x06];
+        let mut system = HeadlessSystem::new(rom.into(), Quirks::default()).unwrap();
+        let frames = Rc::new(RefCell::new(0));
+        let beeps = Rc::new(RefCell::new(Vec::new()));
+
+        system.on_frame(Box::new({
+            let frames = Rc::clone(&frames);
+
+            move |_| *frames.borrow_mut() += 1
+        }));
+        system.on_beep(Box::new({
+            let beeps = Rc::clone(&beeps);
+
+            move |beeping| beeps.borrow_mut().push(beeping)
+        }));
+        system.run_frames(5).unwrap();
+
+        assert_eq!(*frames.borrow(), 5);
+        assert_eq!(*beeps.borrow(), vec![true, false], "Beeps for 2 frames");
+    }
+
+    #[test]
+    fn rejects_roms_not_fitting_in_memory() {
+        let rom = vec![0x00; 4000];
+
+        assert_eq!(
+            HeadlessSystem::new(rom.into(), Quirks::default()).err(),
+            Some(LoadError::TooLarge {
+                size: 4000,
+                available: 3584
+            })
+        );
+    }
+}
@@ -0,0 +1,50 @@
+use crate::display::buffer::DisplayBuffer;
+
+/// Callback invoked on every frame with the display output
+pub type FrameCallback = Box<dyn FnMut(&DisplayBuffer)>;
+
+/// Callback invoked when the beeper starts (`true`) or stops (`false`)
+pub type BeepCallback = Box<dyn FnMut(bool)>;
+
+/// Callbacks registered by hosts embedding the emulator to observe its
+/// output, e.g. to record video or drive their own audio. None are
+/// registered by default.
+#[derive(Default)]
+pub struct Hooks {
+    on_frame: Option<FrameCallback>,
+    on_beep: Option<BeepCallback>,
+    /// Last beeper state reported to `on_beep`
+    beeping: bool,
+}
+
+impl Hooks {
+    /// Registers the callback invoked on every frame (vertical blank)
+    pub fn set_on_frame(&mut self, callback: FrameCallback) {
+        self.on_frame = Some(callback);
+    }
+
+    /// Registers the callback invoked when the beeper state changes
+    pub fn set_on_beep(&mut self, callback: BeepCallback) {
+        self.on_beep = Some(callback);
+    }
+
+    /// Reports a frame with the provided display output
+    pub(crate) fn frame(&mut self, display_buffer: &DisplayBuffer) {
+        if let Some(on_frame) = self.on_frame.as_mut() {
+            on_frame(display_buffer);
+        }
+    }
+
+    /// Reports the beeper state, the callback is only invoked on changes
+    pub(crate) fn beep(&mut self, beeping: bool) {
+        if self.beeping == beeping {
+            return;
+        }
+
+        self.beeping = beeping;
+
+        if let Some(on_beep) = self.on_beep.as_mut() {
+            on_beep(beeping);
+        }
+    }
+}
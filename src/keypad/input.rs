@@ -0,0 +1,573 @@
+use log::warn;
+use sdl2::controller::{Button, GameController};
+use sdl2::event::Event;
+use sdl2::keyboard::{Keycode, Scancode};
+use sdl2::{EventPump, GameControllerSubsystem};
+use std::str::FromStr;
+
+use super::KeypadState;
+
+/// Signals raised by the user while polling the keypad, interrupting the
+/// regular keypad state retrieval.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PollSignal {
+    /// The window was closed or the Escape key was pressed
+    Quit,
+    /// The Spacebar was pressed, toggling the paused state
+    Pause,
+    /// F1 was pressed, requesting to restart the ROM
+    Reset,
+    /// F5 was pressed, requesting a quick save
+    SaveState,
+    /// F9 was pressed, requesting to restore the quick save
+    LoadState,
+    /// F12 was pressed, requesting a screenshot
+    Screenshot,
+    /// F11 was pressed, toggling fullscreen mode
+    ToggleFullscreen,
+    /// F7 was pressed, requesting to lower the volume
+    VolumeDown,
+    /// F8 was pressed, requesting to raise the volume
+    VolumeUp,
+}
+
+impl PollSignal {
+    /// Maps an Sdl2 `Event` into the `PollSignal` it raises, if any.
+    pub fn from_event(event: &Event) -> Option<PollSignal> {
+        match event {
+            Event::Quit { .. }
+            | Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                ..
+            } => Some(PollSignal::Quit),
+            Event::KeyDown {
+                keycode: Some(Keycode::Space),
+                repeat: false,
+                ..
+            } => Some(PollSignal::Pause),
+            Event::KeyDown {
+                keycode: Some(Keycode::F1),
+                repeat: false,
+                ..
+            } => Some(PollSignal::Reset),
+            Event::KeyDown {
+                keycode: Some(Keycode::F5),
+                repeat: false,
+                ..
+            } => Some(PollSignal::SaveState),
+            Event::KeyDown {
+                keycode: Some(Keycode::F9),
+                repeat: false,
+                ..
+            } => Some(PollSignal::LoadState),
+            Event::KeyDown {
+                keycode: Some(Keycode::F12),
+                repeat: false,
+                ..
+            } => Some(PollSignal::Screenshot),
+            Event::KeyDown {
+                keycode: Some(Keycode::F11),
+                repeat: false,
+                ..
+            } => Some(PollSignal::ToggleFullscreen),
+            Event::KeyDown {
+                keycode: Some(Keycode::F7),
+                ..
+            } => Some(PollSignal::VolumeDown),
+            Event::KeyDown {
+                keycode: Some(Keycode::F8),
+                ..
+            } => Some(PollSignal::VolumeUp),
+            _ => None,
+        }
+    }
+}
+
+/// COSMAC VIP keys in the order they are laid out on the keypad, row by row
+const KEYPAD_LAYOUT: [usize; 16] = [
+    0x1, 0x2, 0x3, 0xC, //
+    0x4, 0x5, 0x6, 0xD, //
+    0x7, 0x8, 0x9, 0xE, //
+    0xA, 0x0, 0xB, 0xF,
+];
+
+/// Physical keyboard keys mapped to each of the 16 COSMAC VIP keys.
+///
+/// Parsed from the 16 keys matching the keypad layout row by row (see
+/// `Keypad`), either as 16 characters (`1234qwerasdfzxcv`, the default) or
+/// as whitespace separated key names (`Up Down Left Right ...`).
+///
+/// Keys are named after the character they type on a US QWERTY keyboard,
+/// but mapped by scancode: the key at the position of `q` is used whatever
+/// the keyboard layout is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyMap([Scancode; 16]);
+
+impl KeyMap {
+    /// COSMAC VIP key mapped to `scancode`, if any
+    pub fn key_for(&self, scancode: Scancode) -> Option<usize> {
+        self.0.iter().position(|mapped| *mapped == scancode)
+    }
+
+    /// COSMAC VIP key mapped to the key typing `c` on a US QWERTY keyboard,
+    /// if any
+    pub fn key_for_char(&self, c: char) -> Option<usize> {
+        char_scancode(c.to_ascii_lowercase()).and_then(|scancode| self.key_for(scancode))
+    }
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        "1234qwerasdfzxcv".parse().unwrap()
+    }
+}
+
+impl FromStr for KeyMap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let names: Vec<String> = if s.split_whitespace().count() == 1 {
+            s.trim().chars().map(String::from).collect()
+        } else {
+            s.split_whitespace().map(String::from).collect()
+        };
+
+        if names.len() != KEYPAD_LAYOUT.len() {
+            return Err(format!(
+                "Expected 16 keys for the keymap, found {}",
+                names.len()
+            ));
+        }
+
+        let mut keys = [None; 16];
+
+        for (position, name) in names.iter().enumerate() {
+            let scancode =
+                parse_scancode(name).ok_or_else(|| format!("Unknown key \"{}\"", name))?;
+
+            // Compared once parsed, as different names may be the same key
+            if keys.contains(&Some(scancode)) {
+                return Err(format!("Key \"{}\" is mapped more than once", name));
+            }
+
+            keys[KEYPAD_LAYOUT[position]] = Some(scancode);
+        }
+
+        Ok(KeyMap(keys.map(|key| key.expect("Every key is mapped"))))
+    }
+}
+
+/// Game controller buttons mapped to each of the 16 COSMAC VIP keys.
+///
+/// Parsed from 16 whitespace separated button names matching the keypad
+/// layout row by row (see `Keypad`), `-` leaving a key unmapped. Buttons are
+/// named as SDL does (`a`, `start`, `dpup`, `leftshoulder`...). By default
+/// the d-pad is mapped to 2, 4, 6 and 8, the keys most games move with, and
+/// the `a` button to 5:
+///
+/// ```text
+/// leftshoulder dpup    -     rightshoulder
+/// dpleft       a       dpright -
+/// -            dpdown  -     back
+/// x            b       y     start
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ControllerMap([Option<Button>; 16]);
+
+impl ControllerMap {
+    /// COSMAC VIP key mapped to `button`, if any
+    pub fn key_for(&self, button: Button) -> Option<usize> {
+        self.0.iter().position(|mapped| *mapped == Some(button))
+    }
+}
+
+impl Default for ControllerMap {
+    fn default() -> Self {
+        "leftshoulder dpup - rightshoulder dpleft a dpright - - dpdown - back x b y start"
+            .parse()
+            .unwrap()
+    }
+}
+
+impl FromStr for ControllerMap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let names: Vec<&str> = s.split_whitespace().collect();
+
+        if names.len() != KEYPAD_LAYOUT.len() {
+            return Err(format!(
+                "Expected 16 buttons for the controller map, found {}",
+                names.len()
+            ));
+        }
+
+        let mut buttons = [None; 16];
+
+        for (position, name) in names.iter().enumerate() {
+            if *name == "-" {
+                continue;
+            }
+
+            let button =
+                parse_button(name).ok_or_else(|| format!("Unknown button \"{}\"", name))?;
+
+            if buttons.contains(&Some(button)) {
+                return Err(format!("Button \"{}\" is mapped more than once", name));
+            }
+
+            buttons[KEYPAD_LAYOUT[position]] = Some(button);
+        }
+
+        Ok(ControllerMap(buttons))
+    }
+}
+
+/// Parses a button name as SDL does, case insensitive. Names are matched
+/// here as `Button::from_string` needs SDL to be initialized.
+fn parse_button(name: &str) -> Option<Button> {
+    let button = match name.to_lowercase().as_str() {
+        "a" => Button::A,
+        "b" => Button::B,
+        "x" => Button::X,
+        "y" => Button::Y,
+        "back" => Button::Back,
+        "guide" => Button::Guide,
+        "start" => Button::Start,
+        "leftstick" => Button::LeftStick,
+        "rightstick" => Button::RightStick,
+        "leftshoulder" => Button::LeftShoulder,
+        "rightshoulder" => Button::RightShoulder,
+        "dpup" => Button::DPadUp,
+        "dpdown" => Button::DPadDown,
+        "dpleft" => Button::DPadLeft,
+        "dpright" => Button::DPadRight,
+        _ => return None,
+    };
+
+    Some(button)
+}
+
+/// Parses a single character as the key that types it on a US QWERTY
+/// keyboard, or a key name such as `Left` or `Keypad 5`.
+fn parse_scancode(name: &str) -> Option<Scancode> {
+    let mut chars = name.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_graphic() => char_scancode(c.to_ascii_lowercase()),
+        _ => Scancode::from_name(name),
+    }
+}
+
+/// Scancode of the key typing `c` on a US QWERTY keyboard. Matched here as
+/// `Scancode::from_name` needs SDL to be loaded.
+fn char_scancode(c: char) -> Option<Scancode> {
+    // Letters and digits have consecutive scancodes
+    let offset = |c: char, first: char, scancode: Scancode| {
+        Scancode::from_i32(scancode as i32 + (c as i32 - first as i32))
+    };
+
+    match c {
+        'a'..='z' => offset(c, 'a', Scancode::A),
+        '1'..='9' => offset(c, '1', Scancode::Num1),
+        '0' => Some(Scancode::Num0),
+        '-' => Some(Scancode::Minus),
+        '=' => Some(Scancode::Equals),
+        '[' => Some(Scancode::LeftBracket),
+        ']' => Some(Scancode::RightBracket),
+        '\\' => Some(Scancode::Backslash),
+        ';' => Some(Scancode::Semicolon),
+        '\'' => Some(Scancode::Apostrophe),
+        '`' => Some(Scancode::Grave),
+        ',' => Some(Scancode::Comma),
+        '.' => Some(Scancode::Period),
+        '/' => Some(Scancode::Slash),
+        _ => None,
+    }
+}
+
+/// Source of the keypad state polled by `System` on every cycle, chosen by
+/// the `--backend` option along with the `Renderer`.
+pub trait Input {
+    /// Polls pending events and retrieves the keypad state.
+    ///
+    /// Returns `Err` with the corresponding `PollSignal` if any of the events
+    /// raised one.
+    fn poll(&mut self) -> Result<KeypadState, PollSignal>;
+}
+
+/// COSMAC VIP Keypad implementation mapped from modern PC's.
+///
+/// Mapping is achieved as follows by default, and can be changed with a
+/// custom `KeyMap`:
+///
+/// COSMAC VIP Keypad Positions
+///
+/// 1 2 3 C
+/// 4 5 6 D
+/// 7 8 9 E
+/// A 0 B F
+///
+/// Modern PC's Equivalent
+///
+/// 1 2 3 4
+/// Q W E R
+/// A S D F
+/// Z X C V
+///
+/// The Spacebar is reserved to pause and resume the emulation, F1 to restart
+/// the ROM, F5 and F9 to quick save and load the emulator state and F7 and
+/// F8 to lower and raise the volume.
+///
+/// Game controllers are supported as well once enabled with
+/// `Keypad::enable_controllers`, their buttons mapped with a `ControllerMap`
+/// and merged with the keyboard state.
+///
+/// Keypad implementation uses scancodes instead of string constants to bring
+/// support for different keyboard layouts, the keys are mapped by their
+/// position on the keyboard rather than the character they type.
+pub struct Keypad {
+    event_pump: EventPump,
+    keymap: KeyMap,
+    /// Opens game controllers as they are connected, `None` until enabled
+    /// with `Keypad::enable_controllers`
+    controller_subsystem: Option<GameControllerSubsystem>,
+    controller_map: ControllerMap,
+    /// Game controllers connected
+    controllers: Vec<GameController>,
+}
+
+impl Keypad {
+    /// Creates a new Keypad and polls events from Sdl2's `EventPump`.
+    pub fn new(event_pump: EventPump, keymap: KeyMap) -> Self {
+        Self {
+            event_pump,
+            keymap,
+            controller_subsystem: None,
+            controller_map: ControllerMap::default(),
+            controllers: Vec::new(),
+        }
+    }
+
+    /// Reads the buttons of the game controllers connected, mapped to the
+    /// COSMAC VIP keys with `controller_map`. Controllers already connected
+    /// are picked up on the following polls.
+    pub fn enable_controllers(
+        &mut self,
+        subsystem: GameControllerSubsystem,
+        controller_map: ControllerMap,
+    ) {
+        self.controller_subsystem = Some(subsystem);
+        self.controller_map = controller_map;
+    }
+
+    /// Waits for a `KeyDown` event and returns `true` if the pressed key's
+    /// keycode is the expected one. Otherwise returns `false`.
+    pub fn wait_for_key(&mut self, _key: Keycode) -> bool {
+        let event = self.event_pump.wait_event();
+
+        matches!(
+            event,
+            Event::KeyDown {
+                keycode: Some(_key),
+                ..
+            }
+        )
+    }
+
+    /// Retrieve pressed keys from Event Pump which matches any of the
+    /// COSMAC VIP keys.
+    fn pressed_keys(&self) -> KeypadState {
+        let mut keypad_state = KeypadState::default();
+
+        self.event_pump
+            .keyboard_state()
+            .pressed_scancodes()
+            .filter_map(|scancode| self.keymap.key_for(scancode))
+            .for_each(|key| keypad_state[key] = true);
+
+        for controller in self.controllers.iter() {
+            for (key, button) in self.controller_map.0.iter().enumerate() {
+                if button.is_some_and(|button| controller.button(button)) {
+                    keypad_state[key] = true;
+                }
+            }
+        }
+
+        keypad_state
+    }
+}
+
+impl Input for Keypad {
+    fn poll(&mut self) -> Result<KeypadState, PollSignal> {
+        let subsystem = self.controller_subsystem.as_ref();
+        let controllers = &mut self.controllers;
+
+        if let Some(signal) = self.event_pump.poll_iter().find_map(|e| {
+            match (&e, subsystem) {
+                // Sent on startup for the controllers already connected too
+                (Event::ControllerDeviceAdded { which, .. }, Some(subsystem)) => {
+                    match subsystem.open(*which) {
+                        Ok(controller) => controllers.push(controller),
+                        Err(err) => warn!("Failed to open game controller: {}", err),
+                    }
+                }
+                (Event::ControllerDeviceRemoved { which, .. }, _) => {
+                    controllers.retain(|controller| controller.instance_id() != *which);
+                }
+                _ => {}
+            }
+
+            PollSignal::from_event(&e)
+        }) {
+            return Err(signal);
+        }
+
+        Ok(self.pressed_keys())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::controller::Button;
+    use sdl2::event::Event;
+    use sdl2::keyboard::{Keycode, Mod, Scancode};
+
+    use super::{ControllerMap, KeyMap, PollSignal};
+
+    fn key_down(keycode: Keycode) -> Event {
+        Event::KeyDown {
+            timestamp: 0,
+            window_id: 0,
+            keycode: Some(keycode),
+            scancode: None,
+            keymod: Mod::NOMOD,
+            repeat: false,
+        }
+    }
+
+    #[test]
+    fn events_raise_poll_signals() {
+        let events = [
+            key_down(Keycode::Q),
+            key_down(Keycode::Escape),
+            Event::Quit { timestamp: 0 },
+            key_down(Keycode::Space),
+            key_down(Keycode::F1),
+            key_down(Keycode::F5),
+            key_down(Keycode::F9),
+            key_down(Keycode::F12),
+            key_down(Keycode::F11),
+            key_down(Keycode::F7),
+            key_down(Keycode::F8),
+        ];
+        let signals: Vec<Option<PollSignal>> = events.iter().map(PollSignal::from_event).collect();
+
+        assert_eq!(
+            signals,
+            vec![
+                None,
+                Some(PollSignal::Quit),
+                Some(PollSignal::Quit),
+                Some(PollSignal::Pause),
+                Some(PollSignal::Reset),
+                Some(PollSignal::SaveState),
+                Some(PollSignal::LoadState),
+                Some(PollSignal::Screenshot),
+                Some(PollSignal::ToggleFullscreen),
+                Some(PollSignal::VolumeDown),
+                Some(PollSignal::VolumeUp),
+            ]
+        );
+    }
+
+    #[test]
+    fn default_keymap_matches_physical_positions() {
+        let keymap = KeyMap::default();
+        let rows = [
+            [
+                Scancode::Num1,
+                Scancode::Num2,
+                Scancode::Num3,
+                Scancode::Num4,
+            ],
+            [Scancode::Q, Scancode::W, Scancode::E, Scancode::R],
+            [Scancode::A, Scancode::S, Scancode::D, Scancode::F],
+            [Scancode::Z, Scancode::X, Scancode::C, Scancode::V],
+        ];
+        let keys: Vec<Option<usize>> = rows
+            .iter()
+            .flatten()
+            .map(|scancode| keymap.key_for(*scancode))
+            .collect();
+
+        assert_eq!(
+            keys,
+            [0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB, 0xF]
+                .map(Some)
+        );
+        assert_eq!(keymap.key_for(Scancode::P), None);
+    }
+
+    #[test]
+    fn parses_custom_keymaps() {
+        let right_hand: KeyMap = "7890UIOPJKL;M,./".parse().unwrap();
+
+        assert_eq!(right_hand.key_for(Scancode::Num7), Some(0x1));
+        assert_eq!(right_hand.key_for(Scancode::U), Some(0x4));
+        assert_eq!(right_hand.key_for(Scancode::Semicolon), Some(0xE));
+
+        let names: KeyMap = "7 8 9 0 u i o p j k l m n , . /".parse().unwrap();
+
+        assert_eq!(names.key_for(Scancode::Comma), Some(0x0));
+        assert_eq!(names.key_for(Scancode::Slash), Some(0xF));
+
+        assert!("1234".parse::<KeyMap>().is_err(), "Missing keys");
+        assert!(
+            "1234qwerasdfzxcq".parse::<KeyMap>().is_err(),
+            "Repeated key"
+        );
+        assert!(
+            "1234qwerasdfzxcQ".parse::<KeyMap>().is_err(),
+            "Repeated key in another case"
+        );
+        assert!(
+            "1 2 3 4 q w e r a s d f z x c Q".parse::<KeyMap>().is_err(),
+            "Repeated key name in another case"
+        );
+    }
+
+    #[test]
+    fn maps_controller_buttons_to_keys() {
+        let controller_map = ControllerMap::default();
+
+        assert_eq!(controller_map.key_for(Button::DPadUp), Some(0x2));
+        assert_eq!(controller_map.key_for(Button::DPadLeft), Some(0x4));
+        assert_eq!(controller_map.key_for(Button::DPadRight), Some(0x6));
+        assert_eq!(controller_map.key_for(Button::DPadDown), Some(0x8));
+        assert_eq!(controller_map.key_for(Button::A), Some(0x5));
+        assert_eq!(controller_map.key_for(Button::Start), Some(0xF));
+        assert_eq!(controller_map.key_for(Button::Guide), None);
+
+        let pong: ControllerMap = "dpup - - DPDOWN a - - - - - - - - - - -".parse().unwrap();
+
+        assert_eq!(pong.key_for(Button::DPadUp), Some(0x1));
+        assert_eq!(pong.key_for(Button::DPadDown), Some(0xC));
+        assert_eq!(pong.key_for(Button::A), Some(0x4));
+        assert_eq!(pong.key_for(Button::B), None);
+
+        assert!("a b".parse::<ControllerMap>().is_err(), "Missing buttons");
+        assert!(
+            "a a - - - - - - - - - - - - - -"
+                .parse::<ControllerMap>()
+                .is_err(),
+            "Repeated button"
+        );
+        assert!(
+            "turbo - - - - - - - - - - - - - - -"
+                .parse::<ControllerMap>()
+                .is_err(),
+            "Unknown button"
+        );
+    }
+}
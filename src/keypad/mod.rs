@@ -0,0 +1,164 @@
+#[cfg(feature = "native")]
+mod input;
+#[cfg(feature = "native")]
+mod terminal;
+
+use std::fmt;
+use std::ops::{Index, IndexMut};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "native")]
+pub use self::input::{ControllerMap, Input, KeyMap, Keypad, PollSignal};
+#[cfg(feature = "native")]
+pub use self::terminal::TerminalKeypad;
+
+/// For each of the 16 keys available, the state (pressed/not-pressed) is kept
+/// in a 16-bit array.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct KeypadState([bool; 16]);
+
+impl KeypadState {
+    /// Creates a `KeypadState` with the provided COSMAC VIP keys (`0x0`
+    /// through `0xF`) pressed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the keys is not a valid COSMAC VIP key (>= 16).
+    pub fn from_keys(keys: &[u8]) -> KeypadState {
+        let mut keypad_state = KeypadState::default();
+
+        for key in keys {
+            assert!(*key < 16, "Invalid COSMAC VIP key: {:#04x}", key);
+            keypad_state[*key as usize] = true;
+        }
+
+        keypad_state
+    }
+
+    /// Packs the state into a bit mask, bit `n` set meaning key `n` is
+    /// pressed.
+    pub fn bits(&self) -> u16 {
+        self.0
+            .iter()
+            .enumerate()
+            .filter(|(_, pressed)| **pressed)
+            .fold(0, |bits, (key, _)| bits | (1 << key))
+    }
+
+    /// Unpacks a bit mask produced by `KeypadState::bits`
+    pub fn from_bits(bits: u16) -> KeypadState {
+        let mut keypad_state = KeypadState::default();
+
+        for key in 0..16 {
+            keypad_state[key] = bits & (1 << key) != 0;
+        }
+
+        keypad_state
+    }
+
+    /// Keys pressed in this state but not in `prev`
+    pub fn pressed_since(&self, prev: &KeypadState) -> [bool; 16] {
+        let mut edges = [false; 16];
+
+        for (key, edge) in edges.iter_mut().enumerate() {
+            *edge = self[key] && !prev[key];
+        }
+
+        edges
+    }
+
+    /// Keys pressed in `prev` but not in this state
+    pub fn released_since(&self, prev: &KeypadState) -> [bool; 16] {
+        prev.pressed_since(self)
+    }
+}
+
+impl fmt::Display for KeypadState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let arr = self.0;
+
+        write!(
+            f,
+            "1:{} 2:{} 3:{} 4:{} Q:{} W:{} E:{} R:{} A:{} S:{} D:{} F:{} Z:{} X:{} C:{} V:{}",
+            arr[0x1] as u8,
+            arr[0x2] as u8,
+            arr[0x3] as u8,
+            arr[0xC] as u8,
+            arr[0x4] as u8,
+            arr[0x5] as u8,
+            arr[0x6] as u8,
+            arr[0xD] as u8,
+            arr[0x7] as u8,
+            arr[0x8] as u8,
+            arr[0x9] as u8,
+            arr[0xE] as u8,
+            arr[0xA] as u8,
+            arr[0x0] as u8,
+            arr[0xB] as u8,
+            arr[0xF] as u8,
+        )
+    }
+}
+
+impl From<[bool; 16]> for KeypadState {
+    fn from(keys: [bool; 16]) -> Self {
+        KeypadState(keys)
+    }
+}
+
+impl Index<usize> for KeypadState {
+    type Output = bool;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.0[index]
+    }
+}
+
+impl IndexMut<usize> for KeypadState {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.0[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KeypadState;
+
+    #[test]
+    fn from_keys_sets_provided_keys() {
+        let keypad_state = KeypadState::from_keys(&[0x1, 0xF]);
+
+        for key in 0..16 {
+            assert_eq!(keypad_state[key], key == 0x1 || key == 0xF);
+        }
+    }
+
+    #[test]
+    fn diffs_key_edges() {
+        let prev = KeypadState::from_keys(&[0x1, 0x5]);
+        let next = KeypadState::from_keys(&[0x5, 0xA]);
+        let mut pressed = [false; 16];
+        let mut released = [false; 16];
+
+        pressed[0xA] = true;
+        released[0x1] = true;
+
+        assert_eq!(next.pressed_since(&prev), pressed);
+        assert_eq!(next.released_since(&prev), released);
+        assert_eq!(
+            next.pressed_since(&next),
+            [false; 16],
+            "Held keys have no edges"
+        );
+        assert_eq!(next.released_since(&next), [false; 16]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid COSMAC VIP key")]
+    fn from_keys_rejects_invalid_keys() {
+        KeypadState::from_keys(&[0x10]);
+    }
+}
@@ -0,0 +1,220 @@
+use std::io::{self, Read};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::warn;
+
+use super::input::{Input, KeyMap, PollSignal};
+use super::KeypadState;
+
+/// Terminals report key presses but not releases, keys are held down for
+/// this long after their last press. Held keys are reported again on every
+/// key repeat, keeping them pressed.
+const KEY_HOLD: Duration = Duration::from_millis(100);
+
+/// Escape byte starting the sequences sent by function keys
+const ESC: u8 = 0x1b;
+/// Byte sent by Ctrl-C once the terminal doesn't turn it into a signal
+const CTRL_C: u8 = 0x03;
+
+/// Keypad reading the keys typed on the terminal the emulator runs in, used
+/// along with the terminal backend so no SDL window is needed, e.g. when
+/// playing over SSH.
+///
+/// The terminal is switched to raw input while the `TerminalKeypad` lives,
+/// keys are mapped with the characters they type through the `KeyMap`.
+/// Escape and Ctrl-C quit, the Spacebar and function keys raise the same
+/// `PollSignal`s as with `Keypad`.
+pub struct TerminalKeypad {
+    keymap: KeyMap,
+    /// Bytes read from the standard input by a background thread
+    input: Receiver<Vec<u8>>,
+    /// When each of the COSMAC VIP keys was last pressed
+    pressed_at: [Option<Instant>; 16],
+    /// Terminal settings to restore on drop, `None` if raw input couldn't be
+    /// enabled
+    saved_mode: Option<String>,
+}
+
+impl TerminalKeypad {
+    /// Switches the terminal to raw input and starts reading keys from the
+    /// standard input
+    pub fn new(keymap: KeyMap) -> Self {
+        let saved_mode = match stty(&["-g"]) {
+            Ok(mode) => match stty(&["-icanon", "-echo", "-isig", "min", "1"]) {
+                Ok(_) => Some(mode),
+                Err(err) => {
+                    warn!("Failed to switch the terminal to raw input: {}", err);
+                    None
+                }
+            },
+            Err(err) => {
+                warn!("Failed to read the terminal settings: {}", err);
+                None
+            }
+        };
+        let (sender, input) = mpsc::channel();
+
+        // Reads block until a key is typed, these are sent to be picked up
+        // on the following polls
+        thread::spawn(move || {
+            let mut stdin = io::stdin();
+            let mut buf = [0; 64];
+
+            while let Ok(read @ 1..) = stdin.read(&mut buf) {
+                if sender.send(buf[..read].to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            keymap,
+            input,
+            pressed_at: [None; 16],
+            saved_mode,
+        }
+    }
+}
+
+impl Input for TerminalKeypad {
+    fn poll(&mut self) -> Result<KeypadState, PollSignal> {
+        let now = Instant::now();
+        let mut signal = None;
+
+        for bytes in self.input.try_iter() {
+            for decoded in decode(&bytes) {
+                match decoded {
+                    Ok(c) => {
+                        if let Some(key) = self.keymap.key_for_char(c) {
+                            self.pressed_at[key] = Some(now);
+                        }
+                    }
+                    Err(raised) => signal = signal.or(Some(raised)),
+                }
+            }
+        }
+
+        if let Some(signal) = signal {
+            return Err(signal);
+        }
+
+        Ok(self
+            .pressed_at
+            .map(|pressed_at| pressed_at.is_some_and(|at| now.duration_since(at) < KEY_HOLD))
+            .into())
+    }
+}
+
+impl Drop for TerminalKeypad {
+    fn drop(&mut self) {
+        if let Some(mode) = self.saved_mode.as_deref() {
+            if let Err(err) = stty(&[mode]) {
+                warn!("Failed to restore the terminal settings: {}", err);
+            }
+        }
+    }
+}
+
+/// Characters and signals typed on the terminal, in order. Escape sequences
+/// other than the function keys raising a `PollSignal` are skipped.
+fn decode(bytes: &[u8]) -> Vec<Result<char, PollSignal>> {
+    let mut decoded = Vec::new();
+    let mut rest = bytes;
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+
+        match byte {
+            CTRL_C => decoded.push(Err(PollSignal::Quit)),
+            ESC => {
+                let (sequence, tail) = escape_sequence(rest);
+                rest = tail;
+
+                let signal = match sequence {
+                    // Escape alone
+                    b"" => PollSignal::Quit,
+                    b"OP" | b"[11~" => PollSignal::Reset,
+                    b"[15~" => PollSignal::SaveState,
+                    b"[18~" => PollSignal::VolumeDown,
+                    b"[19~" => PollSignal::VolumeUp,
+                    b"[20~" => PollSignal::LoadState,
+                    b"[23~" => PollSignal::ToggleFullscreen,
+                    b"[24~" => PollSignal::Screenshot,
+                    _ => continue,
+                };
+
+                decoded.push(Err(signal));
+            }
+            b' ' => decoded.push(Err(PollSignal::Pause)),
+            _ if byte.is_ascii_graphic() => decoded.push(Ok(byte as char)),
+            _ => {}
+        }
+    }
+
+    decoded
+}
+
+/// Splits the escape sequence following an escape byte from the rest of
+/// the bytes, either `O` and a single character or a CSI sequence (`[` up
+/// to its final byte).
+fn escape_sequence(bytes: &[u8]) -> (&[u8], &[u8]) {
+    let len = match bytes.first() {
+        Some(b'O') => bytes.len().min(2),
+        Some(b'[') => bytes[1..]
+            .iter()
+            .position(|byte| (0x40..=0x7e).contains(byte))
+            .map_or(bytes.len(), |end| end + 2),
+        _ => 0,
+    };
+
+    bytes.split_at(len)
+}
+
+/// Runs `stty` on the terminal the emulator runs in, returning its output
+fn stty(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output()?;
+
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keypad::PollSignal;
+
+    use super::decode;
+
+    #[test]
+    fn decodes_keys_and_function_keys() {
+        assert_eq!(
+            decode(b"qW \x1bOP\x1b[15~\x1b[20~\x1b[19~"),
+            vec![
+                Ok('q'),
+                Ok('W'),
+                Err(PollSignal::Pause),
+                Err(PollSignal::Reset),
+                Err(PollSignal::SaveState),
+                Err(PollSignal::LoadState),
+                Err(PollSignal::VolumeUp),
+            ]
+        );
+        assert_eq!(decode(b"\x1b"), vec![Err(PollSignal::Quit)]);
+        assert_eq!(decode(b"\x03"), vec![Err(PollSignal::Quit)]);
+        assert_eq!(
+            decode(b"\x1b[A1\x1b[1;5C\r"),
+            vec![Ok('1')],
+            "Arrow keys and Enter are skipped"
+        );
+    }
+}
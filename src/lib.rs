@@ -1,11 +1,25 @@
+pub mod assembler;
 pub mod audio;
+#[cfg(feature = "native")]
 pub mod config;
+pub mod core;
 pub mod cpu;
+pub mod debugger;
+pub mod disassembler;
 pub mod display;
+pub mod headless;
+pub mod hooks;
 pub mod keypad;
 pub mod memory;
 pub mod opcode;
+pub mod quirks;
 pub mod register_set;
+pub mod replay;
 pub mod rom;
+pub mod scheduler;
 pub mod stack;
+pub mod state;
+#[cfg(feature = "native")]
 pub mod system;
+pub mod trace;
+pub mod watch;
@@ -1,10 +1,59 @@
-use ch8::config::Config;
+use std::env;
+use std::fs;
+
+use anyhow::Result;
+use ch8::assembler::assemble;
+use ch8::config::Cli;
+use ch8::disassembler::disassemble;
+use ch8::memory::USER_SPACE_STR;
+use ch8::rom::Rom;
 use ch8::system::System;
-use structopt::StructOpt;
+use log::LevelFilter;
+
+fn main() -> Result<()> {
+    let cli = Cli::parse(env::args_os()).unwrap_or_else(|err| err.exit());
+    let level = match &cli {
+        Cli::Run(config) => config.log_level(),
+        _ => LevelFilter::Info,
+    };
+
+    env_logger::Builder::new()
+        .filter_level(level)
+        .format_target(false)
+        .parse_default_env()
+        .init();
+
+    match cli {
+        Cli::Run(config) => System::new(config)?.start(),
+        Cli::Disasm { rom } => {
+            let rom = Rom::read(&rom)?;
+
+            for (address, opcode, instr) in disassemble(rom.bytes()) {
+                println!("{:#06x}  {}  {}", address, opcode, instr);
+            }
+        }
+        Cli::Asm { source, output } => {
+            let bytes = assemble(&fs::read_to_string(source)?)?;
+
+            Rom::write(&output, bytes)?;
+        }
+        Cli::Info { rom, start } => {
+            let info = Rom::read(&rom)?.info();
+            let mnemonics: Vec<&str> = info.mnemonics.into_iter().collect();
 
-fn main() {
-    let config = Config::from_args();
-    let system = System::new(config);
+            println!("Size: {} bytes", info.size);
+            println!("Variant: {}", info.variant);
+            println!(
+                "Entrypoint: {:#06x}",
+                start.unwrap_or(USER_SPACE_STR as u16)
+            );
+            println!(
+                "Opcodes: {} known, {} unknown",
+                info.known_opcodes, info.unknown_opcodes
+            );
+            println!("Instructions: {}", mnemonics.join(" "));
+        }
+    }
 
-    system.start();
+    Ok(())
 }
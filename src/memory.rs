@@ -1,4 +1,9 @@
-use std::ops::{Index, IndexMut};
+use std::error::Error;
+use std::fmt;
+use std::ops::{Index, IndexMut, Range};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 /// Chip8 Fonts
 ///
@@ -22,6 +27,39 @@ const FONTS: [u8; 0x0050] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // Font: F
 ];
 
+/// SUPER-CHIP high resolution font, 8x10 sprites for the digits 0 to F
+const LARGE_FONTS: [u8; 0x00A0] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // Font: 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // Font: 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // Font: 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // Font: 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // Font: 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // Font: 5
+    0x3E, 0x7C, 0xE0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // Font: 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // Font: 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // Font: 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // Font: 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // Font: A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // Font: B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // Font: C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // Font: D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // Font: E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // Font: F
+];
+
+/// Memory Address where the font sprites start by default
+pub const FONT_BASE_ADDR: usize = 0x0000;
+
+/// Bytes used by each font sprite (glyph)
+pub const FONT_GLYPH_LEN: u16 = 5;
+
+/// Bytes used by each large font sprite (glyph)
+pub const LARGE_FONT_GLYPH_LEN: u16 = 10;
+
+/// Bytes used by the whole font, the large font included. The large font
+/// is stored right after the small one.
+pub const FONT_LEN: usize = FONTS.len() + LARGE_FONTS.len();
+
 /// Memory Address for User Space area start
 pub const USER_SPACE_STR: usize = 0x0200;
 
@@ -31,6 +69,39 @@ pub const MEMORY_END: usize = 0x1000;
 /// Memory Capacity
 pub const MEMORY_SIZE: usize = 4096;
 
+/// Memory Address where ETI-660 programs start
+pub const ETI660_USER_SPACE_STR: usize = 0x0600;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MemoryError {
+    /// The address is past the end of memory
+    OutOfBounds {
+        /// First address accessed out of bounds
+        addr: usize,
+    },
+    /// The write falls in the fonts or interpreter reserved area, below the
+    /// user space
+    Reserved {
+        /// First address written in the reserved area
+        addr: usize,
+    },
+}
+
+impl fmt::Display for MemoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MemoryError::OutOfBounds { addr } => {
+                write!(f, "Memory address {:#06x} out of bounds", addr)
+            }
+            MemoryError::Reserved { addr } => {
+                write!(f, "Memory address {:#06x} is reserved", addr)
+            }
+        }
+    }
+}
+
+impl Error for MemoryError {}
+
 /// # The CHIP-8 Memory
 ///
 /// CHIP-8 Memory is 4KB (4096 bytes) of size, the index register (IR) can only
@@ -49,17 +120,36 @@ pub const MEMORY_SIZE: usize = 4096;
 /// | User Space           |
 /// 0x1000 ------------------> END - 4096B
 /// ```
-#[derive(Debug, PartialEq, Eq)]
-pub struct Memory([u8; MEMORY_SIZE]);
+///
+/// Other targets use a different layout, the size and the entry point (the
+/// start of the `User Space`) are set with `Memory::new`. ETI-660 programs,
+/// for instance, start at `0x0600`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Memory {
+    bytes: Vec<u8>,
+    entry_point: usize,
+    /// Address of the first font sprite
+    font_base: usize,
+    /// Regions written since the last `take_written`, recorded only while
+    /// `Memory::track_writes` is enabled
+    #[cfg_attr(feature = "serde", serde(skip))]
+    written: Option<Vec<Range<usize>>>,
+}
 
-impl Default for Memory {
-    fn default() -> Self {
-        let mut mem = [0; 0x1000];
+impl PartialEq for Memory {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes == other.bytes
+            && self.entry_point == other.entry_point
+            && self.font_base == other.font_base
+    }
+}
 
-        // Load fonts into interpreter reserved memory
-        mem[..0x050].copy_from_slice(&FONTS);
+impl Eq for Memory {}
 
-        Self(mem)
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new(MEMORY_SIZE, USER_SPACE_STR)
     }
 }
 
@@ -67,28 +157,201 @@ impl Index<usize> for Memory {
     type Output = u8;
 
     fn index(&self, index: usize) -> &Self::Output {
-        &self.0[index]
+        &self.bytes[index]
     }
 }
 
 impl IndexMut<usize> for Memory {
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        &mut self.0[index]
+        self.record_write(index..index + 1);
+        &mut self.bytes[index]
     }
 }
 
 impl Memory {
-    /// Allocates bytes in the `User Space` (0x0200 and beyond)
-    pub fn load(&mut self, bytes: &[u8]) {
-        let area = USER_SPACE_STR + bytes.len();
+    /// Creates `size` bytes of memory with the fonts loaded, where programs
+    /// are loaded at `entry_point`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fonts or the entry point don't fit in `size` bytes.
+    pub fn new(size: usize, entry_point: usize) -> Self {
+        Self::with_font_base(size, entry_point, FONT_BASE_ADDR)
+    }
+
+    /// Creates memory as `Memory::new` does, with the fonts loaded at
+    /// `font_base` instead of `FONT_BASE_ADDR`. Some ROMs expect them at
+    /// `0x0050`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fonts don't fit below the entry point or the entry
+    /// point doesn't fit in `size` bytes.
+    pub fn with_font_base(size: usize, entry_point: usize, font_base: usize) -> Self {
+        assert!(
+            font_base + FONT_LEN <= entry_point && entry_point < size,
+            "Entry point {:#06x} must be between the fonts and the end of memory",
+            entry_point
+        );
+
+        let mut bytes = vec![0; size];
+
+        // Load fonts into interpreter reserved memory
+        let large_font_base = font_base + FONTS.len();
 
-        self.0[USER_SPACE_STR..area].copy_from_slice(bytes);
+        bytes[font_base..large_font_base].copy_from_slice(&FONTS);
+        bytes[large_font_base..font_base + FONT_LEN].copy_from_slice(&LARGE_FONTS);
+
+        Self {
+            bytes,
+            entry_point,
+            font_base,
+            written: None,
+        }
+    }
+
+    /// Memory laid out for the ETI-660, programs start at `0x0600`
+    pub fn eti660() -> Self {
+        Self::new(MEMORY_SIZE, ETI660_USER_SPACE_STR)
+    }
+
+    /// Memory capacity in bytes
+    pub fn size(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Memory address where programs are loaded and start running
+    pub fn entry_point(&self) -> usize {
+        self.entry_point
+    }
+
+    /// Restores the initial layout, with the fonts loaded and the rest of
+    /// the memory cleared
+    pub fn clear(&mut self) {
+        let written = self.written.take();
+
+        *self = Self::with_font_base(self.size(), self.entry_point, self.font_base);
+        self.written = written.map(|_| Vec::new());
+    }
+
+    /// Starts or stops recording the regions written, discarding the ones
+    /// recorded so far
+    pub(crate) fn track_writes(&mut self, enabled: bool) {
+        self.written = enabled.then(Vec::new);
+    }
+
+    /// Regions written since the last call, empty unless tracking writes
+    pub(crate) fn take_written(&mut self) -> Vec<Range<usize>> {
+        self.written
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    fn record_write(&mut self, region: Range<usize>) {
+        if let Some(written) = self.written.as_mut() {
+            written.push(region);
+        }
+    }
+
+    /// Memory address where the font sprites start
+    pub fn font_base(&self) -> usize {
+        self.font_base
+    }
+
+    /// Memory address of the font sprite for the hexadecimal `digit`.
+    ///
+    /// Only the low nibble of `digit` is taken into account.
+    pub fn font_addr(&self, digit: u8) -> u16 {
+        self.font_base as u16 + (digit & 0x0F) as u16 * FONT_GLYPH_LEN
+    }
+
+    /// Memory address of the large font sprite for the hexadecimal `digit`,
+    /// stored right after the small font sprites.
+    ///
+    /// Only the low nibble of `digit` is taken into account.
+    pub fn large_font_addr(&self, digit: u8) -> u16 {
+        (self.font_base + FONTS.len()) as u16 + (digit & 0x0F) as u16 * LARGE_FONT_GLYPH_LEN
+    }
+
+    /// Borrows `len` bytes starting at `start`. The region is clamped to the
+    /// end of memory.
+    pub fn dump(&self, start: usize, len: usize) -> &[u8] {
+        let start = start.min(self.size());
+        let end = start.saturating_add(len).min(self.size());
+
+        &self.bytes[start..end]
+    }
+
+    /// Reads the byte at `addr`
+    pub fn peek(&self, addr: usize) -> Result<u8, MemoryError> {
+        self.bytes
+            .get(addr)
+            .copied()
+            .ok_or(MemoryError::OutOfBounds { addr })
+    }
+
+    /// Writes `byte` at `addr`
+    pub fn poke(&mut self, addr: usize, byte: u8) -> Result<(), MemoryError> {
+        let cell = self
+            .bytes
+            .get_mut(addr)
+            .ok_or(MemoryError::OutOfBounds { addr })?;
+
+        *cell = byte;
+        self.record_write(addr..addr + 1);
+
+        Ok(())
+    }
+
+    /// Writes `bytes` starting at `addr`.
+    ///
+    /// Writes below the entry point would overwrite the fonts or the
+    /// interpreter reserved area and fail unless `allow_reserved` is set.
+    pub fn load_at(
+        &mut self,
+        addr: usize,
+        bytes: &[u8],
+        allow_reserved: bool,
+    ) -> Result<(), MemoryError> {
+        if !allow_reserved && addr < self.entry_point && !bytes.is_empty() {
+            return Err(MemoryError::Reserved { addr });
+        }
+
+        let end = addr.saturating_add(bytes.len());
+
+        if end > self.size() {
+            return Err(MemoryError::OutOfBounds {
+                addr: addr.max(self.size()),
+            });
+        }
+
+        self.bytes[addr..end].copy_from_slice(bytes);
+        self.record_write(addr..end);
+
+        Ok(())
+    }
+
+    /// Allocates bytes in the `User Space`, from the entry point (0x0200
+    /// by default) and beyond
+    pub fn load(&mut self, bytes: &[u8]) -> Result<(), MemoryError> {
+        self.load_at(self.entry_point, bytes, false)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Memory, FONTS, USER_SPACE_STR};
+    use super::USER_SPACE_STR;
+    use super::{Memory, MemoryError, ETI660_USER_SPACE_STR, FONTS, LARGE_FONTS, MEMORY_SIZE};
+
+    #[test]
+    fn font_addr_points_to_glyph() {
+        let mem = Memory::default();
+
+        assert_eq!(mem.font_addr(0x0), 0x0);
+        assert_eq!(mem.font_addr(0xA), 0xA * 5);
+        assert_eq!(mem.font_addr(0xF), 0xF * 5);
+    }
 
     #[test]
     fn default_loads_fonts_into_memory() {
@@ -96,7 +359,20 @@ mod tests {
 
         assert_eq!(mem[0x0000], FONTS[0x0000]);
         assert_eq!(mem[0x0049], FONTS[0x0049]);
-        assert_eq!(mem[0x0050], 0x0000);
+        assert_eq!(mem[0x0050], LARGE_FONTS[0x0000]);
+        assert_eq!(mem[0x00EF], LARGE_FONTS[0x009F]);
+        assert_eq!(mem[0x00F0], 0x0000);
+    }
+
+    #[test]
+    fn loads_fonts_at_custom_base() {
+        let mem = Memory::with_font_base(MEMORY_SIZE, USER_SPACE_STR, 0x50);
+
+        assert_eq!(mem.font_base(), 0x50);
+        assert_eq!(mem.font_addr(0x3), 0x50 + 15);
+        assert_eq!(mem.dump(0x50, FONTS.len()), &FONTS);
+        assert_eq!(mem.large_font_addr(0x5), 0x50 + 0x50 + 50);
+        assert_eq!(mem[0x0000], 0x00);
     }
 
     #[test]
@@ -104,7 +380,7 @@ mod tests {
         let mut mem = Memory::default();
         let bytes: [u8; 5] = [0x01A, 0x02A, 0x03A, 0x04A, 0x05A];
 
-        mem.load(&bytes);
+        mem.load(&bytes).unwrap();
 
         assert_eq!(mem[0x0000], FONTS[0x0000]);
         assert_eq!(mem[0x0049], FONTS[0x0049]);
@@ -115,4 +391,95 @@ mod tests {
         assert_eq!(mem[USER_SPACE_STR + 4], 0x05A);
         assert_eq!(mem[USER_SPACE_STR + 5], 0x000);
     }
+
+    #[test]
+    fn dumps_memory_regions() {
+        let mem = Memory::default();
+
+        assert_eq!(mem.dump(mem.font_addr(0x1) as usize, 5), &FONTS[5..10]);
+        assert_eq!(mem.dump(MEMORY_SIZE - 2, 16).len(), 2, "Clamped to memory");
+        assert!(mem.dump(MEMORY_SIZE + 10, 4).is_empty());
+    }
+
+    #[test]
+    fn peeks_and_pokes_bytes() {
+        let mut mem = Memory::default();
+
+        assert_eq!(mem.peek(0x0000), Ok(FONTS[0]));
+
+        mem.poke(USER_SPACE_STR + 4, 0xAB).unwrap();
+
+        assert_eq!(mem.peek(USER_SPACE_STR + 4), Ok(0xAB));
+        assert_eq!(
+            mem.poke(MEMORY_SIZE, 0xAB),
+            Err(MemoryError::OutOfBounds { addr: MEMORY_SIZE })
+        );
+        assert_eq!(
+            mem.peek(0x1234),
+            Err(MemoryError::OutOfBounds { addr: 0x1234 })
+        );
+    }
+
+    #[test]
+    fn tracks_written_regions() {
+        let mut mem = Memory::default();
+
+        mem.poke(0x300, 0xAB).unwrap();
+
+        assert!(mem.take_written().is_empty(), "Not tracking writes");
+
+        mem.track_writes(true);
+        mem.poke(0x300, 0xAB).unwrap();
+        mem.load_at(0x310, &[1, 2, 3], false).unwrap();
+        mem[0x320] = 0xCD;
+
+        assert_eq!(
+            mem.take_written(),
+            vec![0x300..0x301, 0x310..0x313, 0x320..0x321]
+        );
+        assert!(mem.take_written().is_empty());
+    }
+
+    #[test]
+    fn loads_bytes_at_custom_offsets() {
+        let mut mem = Memory::default();
+
+        mem.load_at(0x300, &[0xDE, 0xAD], false).unwrap();
+
+        assert_eq!(mem.dump(0x300, 2), &[0xDE, 0xAD]);
+        assert_eq!(mem.dump(0x0000, FONTS.len()), &FONTS, "Fonts are untouched");
+        assert_eq!(
+            mem.load_at(0x0040, &[0xFF], false),
+            Err(MemoryError::Reserved { addr: 0x0040 })
+        );
+        assert_eq!(mem.dump(0x0000, FONTS.len()), &FONTS);
+        assert_eq!(
+            mem.load_at(MEMORY_SIZE - 1, &[0x01, 0x02], false),
+            Err(MemoryError::OutOfBounds { addr: MEMORY_SIZE })
+        );
+
+        mem.load_at(0x0100, &[0x01], true).unwrap();
+
+        assert_eq!(mem[0x0100], 0x01, "Reserved area is writable if allowed");
+    }
+
+    #[test]
+    fn loads_bytes_at_the_entry_point() {
+        let mut mem = Memory::eti660();
+
+        mem.load(&[0x01, 0x02]).unwrap();
+
+        assert_eq!(mem.entry_point(), ETI660_USER_SPACE_STR);
+        assert_eq!(mem.dump(ETI660_USER_SPACE_STR, 2), &[0x01, 0x02]);
+        assert_eq!(mem[USER_SPACE_STR], 0x00);
+        assert_eq!(
+            mem.load_at(0x0400, &[0xFF], false),
+            Err(MemoryError::Reserved { addr: 0x0400 })
+        );
+
+        let mem = Memory::new(0x10000, USER_SPACE_STR);
+
+        assert_eq!(mem.size(), 0x10000);
+        assert_eq!(mem.peek(0xFFFF), Ok(0x00));
+    }
 }
@@ -1,16 +1,22 @@
 use std::fmt;
+use std::mem;
 
 /// CPU Executable Instructions
 ///
 /// Refer: http://devernay.free.fr/hacks/chip8/C8TECH10.HTM#3.1
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Instruction {
     /// `0nnn` - SYS addr
     /// Jump to a machine code routine at nnn.
     ///
     /// This instruction is only used on the old computers on which Chip-8 was
     /// originally implemented. It is ignored by modern interpreters.
-    SysAddr,
+    SysAddr(u16),
+    /// `00Cn` - SCD nibble
+    /// Scroll the display down by n lines (SUPER-CHIP).
+    ///
+    /// Rows are moved n pixels down, the top n rows are cleared.
+    ScrollDown(u8),
     /// `00E0` - CLS
     /// Clear the display.
     Cls,
@@ -20,6 +26,31 @@ pub enum Instruction {
     /// The interpreter sets the program counter to the address at the top of
     /// the stack, then subtracts 1 from the stack pointer.
     Ret,
+    /// `00FB` - SCR
+    /// Scroll the display right by 4 pixels (SUPER-CHIP).
+    ///
+    /// Columns are moved 4 pixels right, the leftmost 4 columns are cleared.
+    ScrollRight,
+    /// `00FC` - SCL
+    /// Scroll the display left by 4 pixels (SUPER-CHIP).
+    ///
+    /// Columns are moved 4 pixels left, the rightmost 4 columns are cleared.
+    ScrollLeft,
+    /// `00FD` - EXIT
+    /// Exit the interpreter (SUPER-CHIP).
+    ///
+    /// The program ends, no further instructions are run.
+    Exit,
+    /// `00FE` - LOW
+    /// Disable high resolution mode (SUPER-CHIP).
+    ///
+    /// The display goes back to the 64x32 resolution.
+    LowRes,
+    /// `00FF` - HIGH
+    /// Enable high resolution mode (SUPER-CHIP).
+    ///
+    /// The display switches to the 128x64 resolution.
+    HighRes,
     /// `1nnn` - JP addr
     /// Jump to location `nnn`.
     ///
@@ -107,7 +138,9 @@ pub enum Instruction {
     ///
     /// If the least-significant bit of Vx is 1, then VF is set to 1,
     /// otherwise 0. Then Vx is divided by 2.
-    BitOpShr(usize),
+    ///
+    /// The COSMAC VIP shifts Vy into Vx instead, so y is kept along with x.
+    BitOpShr(usize, usize),
     /// 8xy7 - SUBN Vx, Vy
     /// Set Vx = Vy - Vx, set VF = NOT borrow.
     ///
@@ -119,7 +152,9 @@ pub enum Instruction {
     ///
     /// If the most-significant bit of Vx is 1, then VF is set to 1, otherwise
     /// to 0. Then Vx is multiplied by 2.
-    BitOpShl(usize),
+    ///
+    /// The COSMAC VIP shifts Vy into Vx instead, so y is kept along with x.
+    BitOpShl(usize, usize),
     /// `9xy0` - SNE Vx, Vy
     /// Skip next instruction if Vx != Vy.
     ///
@@ -135,7 +170,10 @@ pub enum Instruction {
     /// Jump to location nnn + V0.
     ///
     /// The program counter is set to nnn plus the value of V0.
-    JumpPcV0(u16),
+    ///
+    /// SUPER-CHIP reads it as `Bxnn`, jumping to xnn plus the value of Vx
+    /// instead (see `Quirks::jump_uses_vx`), so x is kept along with nnn.
+    JumpPcV0(usize, u16),
     /// `Cxkk` - RND Vx, byte
     /// Set Vx = random byte AND kk.
     ///
@@ -201,6 +239,12 @@ pub enum Instruction {
     /// corresponding to the value of Vx. See section 2.4, Display, for more
     /// information on the Chip-8 hexadecimal font.
     SetIEqToVx(usize),
+    /// `Fx30` - LD HF, Vx
+    /// Set I = location of large sprite for digit Vx.
+    ///
+    /// SUPER-CHIP instruction, the value of I is set to the location of the
+    /// 8x10 sprite for the digit in Vx, from the high resolution font.
+    SetIEqToLargeFont(usize),
     /// `Fx33` - LD B, Vx
     /// Store BCD representation of Vx in memory locations I, I+1, and I+2.
     ///
@@ -220,52 +264,258 @@ pub enum Instruction {
     /// The interpreter reads values from memory starting at location I into
     /// registers V0 through Vx.
     GetRegsInI(usize),
+    /// `Fx75` - LD R, Vx
+    /// Store registers V0 through Vx in the RPL user flags (SUPER-CHIP).
+    ///
+    /// SUPER-CHIP supports up to V7, XO-CHIP extends it to VF.
+    StoreFlags(usize),
+    /// `Fx85` - LD Vx, R
+    /// Read registers V0 through Vx from the RPL user flags (SUPER-CHIP).
+    ///
+    /// SUPER-CHIP supports up to V7, XO-CHIP extends it to VF.
+    LoadFlags(usize),
+    /// `F002` - AUDIO
+    /// Load the 16 bytes at I into the audio pattern buffer (XO-CHIP).
+    ///
+    /// While the sound timer is active, the pattern bits are played instead
+    /// of the regular beep.
+    LoadAudioPattern,
+    /// `Fx3A` - PITCH Vx
+    /// Set the audio pattern playback rate from Vx (XO-CHIP).
+    ///
+    /// The pattern is played at `4000 * 2 ^ ((Vx - 64) / 48)` bits per
+    /// second.
+    SetPitch(usize),
+    /// `Fn01` - PLANE n
+    /// Select the bitplanes affected by drawing and clearing (XO-CHIP).
+    ///
+    /// `n` is a bit mask from 0 to 3, bit 0 selecting the first plane and
+    /// bit 1 the second one. Only the first plane is selected by default.
+    SelectPlanes(u8),
+    /// `F000 nnnn` - LD I, LONG nnnn
+    /// Set I = nnnn, a full 16-bit address (XO-CHIP).
+    ///
+    /// The address is held by the 2 bytes following the `F000` opcode, these
+    /// are read by the CPU when fetching the instruction so it takes 4 bytes.
+    LoadLongI(u16),
     /// An Instruction sent when an unknown opcode is encountered
     Unknown,
 }
 
-impl fmt::Display for Instruction {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let instr_code = match &self {
+impl Instruction {
+    /// Assembly mnemonic for the instruction, without operands
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instruction::ScrollDown(_) => "SCD",
+            Instruction::ScrollRight => "SCR",
+            Instruction::ScrollLeft => "SCL",
             Instruction::Cls => "CLS",
-            Instruction::SysAddr => "SYS",
+            Instruction::SysAddr(_) => "SYS",
             Instruction::Ret => "RET",
-            Instruction::Jump(_) => "JP",
+            Instruction::LowRes => "LOW",
+            Instruction::HighRes => "HIGH",
+            Instruction::Exit => "EXIT",
+            Instruction::Jump(_) | Instruction::JumpPcV0(_, _) => "JP",
             Instruction::CallSubroutine(_) => "CALL",
-            Instruction::CondEq(_, _) => "SE",
-            Instruction::CondNotEq(_, _) => "SNE",
-            Instruction::CondEqVxVy(_, _) => "SNER",
-            Instruction::ConstAssignVxToKk(_, _) => "LDVX",
-            Instruction::ConstAddVxToKk(_, _) => "ADD",
-            Instruction::AssignVxToVy(_, _) => "LDVXVY",
-            Instruction::BitOpOr(_, _) => "ORVXVY",
-            Instruction::BitOpAnd(_, _) => "ANDVXVY",
-            Instruction::BitOpXor(_, _) => "XORVXVY",
-            Instruction::MathAdd(_, _) => "MADD",
-            Instruction::MathSub(_, _) => "MSUB",
-            Instruction::MathSubVyVx(_, _) => "MSUBVXVY",
-            Instruction::BitOpShr(_) => "SHRT",
-            Instruction::BitOpShl(_) => "SHLT",
-            Instruction::CondVxNotEqVy(_, _) => "SNEVXVY",
-            Instruction::Mem(_) => "MEM",
-            Instruction::JumpPcV0(_) => "JPV0",
+            Instruction::CondEq(_, _) | Instruction::CondEqVxVy(_, _) => "SE",
+            Instruction::CondNotEq(_, _) | Instruction::CondVxNotEqVy(_, _) => "SNE",
+            Instruction::ConstAddVxToKk(_, _)
+            | Instruction::MathAdd(_, _)
+            | Instruction::SetIEqToIPlusVx(_) => "ADD",
+            Instruction::BitOpOr(_, _) => "OR",
+            Instruction::BitOpAnd(_, _) => "AND",
+            Instruction::BitOpXor(_, _) => "XOR",
+            Instruction::MathSub(_, _) => "SUB",
+            Instruction::MathSubVyVx(_, _) => "SUBN",
+            Instruction::BitOpShr(_, _) => "SHR",
+            Instruction::BitOpShl(_, _) => "SHL",
             Instruction::Rand(_, _) => "RND",
             Instruction::Draw(_, _, _) => "DRW",
             Instruction::SkipIfKeyPressed(_) => "SKP",
             Instruction::KeyOpVxNotPressed(_) => "SKNP",
-            Instruction::SetVxEqToDt(_) => "LDVXDT",
-            Instruction::WaitKeyPressAndStoreOnVx(_) => "LDVXK",
-            Instruction::SetDtEqToVx(_) => "LDDTVX",
-            Instruction::SetStEqToVx(_) => "LDSTVX",
-            Instruction::SetIEqToIPlusVx(_) => "ADDIVX",
-            Instruction::SetIEqToVx(_) => "LDFVX",
-            Instruction::StoreBinaryCodedDecimal(_) => "LDBVX",
-            Instruction::SetRegsInI(_) => "LDIVX",
-            Instruction::GetRegsInI(_) => "LDVXI",
+            Instruction::ConstAssignVxToKk(_, _)
+            | Instruction::AssignVxToVy(_, _)
+            | Instruction::Mem(_)
+            | Instruction::SetVxEqToDt(_)
+            | Instruction::WaitKeyPressAndStoreOnVx(_)
+            | Instruction::SetDtEqToVx(_)
+            | Instruction::SetStEqToVx(_)
+            | Instruction::SetIEqToVx(_)
+            | Instruction::SetIEqToLargeFont(_)
+            | Instruction::StoreBinaryCodedDecimal(_)
+            | Instruction::SetRegsInI(_)
+            | Instruction::GetRegsInI(_)
+            | Instruction::StoreFlags(_)
+            | Instruction::LoadFlags(_) => "LD",
+            Instruction::LoadAudioPattern => "AUDIO",
+            Instruction::LoadLongI(_) => "LD",
+            Instruction::SelectPlanes(_) => "PLANE",
+            Instruction::SetPitch(_) => "PITCH",
             Instruction::Unknown => "UNKWN",
+        }
+    }
+
+    /// Opcode pattern of the instruction (e.g. `Fx55`), identifying its kind
+    /// unlike the mnemonic, which many kinds share
+    pub fn pattern(&self) -> &'static str {
+        match self {
+            Instruction::SysAddr(_) => "0nnn",
+            Instruction::ScrollDown(_) => "00Cn",
+            Instruction::Cls => "00E0",
+            Instruction::Ret => "00EE",
+            Instruction::ScrollRight => "00FB",
+            Instruction::ScrollLeft => "00FC",
+            Instruction::Exit => "00FD",
+            Instruction::LowRes => "00FE",
+            Instruction::HighRes => "00FF",
+            Instruction::Jump(_) => "1nnn",
+            Instruction::CallSubroutine(_) => "2nnn",
+            Instruction::CondEq(_, _) => "3xkk",
+            Instruction::CondNotEq(_, _) => "4xkk",
+            Instruction::CondEqVxVy(_, _) => "5xy0",
+            Instruction::ConstAssignVxToKk(_, _) => "6xkk",
+            Instruction::ConstAddVxToKk(_, _) => "7xkk",
+            Instruction::AssignVxToVy(_, _) => "8xy0",
+            Instruction::BitOpOr(_, _) => "8xy1",
+            Instruction::BitOpAnd(_, _) => "8xy2",
+            Instruction::BitOpXor(_, _) => "8xy3",
+            Instruction::MathAdd(_, _) => "8xy4",
+            Instruction::MathSub(_, _) => "8xy5",
+            Instruction::BitOpShr(_, _) => "8xy6",
+            Instruction::MathSubVyVx(_, _) => "8xy7",
+            Instruction::BitOpShl(_, _) => "8xyE",
+            Instruction::CondVxNotEqVy(_, _) => "9xy0",
+            Instruction::Mem(_) => "Annn",
+            Instruction::JumpPcV0(_, _) => "Bnnn",
+            Instruction::Rand(_, _) => "Cxkk",
+            Instruction::Draw(_, _, _) => "Dxyn",
+            Instruction::SkipIfKeyPressed(_) => "Ex9E",
+            Instruction::KeyOpVxNotPressed(_) => "ExA1",
+            Instruction::SetVxEqToDt(_) => "Fx07",
+            Instruction::WaitKeyPressAndStoreOnVx(_) => "Fx0A",
+            Instruction::SetDtEqToVx(_) => "Fx15",
+            Instruction::SetStEqToVx(_) => "Fx18",
+            Instruction::SetIEqToIPlusVx(_) => "Fx1E",
+            Instruction::SetIEqToVx(_) => "Fx29",
+            Instruction::SetIEqToLargeFont(_) => "Fx30",
+            Instruction::StoreBinaryCodedDecimal(_) => "Fx33",
+            Instruction::SetRegsInI(_) => "Fx55",
+            Instruction::GetRegsInI(_) => "Fx65",
+            Instruction::StoreFlags(_) => "Fx75",
+            Instruction::LoadFlags(_) => "Fx85",
+            Instruction::LoadAudioPattern => "F002",
+            Instruction::SetPitch(_) => "Fx3A",
+            Instruction::SelectPlanes(_) => "Fn01",
+            Instruction::LoadLongI(_) => "F000",
+            Instruction::Unknown => "????",
+        }
+    }
+
+    /// Instruction of the kind matching `pattern` (e.g. `Fx55`, case
+    /// insensitive) with all its operands set to 0, `None` if no kind of
+    /// instruction has this pattern.
+    pub fn from_pattern(pattern: &str) -> Option<Instruction> {
+        // Operands are replaced by 0s, leaving an opcode of the kind
+        let hexa: String = pattern
+            .to_ascii_lowercase()
+            .chars()
+            .map(|c| if "nxyk".contains(c) { '0' } else { c })
+            .collect();
+        let instr = match u16::from_str_radix(&hexa, 16).ok()? {
+            // Only decoded by the CPU, along with the address following it
+            0xF000 => Instruction::LoadLongI(0),
+            hexa => Opcode::from(hexa).decode(),
         };
 
-        write!(f, "{}", instr_code)
+        Some(instr).filter(|instr| instr.pattern().eq_ignore_ascii_case(pattern))
+    }
+
+    /// An instruction of every kind named `mnemonic`, case insensitive (e.g.
+    /// all the `LD` kinds for `ld`). Empty if no instruction is named so.
+    pub fn kinds_with_mnemonic(mnemonic: &str) -> Vec<Instruction> {
+        let mut kinds: Vec<Instruction> = Vec::new();
+        // `F000` is only decoded by the CPU, along with the address following it
+        let instrs = (0..=u16::MAX)
+            .map(|hexa| Opcode::from(hexa).decode())
+            .chain([Instruction::LoadLongI(0)]);
+
+        for instr in instrs.filter(|instr| !matches!(instr, Instruction::Unknown)) {
+            if instr.mnemonic().eq_ignore_ascii_case(mnemonic)
+                && !kinds
+                    .iter()
+                    .any(|kind| mem::discriminant(kind) == mem::discriminant(&instr))
+            {
+                kinds.push(instr);
+            }
+        }
+
+        kinds
+    }
+}
+
+/// Renders the instruction in assembly form, the mnemonic followed by its
+/// operands: registers as `VX`, addresses and bytes in hex and nibbles in
+/// decimal (e.g. `LD V3, 0x1F` or `DRW V1, V2, 5`).
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mnemonic = self.mnemonic();
+
+        match *self {
+            Instruction::SysAddr(nnn)
+            | Instruction::Jump(nnn)
+            | Instruction::CallSubroutine(nnn) => write!(f, "{} 0x{:03X}", mnemonic, nnn),
+            Instruction::ScrollDown(n) | Instruction::SelectPlanes(n) => {
+                write!(f, "{} {}", mnemonic, n)
+            }
+            Instruction::CondEq(vx, kk)
+            | Instruction::CondNotEq(vx, kk)
+            | Instruction::ConstAssignVxToKk(vx, kk)
+            | Instruction::ConstAddVxToKk(vx, kk)
+            | Instruction::Rand(vx, kk) => write!(f, "{} V{:X}, 0x{:02X}", mnemonic, vx, kk),
+            Instruction::CondEqVxVy(vx, vy)
+            | Instruction::CondVxNotEqVy(vx, vy)
+            | Instruction::AssignVxToVy(vx, vy)
+            | Instruction::BitOpOr(vx, vy)
+            | Instruction::BitOpAnd(vx, vy)
+            | Instruction::BitOpXor(vx, vy)
+            | Instruction::MathAdd(vx, vy)
+            | Instruction::MathSub(vx, vy)
+            | Instruction::MathSubVyVx(vx, vy)
+            | Instruction::BitOpShr(vx, vy)
+            | Instruction::BitOpShl(vx, vy) => write!(f, "{} V{:X}, V{:X}", mnemonic, vx, vy),
+            Instruction::SkipIfKeyPressed(vx)
+            | Instruction::KeyOpVxNotPressed(vx)
+            | Instruction::SetPitch(vx) => write!(f, "{} V{:X}", mnemonic, vx),
+            Instruction::Mem(nnn) => write!(f, "{} I, 0x{:03X}", mnemonic, nnn),
+            // The register is only meaningful with the `jump_uses_vx` quirk,
+            // the canonical form keeps the full address
+            Instruction::JumpPcV0(_, nnn) => write!(f, "{} V0, 0x{:03X}", mnemonic, nnn),
+            Instruction::Draw(vx, vy, n) => {
+                write!(f, "{} V{:X}, V{:X}, {}", mnemonic, vx, vy, n)
+            }
+            Instruction::SetVxEqToDt(vx) => write!(f, "{} V{:X}, DT", mnemonic, vx),
+            Instruction::WaitKeyPressAndStoreOnVx(vx) => write!(f, "{} V{:X}, K", mnemonic, vx),
+            Instruction::SetDtEqToVx(vx) => write!(f, "{} DT, V{:X}", mnemonic, vx),
+            Instruction::SetStEqToVx(vx) => write!(f, "{} ST, V{:X}", mnemonic, vx),
+            Instruction::SetIEqToIPlusVx(vx) => write!(f, "{} I, V{:X}", mnemonic, vx),
+            Instruction::SetIEqToVx(vx) => write!(f, "{} F, V{:X}", mnemonic, vx),
+            Instruction::SetIEqToLargeFont(vx) => write!(f, "{} HF, V{:X}", mnemonic, vx),
+            Instruction::LoadLongI(nnnn) => write!(f, "{} I, LONG 0x{:04X}", mnemonic, nnnn),
+            Instruction::StoreBinaryCodedDecimal(vx) => write!(f, "{} B, V{:X}", mnemonic, vx),
+            Instruction::SetRegsInI(vx) => write!(f, "{} [I], V{:X}", mnemonic, vx),
+            Instruction::GetRegsInI(vx) => write!(f, "{} V{:X}, [I]", mnemonic, vx),
+            Instruction::StoreFlags(vx) => write!(f, "{} R, V{:X}", mnemonic, vx),
+            Instruction::LoadFlags(vx) => write!(f, "{} V{:X}, R", mnemonic, vx),
+            Instruction::Cls
+            | Instruction::Ret
+            | Instruction::ScrollRight
+            | Instruction::ScrollLeft
+            | Instruction::LowRes
+            | Instruction::HighRes
+            | Instruction::Exit
+            | Instruction::LoadAudioPattern
+            | Instruction::Unknown => write!(f, "{}", mnemonic),
+        }
     }
 }
 
@@ -312,8 +562,8 @@ impl fmt::Display for Instruction {
 /// ```
 ///
 /// Refer: http://devernay.free.fr/hacks/chip8/C8TECH10.HTM#3.0
-#[derive(Debug)]
-pub struct Opcode(u16);
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Opcode(pub(crate) u16);
 
 impl fmt::Display for Opcode {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -322,6 +572,12 @@ impl fmt::Display for Opcode {
 }
 
 impl Opcode {
+    /// Opcode value as fetched from memory
+    #[inline(always)]
+    pub fn raw(&self) -> u16 {
+        self.0
+    }
+
     #[inline(always)]
     pub fn c(&self) -> u8 {
         ((self.0 & 0xF000) >> 12) as u8
@@ -359,55 +615,75 @@ impl Opcode {
 
     /// Decodes a `Opcode` as hexadecimal as an `Instruction` which can be
     /// processed by the CPU.
+    ///
+    /// Dispatches on the opcode group (`c`) first, then matches the
+    /// remaining nibbles within the group only.
     pub fn decode(&self) -> Instruction {
-        let nibbles = (
-            (self.0 & 0xF000) >> 12 as u8,
-            (self.0 & 0x0F00) >> 8 as u8,
-            (self.0 & 0x00F0) >> 4 as u8,
-            (self.0 & 0x000F) as u8,
-        );
+        let nnn = self.nnn();
+        let kk = self.kk();
+        let vx = self.vx();
+        let vy = self.vy();
+        let n = self.n();
 
-        let nnn = (self.0 & 0x0FFF) as u16;
-        let kk = (self.0 & 0x00FF) as u8;
-        let vx = nibbles.1 as usize;
-        let vy = nibbles.2 as usize;
-        let n = nibbles.3 as u8;
-
-        match nibbles {
-            (0x00, 0x00, 0x0e, 0x00) => Instruction::Cls,
-            (0x00, 0x00, 0x0e, 0x0e) => Instruction::Ret,
-            (0x01, _, _, _) => Instruction::Jump(nnn),
-            (0x02, _, _, _) => Instruction::CallSubroutine(nnn),
-            (0x03, _, _, _) => Instruction::CondEq(vx, kk),
-            (0x04, _, _, _) => Instruction::CondNotEq(vx, kk),
-            (0x05, _, _, 0x00) => Instruction::CondEqVxVy(vx, vy),
-            (0x06, _, _, _) => Instruction::ConstAssignVxToKk(vx, kk),
-            (0x07, _, _, _) => Instruction::ConstAddVxToKk(vx, kk),
-            (0x08, _, _, 0x00) => Instruction::AssignVxToVy(vx, vy),
-            (0x08, _, _, 0x01) => Instruction::BitOpOr(vx, vy),
-            (0x08, _, _, 0x02) => Instruction::BitOpAnd(vx, vy),
-            (0x08, _, _, 0x03) => Instruction::BitOpXor(vx, vy),
-            (0x08, _, _, 0x04) => Instruction::MathAdd(vx, vy),
-            (0x08, _, _, 0x05) => Instruction::MathSub(vx, vy),
-            (0x08, _, _, 0x06) => Instruction::BitOpShr(vx),
-            (0x08, _, _, 0x07) => Instruction::MathSubVyVx(vx, vy),
-            (0x08, _, _, 0x0E) => Instruction::BitOpShl(vx),
-            (0x09, _, _, 0x00) => Instruction::CondVxNotEqVy(vx, vy),
-            (0x0A, _, _, _) => Instruction::Mem(nnn),
-            (0x0B, _, _, _) => Instruction::JumpPcV0(nnn),
-            (0x0C, _, _, _) => Instruction::Rand(vx, kk),
-            (0x0D, _, _, _) => Instruction::Draw(vx, vy, n),
-            (0x0E, _, 0x09, 0x0E) => Instruction::SkipIfKeyPressed(vx),
-            (0x0E, _, 0x0A, 0x01) => Instruction::KeyOpVxNotPressed(vx),
-            (0x0F, _, 0x00, 0x07) => Instruction::SetVxEqToDt(vx),
-            (0x0F, _, 0x00, 0x0A) => Instruction::WaitKeyPressAndStoreOnVx(vx),
-            (0x0F, _, 0x01, 0x05) => Instruction::SetDtEqToVx(vx),
-            (0x0F, _, 0x01, 0x08) => Instruction::SetStEqToVx(vx),
-            (0x0F, _, 0x01, 0x0E) => Instruction::SetIEqToIPlusVx(vx),
-            (0x0F, _, 0x02, 0x09) => Instruction::SetIEqToVx(vx),
-            (0x0F, _, 0x03, 0x03) => Instruction::StoreBinaryCodedDecimal(vx),
-            (0x0F, _, 0x05, 0x05) => Instruction::SetRegsInI(vx),
-            (0x0F, _, 0x06, 0x05) => Instruction::GetRegsInI(vx),
+        match self.c() {
+            0x00 => match self.0 {
+                0x00C0..=0x00CF => Instruction::ScrollDown(n),
+                0x00E0 => Instruction::Cls,
+                0x00EE => Instruction::Ret,
+                0x00FB => Instruction::ScrollRight,
+                0x00FC => Instruction::ScrollLeft,
+                0x00FE => Instruction::LowRes,
+                0x00FF => Instruction::HighRes,
+                0x00FD => Instruction::Exit,
+                _ => Instruction::SysAddr(nnn),
+            },
+            0x01 => Instruction::Jump(nnn),
+            0x02 => Instruction::CallSubroutine(nnn),
+            0x03 => Instruction::CondEq(vx, kk),
+            0x04 => Instruction::CondNotEq(vx, kk),
+            0x05 if n == 0x00 => Instruction::CondEqVxVy(vx, vy),
+            0x06 => Instruction::ConstAssignVxToKk(vx, kk),
+            0x07 => Instruction::ConstAddVxToKk(vx, kk),
+            0x08 => match n {
+                0x00 => Instruction::AssignVxToVy(vx, vy),
+                0x01 => Instruction::BitOpOr(vx, vy),
+                0x02 => Instruction::BitOpAnd(vx, vy),
+                0x03 => Instruction::BitOpXor(vx, vy),
+                0x04 => Instruction::MathAdd(vx, vy),
+                0x05 => Instruction::MathSub(vx, vy),
+                0x06 => Instruction::BitOpShr(vx, vy),
+                0x07 => Instruction::MathSubVyVx(vx, vy),
+                0x0E => Instruction::BitOpShl(vx, vy),
+                _ => Instruction::Unknown,
+            },
+            0x09 if n == 0x00 => Instruction::CondVxNotEqVy(vx, vy),
+            0x0A => Instruction::Mem(nnn),
+            0x0B => Instruction::JumpPcV0(vx, nnn),
+            0x0C => Instruction::Rand(vx, kk),
+            0x0D => Instruction::Draw(vx, vy, n),
+            0x0E => match kk {
+                0x9E => Instruction::SkipIfKeyPressed(vx),
+                0xA1 => Instruction::KeyOpVxNotPressed(vx),
+                _ => Instruction::Unknown,
+            },
+            0x0F => match kk {
+                0x01 if vx <= 0x03 => Instruction::SelectPlanes(vx as u8),
+                0x02 if vx == 0x00 => Instruction::LoadAudioPattern,
+                0x07 => Instruction::SetVxEqToDt(vx),
+                0x0A => Instruction::WaitKeyPressAndStoreOnVx(vx),
+                0x15 => Instruction::SetDtEqToVx(vx),
+                0x18 => Instruction::SetStEqToVx(vx),
+                0x1E => Instruction::SetIEqToIPlusVx(vx),
+                0x29 => Instruction::SetIEqToVx(vx),
+                0x30 => Instruction::SetIEqToLargeFont(vx),
+                0x33 => Instruction::StoreBinaryCodedDecimal(vx),
+                0x3A => Instruction::SetPitch(vx),
+                0x55 => Instruction::SetRegsInI(vx),
+                0x65 => Instruction::GetRegsInI(vx),
+                0x75 => Instruction::StoreFlags(vx),
+                0x85 => Instruction::LoadFlags(vx),
+                _ => Instruction::Unknown,
+            },
             _ => Instruction::Unknown,
         }
     }
@@ -421,7 +697,18 @@ impl From<u16> for Opcode {
 
 #[cfg(test)]
 mod tests {
-    use super::Opcode;
+    use std::collections::HashSet;
+
+    use super::{Instruction, Opcode};
+
+    #[test]
+    fn retrieves_raw_value() {
+        let opcode = Opcode::from(0x1234);
+
+        assert_eq!(opcode.raw(), 0x1234);
+        assert_eq!(opcode, Opcode::from(0x1234));
+        assert_ne!(opcode, Opcode::from(0x1235));
+    }
 
     #[test]
     fn retrieves_variable_n() {
@@ -485,4 +772,96 @@ mod tests {
 
         assert_eq!(nnn, 291);
     }
+
+    #[test]
+    fn displays_instructions_with_operands() {
+        let rendered = |hexa: u16| Opcode::from(hexa).decode().to_string();
+
+        assert_eq!(rendered(0x12A4), "JP 0x2A4");
+        assert_eq!(rendered(0x631F), "LD V3, 0x1F");
+        assert_eq!(rendered(0xD125), "DRW V1, V2, 5");
+        assert_eq!(rendered(0xFA1E), "ADD I, VA");
+        assert_eq!(rendered(0x8AB4), "ADD VA, VB");
+        assert_eq!(rendered(0xF265), "LD V2, [I]");
+        assert_eq!(rendered(0x00E0), "CLS");
+        assert_eq!(rendered(0xF530), "LD HF, V5");
+        assert_eq!(Instruction::Jump(0x2A4).mnemonic(), "JP");
+    }
+
+    #[test]
+    fn compares_and_hashes_instructions() {
+        assert_eq!(Opcode::from(0x12A4).decode(), Instruction::Jump(0x2A4));
+        assert_eq!(Opcode::from(0xD125).decode(), Instruction::Draw(1, 2, 5));
+        assert_ne!(Opcode::from(0x12A4).decode(), Instruction::Jump(0x2A6));
+
+        let instructions: HashSet<Instruction> = [0x00E0, 0x12A4, 0x00E0, 0x12A4, 0x631F]
+            .into_iter()
+            .map(|hexa| Opcode::from(hexa).decode())
+            .collect();
+
+        assert_eq!(instructions.len(), 3);
+        assert!(instructions.contains(&Instruction::Cls));
+        assert!(!instructions.contains(&Instruction::Unknown));
+    }
+
+    #[test]
+    fn decodes_group_sub_opcodes() {
+        let decoded = |hexa: u16| Opcode::from(hexa).decode();
+
+        assert_eq!(decoded(0x00C3), Instruction::ScrollDown(3));
+        assert_eq!(decoded(0x00EE), Instruction::Ret);
+        assert_eq!(decoded(0x00FD), Instruction::Exit);
+        assert_eq!(decoded(0x01EE), Instruction::SysAddr(0x1EE));
+        assert_eq!(decoded(0x5121), Instruction::Unknown);
+        assert_eq!(decoded(0x812E), Instruction::BitOpShl(1, 2));
+        assert_eq!(decoded(0x8128), Instruction::Unknown);
+        assert_eq!(decoded(0xE3A1), Instruction::KeyOpVxNotPressed(3));
+        assert_eq!(decoded(0xE3A2), Instruction::Unknown);
+        assert_eq!(decoded(0xF301), Instruction::SelectPlanes(3));
+        assert_eq!(decoded(0xF401), Instruction::Unknown);
+        assert_eq!(decoded(0xF002), Instruction::LoadAudioPattern);
+        assert_eq!(decoded(0xF102), Instruction::Unknown);
+        assert_eq!(decoded(0xF585), Instruction::LoadFlags(5));
+    }
+
+    #[test]
+    fn finds_instructions_by_pattern() {
+        assert_eq!(
+            Instruction::from_pattern("Fx55"),
+            Some(Instruction::SetRegsInI(0))
+        );
+        assert_eq!(
+            Instruction::from_pattern("dxyn"),
+            Some(Instruction::Draw(0, 0, 0))
+        );
+        assert_eq!(
+            Instruction::from_pattern("F000"),
+            Some(Instruction::LoadLongI(0))
+        );
+        assert_eq!(
+            Instruction::from_pattern("0nnn"),
+            Some(Instruction::SysAddr(0))
+        );
+        assert_eq!(
+            Instruction::from_pattern("F055"),
+            None,
+            "Operands are not values"
+        );
+        assert_eq!(Instruction::from_pattern("Fx56"), None);
+        assert_eq!(Instruction::from_pattern("DRW"), None);
+        assert_eq!(Instruction::from_pattern("????"), None);
+
+        for hexa in 0..=u16::MAX {
+            let instr = Opcode::from(hexa).decode();
+
+            if instr != Instruction::Unknown {
+                assert_eq!(
+                    Instruction::from_pattern(instr.pattern()).map(|kind| kind.pattern()),
+                    Some(instr.pattern()),
+                    "Finds {:#06x} by its pattern",
+                    hexa
+                );
+            }
+        }
+    }
 }
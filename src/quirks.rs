@@ -0,0 +1,172 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// CHIP-8 dialects supported by the emulator
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Chip8Variant {
+    /// The original COSMAC VIP interpreter
+    #[default]
+    Chip8,
+    /// SUPER-CHIP 1.1, adds high resolution mode, scrolling, large fonts and
+    /// the RPL flags.
+    SuperChip,
+    /// XO-CHIP, extends SUPER-CHIP with bitplanes, audio patterns and 16-bit
+    /// addressing.
+    XoChip,
+}
+
+impl fmt::Display for Chip8Variant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Chip8Variant::Chip8 => write!(f, "chip8"),
+            Chip8Variant::SuperChip => write!(f, "schip"),
+            Chip8Variant::XoChip => write!(f, "xochip"),
+        }
+    }
+}
+
+impl FromStr for Chip8Variant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "chip8" | "chip-8" => Ok(Chip8Variant::Chip8),
+            "schip" | "superchip" | "super-chip" => Ok(Chip8Variant::SuperChip),
+            "xochip" | "xo-chip" => Ok(Chip8Variant::XoChip),
+            _ => Err(format!(
+                "Unknown variant \"{}\", expected one of: chip8, schip, xochip",
+                s
+            )),
+        }
+    }
+}
+
+/// Behaviors that differ between CHIP-8 interpreters.
+///
+/// ROMs are written against a specific interpreter and may rely on its
+/// quirks to run properly. Use `Quirks::for_variant` to get the profile
+/// matching a `Chip8Variant`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quirks {
+    /// Variant this profile is based on
+    pub variant: Chip8Variant,
+    /// Fail with `CpuError::SysAddr` on machine code routine calls (`0nnn`)
+    /// instead of skipping them. Not part of any interpreter behavior, this
+    /// helps finding ROMs jumping into data.
+    pub trap_sys_addr: bool,
+    /// Fail with `CpuError::MisalignedPc` when fetching an opcode from an
+    /// odd address. Not part of any interpreter behavior either, some ROMs
+    /// do run code from odd addresses.
+    pub trap_misaligned_pc: bool,
+    /// Clip sprites at the right and bottom edges of the screen instead of
+    /// wrapping them around to the opposite edges. The original CHIP-8 and
+    /// SUPER-CHIP clip, XO-CHIP wraps.
+    pub clip_sprites: bool,
+    /// Read `Bnnn` as `Bxnn`, jumping to `xnn + Vx` instead of `nnn + V0`,
+    /// as SUPER-CHIP does.
+    pub jump_uses_vx: bool,
+    /// Mask the targets of `1nnn`, `2nnn` and `Bnnn` to the 12-bit address
+    /// space, so `Bnnn` jumping past 0xFFF wraps around to the start of
+    /// memory. XO-CHIP addresses 64 KiB and jumps past 0xFFF as is.
+    pub wrap_jumps: bool,
+    /// Reset `VF` to 0 after `8xy1`, `8xy2` and `8xy3`, as a side effect of
+    /// the COSMAC VIP running these through VF. SUPER-CHIP and XO-CHIP
+    /// leave it untouched.
+    pub logic_resets_vf: bool,
+    /// Resolve `Fx0A` when the pressed key is released instead of as soon as
+    /// it's pressed, as the COSMAC VIP does. Keeps a held key from resolving
+    /// consecutive waits.
+    pub wait_for_key_release: bool,
+    /// Set `VF` when `Fx1E` moves `I` past the end of memory and clear it
+    /// otherwise, as the Amiga interpreter does. Some games, like
+    /// Spacefight 2091!, rely on it.
+    pub add_i_sets_vf: bool,
+    /// Stall execution after `Dxyn` until the next frame, as the COSMAC VIP
+    /// waits for the vertical blank interrupt to draw. Limits ROMs to one
+    /// sprite per frame, some rely on it for their animation speed.
+    pub display_wait: bool,
+    /// Fail with `CpuError::UnknownOpcode` on opcodes no interpreter
+    /// defines instead of skipping them. Not part of any interpreter
+    /// behavior, keeps corrupt ROMs from running into garbage silently.
+    pub strict_opcodes: bool,
+}
+
+impl Quirks {
+    /// Quirks profile for the provided `variant`
+    pub fn for_variant(variant: Chip8Variant) -> Self {
+        Self {
+            variant,
+            trap_sys_addr: false,
+            trap_misaligned_pc: false,
+            clip_sprites: variant != Chip8Variant::XoChip,
+            jump_uses_vx: variant == Chip8Variant::SuperChip,
+            wrap_jumps: variant != Chip8Variant::XoChip,
+            logic_resets_vf: variant == Chip8Variant::Chip8,
+            wait_for_key_release: false,
+            add_i_sets_vf: false,
+            display_wait: false,
+            strict_opcodes: false,
+        }
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks::for_variant(Chip8Variant::default())
+    }
+}
+
+impl From<Chip8Variant> for Quirks {
+    fn from(variant: Chip8Variant) -> Self {
+        Quirks::for_variant(variant)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Chip8Variant, Quirks};
+
+    #[test]
+    fn parses_variant_names() {
+        assert_eq!("chip8".parse(), Ok(Chip8Variant::Chip8));
+        assert_eq!("SCHIP".parse(), Ok(Chip8Variant::SuperChip));
+        assert_eq!("xo-chip".parse(), Ok(Chip8Variant::XoChip));
+        assert!("megachip".parse::<Chip8Variant>().is_err());
+
+        for variant in [
+            Chip8Variant::Chip8,
+            Chip8Variant::SuperChip,
+            Chip8Variant::XoChip,
+        ] {
+            assert_eq!(variant.to_string().parse(), Ok(variant));
+        }
+    }
+
+    #[test]
+    fn only_xo_chip_wraps_sprites() {
+        assert!(Quirks::default().clip_sprites);
+        assert!(Quirks::for_variant(Chip8Variant::SuperChip).clip_sprites);
+        assert!(!Quirks::for_variant(Chip8Variant::XoChip).clip_sprites);
+    }
+
+    #[test]
+    fn only_super_chip_jumps_with_vx() {
+        assert!(!Quirks::default().jump_uses_vx);
+        assert!(Quirks::for_variant(Chip8Variant::SuperChip).jump_uses_vx);
+        assert!(!Quirks::for_variant(Chip8Variant::XoChip).jump_uses_vx);
+    }
+
+    #[test]
+    fn only_chip_8_resets_vf_on_logic_ops() {
+        assert!(Quirks::default().logic_resets_vf);
+        assert!(!Quirks::for_variant(Chip8Variant::SuperChip).logic_resets_vf);
+        assert!(!Quirks::for_variant(Chip8Variant::XoChip).logic_resets_vf);
+    }
+
+    #[test]
+    fn only_xo_chip_jumps_past_4k() {
+        assert!(Quirks::default().wrap_jumps);
+        assert!(Quirks::for_variant(Chip8Variant::SuperChip).wrap_jumps);
+        assert!(!Quirks::for_variant(Chip8Variant::XoChip).wrap_jumps);
+    }
+}
@@ -1,22 +1,68 @@
 use std::fmt;
 use std::ops::{Index, IndexMut};
 
-#[derive(Debug, PartialEq, Eq)]
-pub struct RegisterSet([u8; 0x0016]);
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-impl fmt::Display for RegisterSet {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "0: {:#04x} 1: {:#04x} 2: {:#04x} 3: {:#04x} 4: {:#04x} 5: {:#04x}",
-            self.0[0], self.0[1], self.0[2], self.0[3], self.0[4], self.0[5]
-        )
+/// Number of general purpose registers, V0 through VF
+pub const REGISTER_COUNT: usize = 16;
+
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RegisterSet(
+    [u8; REGISTER_COUNT],
+    /// Registers written through `IndexMut` since the last `take_written`,
+    /// a bit mask with bit 0 for V0
+    #[cfg_attr(feature = "serde", serde(skip))]
+    u16,
+);
+
+impl RegisterSet {
+    /// Iterates over the register values, from V0 to VF
+    pub fn iter(&self) -> impl Iterator<Item = &u8> {
+        self.0.iter()
+    }
+
+    /// Value of the register `vx`, `None` if there's no such register
+    pub fn get(&self, vx: usize) -> Option<u8> {
+        self.0.get(vx).copied()
+    }
+
+    /// Number of registers
+    pub fn len(&self) -> usize {
+        REGISTER_COUNT
+    }
+
+    /// Always `false`, there's a fixed number of registers
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Bit mask of the registers written since the last call, bit 0 for V0
+    pub(crate) fn take_written(&mut self) -> u16 {
+        std::mem::take(&mut self.1)
     }
 }
 
-impl Default for RegisterSet {
-    fn default() -> Self {
-        RegisterSet([0x0; 0x0016])
+impl PartialEq for RegisterSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for RegisterSet {}
+
+impl fmt::Display for RegisterSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (vx, value) in self.iter().enumerate() {
+            if vx > 0 {
+                write!(f, " ")?;
+            }
+
+            write!(f, "{:X}: {:#04x}", vx, value)?;
+        }
+
+        Ok(())
     }
 }
 
@@ -24,12 +70,74 @@ impl Index<usize> for RegisterSet {
     type Output = u8;
 
     fn index(&self, index: usize) -> &Self::Output {
+        debug_assert!(index < REGISTER_COUNT, "Invalid register V{:X}", index);
         &self.0[index]
     }
 }
 
 impl IndexMut<usize> for RegisterSet {
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        debug_assert!(index < REGISTER_COUNT, "Invalid register V{:X}", index);
+        self.1 |= 1 << index;
         &mut self.0[index]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::RegisterSet;
+
+    #[test]
+    fn tracks_written_registers() {
+        let mut registers = RegisterSet::default();
+
+        registers[0x5] = 0;
+        registers[0xF] = registers[0x1];
+
+        assert_eq!(registers.take_written(), 1 << 0x5 | 1 << 0xF);
+        assert_eq!(registers.take_written(), 0);
+    }
+
+    #[test]
+    fn displays_every_register() {
+        let mut registers = RegisterSet::default();
+
+        registers[0xA] = 0x2A;
+
+        let display = registers.to_string();
+
+        assert!(display.starts_with("0: 0x00 1: 0x00"));
+        assert!(display.contains("A: 0x2a"));
+        assert!(display.ends_with("F: 0x00"));
+    }
+
+    #[test]
+    fn iterates_over_registers() {
+        let mut registers = RegisterSet::default();
+
+        registers[0xF] = 0x01;
+
+        assert_eq!(registers.len(), 16);
+        assert_eq!(registers.iter().count(), 16);
+        assert_eq!(registers.iter().last(), Some(&0x01));
+    }
+
+    #[test]
+    fn vf_is_the_last_register() {
+        let mut registers = RegisterSet::default();
+
+        registers[0xF] = 0x01;
+
+        assert_eq!(registers.get(0xF), Some(0x01));
+        assert_eq!(registers.iter().last(), Some(&0x01));
+        assert_eq!(registers.get(0x10), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn detects_invalid_registers() {
+        let registers = RegisterSet::default();
+
+        let _ = registers[0x10];
+    }
+}
@@ -0,0 +1,221 @@
+use std::fmt;
+use std::fs;
+use std::iter;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Error, Result};
+
+use crate::keypad::KeypadState;
+
+/// Keypad states fed to `Cpu::cycle` during a run, one per cycle, along with
+/// the seed for the random number generator and the cycles between vertical
+/// blanks.
+///
+/// Replaying the inputs into a `Cpu` created with `Cpu::with_seed(seed)`,
+/// signaling `Cpu::vblank` every `cycles_per_frame` cycles, reproduces the
+/// recorded run.
+///
+/// Input logs are stored as text, the seed on the first line and the cycles
+/// per frame on the second, followed by runs of cycles with the same keys
+/// pressed, as the number of cycles and the `KeypadState::bits` in hex:
+///
+/// ```text
+/// seed 1234
+/// frame 12
+/// 120 0000
+/// 8 0020
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InputLog {
+    pub seed: u64,
+    pub cycles_per_frame: u32,
+    runs: Vec<(u32, KeypadState)>,
+}
+
+impl InputLog {
+    /// Creates an empty log for a run seeded with `seed`, with a vertical
+    /// blank every `cycles_per_frame` cycles
+    pub fn new(seed: u64, cycles_per_frame: u32) -> Self {
+        Self {
+            seed,
+            cycles_per_frame,
+            runs: Vec::new(),
+        }
+    }
+
+    /// Records the keypad state for a cycle
+    pub fn push(&mut self, keypad_state: KeypadState) {
+        match self.runs.last_mut() {
+            Some((cycles, last)) if *last == keypad_state && *cycles < u32::MAX => *cycles += 1,
+            _ => self.runs.push((1, keypad_state)),
+        }
+    }
+
+    /// Keypad states for each recorded cycle, in order
+    pub fn inputs(&self) -> impl Iterator<Item = KeypadState> + '_ {
+        self.runs
+            .iter()
+            .flat_map(|(cycles, keypad_state)| iter::repeat_n(*keypad_state, *cycles as usize))
+    }
+
+    /// Reads an `InputLog` from `path`
+    pub fn read(path: &Path) -> Result<Self> {
+        fs::read_to_string(path)?.parse().map_err(Error::msg)
+    }
+
+    /// Writes the `InputLog` to `path`, replacing any existing file
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_string())?;
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for InputLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "frame {}", self.cycles_per_frame)?;
+
+        for (cycles, keypad_state) in self.runs.iter() {
+            writeln!(f, "{} {:04x}", cycles, keypad_state.bits())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for InputLog {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty());
+        let seed = lines
+            .next()
+            .and_then(|(_, line)| line.trim().strip_prefix("seed "))
+            .and_then(|seed| seed.trim().parse().ok())
+            .ok_or_else(|| String::from("Expected the seed on the first line of the input log"))?;
+        let cycles_per_frame = lines
+            .next()
+            .and_then(|(_, line)| line.trim().strip_prefix("frame "))
+            .and_then(|cycles| cycles.trim().parse().ok())
+            .filter(|cycles| *cycles > 0)
+            .ok_or_else(|| {
+                String::from("Expected the cycles per frame on the second line of the input log")
+            })?;
+        let mut runs = Vec::new();
+
+        for (n, line) in lines {
+            let invalid = || format!("Invalid input log entry \"{}\" on line {}", line, n + 1);
+            let (cycles, bits) = line.trim().split_once(' ').ok_or_else(invalid)?;
+            let cycles = cycles.parse().map_err(|_| invalid())?;
+            let bits = u16::from_str_radix(bits.trim(), 16).map_err(|_| invalid())?;
+
+            runs.push((cycles, KeypadState::from_bits(bits)));
+        }
+
+        Ok(Self {
+            seed,
+            cycles_per_frame,
+            runs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cpu::Cpu;
+    use crate::keypad::KeypadState;
+
+    use super::InputLog;
+
+    #[test]
+    fn replays_recorded_inputs() {
+        // 0x200: RND V1, 0x3F
+        // 0x202: RND V2, 0x1F
+        // 0x204: LD V0, K
+        // 0x206: LD F, V0
+        // 0x208: DRW V1, V2, 5
+        // 0x20A: LD V3, DT
+        // 0x20C: SE V3, 0
+        // 0x20E: JP 0x20A
+        // 0x210: LD DT, V0
+        // 0x212: JP 0x200
+        let rom = vec![
+            0xC1, 0x3F, 0xC2, 0x1F, 0xF0, 0x0A, 0xF0, 0x29, 0xD1, 0x25, 0xF3, 0x07, 0x33, 0x00,
+            0x12, 0x0A, 0xF0, 0x15, 0x12, 0x00,
+        ];
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.ch8log");
+        let mut recording = InputLog::new(0xC8, 12);
+        let mut cpu = Cpu::with_seed(recording.seed);
+
+        cpu.load(rom.clone().into()).unwrap();
+
+        for n in 0..600 {
+            let keypad_state = if n % 40 < 10 {
+                KeypadState::from_keys(&[(n / 40) as u8 % 16])
+            } else {
+                KeypadState::default()
+            };
+
+            recording.push(keypad_state);
+            cpu.cycle(keypad_state).unwrap();
+
+            if cpu
+                .cycle_count()
+                .is_multiple_of(recording.cycles_per_frame.into())
+            {
+                cpu.vblank();
+            }
+        }
+
+        recording.write(&path).unwrap();
+
+        let replay = InputLog::read(&path).unwrap();
+        let mut replayed = Cpu::with_seed(replay.seed);
+
+        replayed.load(rom.into()).unwrap();
+
+        for keypad_state in replay.inputs() {
+            replayed.cycle(keypad_state).unwrap();
+
+            if replayed
+                .cycle_count()
+                .is_multiple_of(replay.cycles_per_frame.into())
+            {
+                replayed.vblank();
+            }
+        }
+
+        assert_eq!(replay, recording);
+        assert_eq!(replay.inputs().count(), 600);
+        assert_eq!(replayed.display_buffer, cpu.display_buffer);
+        assert_eq!(replayed.dt, cpu.dt);
+        assert_ne!(
+            cpu.display_buffer,
+            Cpu::new().display_buffer,
+            "Draws sprites"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_input_logs() {
+        assert!("120 0000".parse::<InputLog>().is_err());
+        assert!("seed 1\n120 0000".parse::<InputLog>().is_err());
+        assert!("seed 1\nframe 0\n120 0000".parse::<InputLog>().is_err());
+        assert!("seed 1\nframe 12\n120".parse::<InputLog>().is_err());
+        assert!("seed 1\nframe 12\n120 zzzz".parse::<InputLog>().is_err());
+        assert_eq!(
+            "seed 1\nframe 12\n2 0020\n"
+                .parse::<InputLog>()
+                .unwrap()
+                .inputs()
+                .collect::<Vec<_>>(),
+            vec![KeypadState::from_keys(&[0x5]); 2]
+        );
+    }
+}
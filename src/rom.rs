@@ -1,7 +1,32 @@
+use std::collections::BTreeSet;
 use std::fs;
-use std::io::prelude::*;
-use std::path::PathBuf;
+use std::io::{self, prelude::*};
+use std::path::{Path, PathBuf};
 
+use crate::disassembler::disassemble;
+use crate::memory::USER_SPACE_STR;
+use crate::opcode::{Instruction, Opcode};
+use crate::quirks::Chip8Variant;
+
+/// ROM path standing for the standard input, see `Rom::read`
+pub const STDIN_PATH: &str = "-";
+
+/// Facts about a ROM gathered without running it, see `Rom::info`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RomInfo {
+    /// Length of the ROM in bytes
+    pub size: usize,
+    /// Opcodes decoding to a known instruction
+    pub known_opcodes: usize,
+    /// Opcodes decoding to `Instruction::Unknown`
+    pub unknown_opcodes: usize,
+    /// Mnemonics of the instructions found
+    pub mnemonics: BTreeSet<&'static str>,
+    /// Variant guessed with `Rom::detect_variant`
+    pub variant: Chip8Variant,
+}
+
+#[derive(Clone, Debug)]
 pub struct Rom(Vec<u8>);
 
 impl Rom {
@@ -11,15 +36,152 @@ impl Rom {
         Rom(file)
     }
 
-    pub fn write(path: &PathBuf, bytes: Vec<u8>) {
-        let mut file = fs::File::create(path).unwrap();
+    /// Reads a ROM from the file at `path`, or from the standard input if
+    /// `path` is `-`
+    pub fn read(path: &Path) -> io::Result<Self> {
+        if path == Path::new(STDIN_PATH) {
+            return Self::from_reader(io::stdin().lock());
+        }
+
+        Ok(Rom(fs::read(path)?))
+    }
+
+    /// Reads a ROM from `reader` until it's exhausted
+    pub fn from_reader(mut reader: impl Read) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+
+        reader.read_to_end(&mut bytes)?;
+
+        Ok(Rom(bytes))
+    }
+
+    /// Writes `bytes` to the file at `path`, replacing it if it exists
+    pub fn write(path: &Path, bytes: Vec<u8>) -> io::Result<()> {
+        let mut file = fs::File::create(path)?;
 
-        file.write_all(&bytes).unwrap();
+        file.write_all(&bytes)
     }
 
     pub fn bytes(&self) -> &[u8] {
         self.0.as_slice()
     }
+
+    /// Guesses the CHIP-8 variant the ROM was written for based on the
+    /// opcodes it runs.
+    ///
+    /// Opcodes exclusive to XO-CHIP (`F000`, `Fn01`, `F002`, `Fx3A`,
+    /// `5xy2`, `5xy3`) take precedence over the SUPER-CHIP ones (`00Cn`,
+    /// `00FB` through `00FF`, `Dxy0`, `Fx30`, `Fx75`, `Fx85`). Only the
+    /// opcodes reachable from the start of the ROM are looked at, sprite data
+    /// often holds words looking like these, and ROMs default to `Chip8`
+    /// when none is found.
+    pub fn detect_variant(&self) -> Chip8Variant {
+        let mut variant = Chip8Variant::Chip8;
+
+        for opcode in self.reachable_opcodes() {
+            match variant_of(opcode) {
+                Some(Chip8Variant::XoChip) => return Chip8Variant::XoChip,
+                Some(found) => variant = found,
+                None => {}
+            }
+        }
+
+        variant
+    }
+
+    /// Opcodes reachable from the start of the ROM, following jumps, calls
+    /// and both outcomes of skips.
+    ///
+    /// Code only reached through `Bnnn` or written at runtime isn't found,
+    /// unknown opcodes are taken as data and end the path.
+    fn reachable_opcodes(&self) -> Vec<u16> {
+        let target = |addr: u16| (addr as usize).checked_sub(USER_SPACE_STR);
+        let mut visited = vec![false; self.0.len()];
+        let mut pending = vec![0];
+        let mut opcodes = Vec::new();
+
+        while let Some(offset) = pending.pop() {
+            let Some(word) = self.0.get(offset..offset + 2) else {
+                continue;
+            };
+
+            if visited[offset] {
+                continue;
+            }
+
+            visited[offset] = true;
+
+            let opcode = u16::from_be_bytes([word[0], word[1]]);
+
+            opcodes.push(opcode);
+
+            match Opcode::from(opcode).decode() {
+                Instruction::Jump(addr) => pending.extend(target(addr)),
+                Instruction::CallSubroutine(addr) => {
+                    pending.extend(target(addr));
+                    pending.push(offset + 2);
+                }
+                Instruction::CondEq(..)
+                | Instruction::CondNotEq(..)
+                | Instruction::CondEqVxVy(..)
+                | Instruction::CondVxNotEqVy(..)
+                | Instruction::SkipIfKeyPressed(_)
+                | Instruction::KeyOpVxNotPressed(_) => {
+                    // XO-CHIP skips both words of `F000 nnnn`
+                    let skipped = match self.0.get(offset + 2..offset + 4) {
+                        Some([0xF0, 0x00]) => 4,
+                        _ => 2,
+                    };
+
+                    pending.extend([offset + 2, offset + 2 + skipped]);
+                }
+                Instruction::LoadLongI(_) => pending.push(offset + 4),
+                Instruction::Ret | Instruction::Exit | Instruction::JumpPcV0(..) => {}
+                Instruction::Unknown if variant_of(opcode).is_none() => {}
+                _ => pending.push(offset + 2),
+            }
+        }
+
+        opcodes
+    }
+
+    /// Decodes the ROM bytes linearly and reports the opcodes found.
+    ///
+    /// Sprite data is decoded as well, so the counts are an upper bound of
+    /// the instructions actually run.
+    pub fn info(&self) -> RomInfo {
+        let listing = disassemble(self.bytes());
+        let unknown_opcodes = listing
+            .iter()
+            .filter(|(_, _, instr)| matches!(instr, Instruction::Unknown))
+            .count();
+        let mnemonics = listing
+            .iter()
+            .filter(|(_, _, instr)| !matches!(instr, Instruction::Unknown))
+            .map(|(_, _, instr)| instr.mnemonic())
+            .collect();
+
+        RomInfo {
+            size: self.0.len(),
+            known_opcodes: listing.len() - unknown_opcodes,
+            unknown_opcodes,
+            mnemonics,
+            variant: self.detect_variant(),
+        }
+    }
+}
+
+/// Variant `opcode` is exclusive to, if it's not part of CHIP-8
+fn variant_of(opcode: u16) -> Option<Chip8Variant> {
+    match (opcode & 0xF000, opcode & 0x000F, opcode & 0x00FF) {
+        (0xF000, _, 0x01 | 0x02 | 0x3A) | (0x5000, 0x2 | 0x3, _) => Some(Chip8Variant::XoChip),
+        (0xF000, _, 0x00) if opcode == 0xF000 => Some(Chip8Variant::XoChip),
+        (0x0000, _, 0xC0..=0xCF | 0xFB..=0xFF) if opcode & 0x0F00 == 0 => {
+            Some(Chip8Variant::SuperChip)
+        }
+        (0xD000, 0x0, _) | (0xF000, _, 0x30 | 0x75 | 0x85) => Some(Chip8Variant::SuperChip),
+        _ => None,
+    }
 }
 
 impl From<Vec<u8>> for Rom {
@@ -27,3 +189,131 @@ impl From<Vec<u8>> for Rom {
         Rom(bytes)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Cursor;
+    use std::path::Path;
+
+    use crate::quirks::Chip8Variant;
+
+    use super::Rom;
+
+    fn detect(bytes: &[u8]) -> Chip8Variant {
+        Rom::from(bytes.to_vec()).detect_variant()
+    }
+
+    #[test]
+    fn reads_rom_from_reader() {
+        let bytes = vec![0x00, 0xE0, 0x12, 0x00];
+        let rom = Rom::from_reader(Cursor::new(bytes.clone())).unwrap();
+
+        assert_eq!(rom.bytes(), bytes.as_slice());
+    }
+
+    #[test]
+    fn writes_rom_to_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rom.ch8");
+
+        Rom::write(&path, vec![0x00, 0xE0]).unwrap();
+
+        assert_eq!(Rom::read(&path).unwrap().bytes(), [0x00, 0xE0]);
+        assert!(
+            Rom::write(&dir.path().join("missing").join("rom.ch8"), Vec::new()).is_err(),
+            "Fails instead of panicking"
+        );
+    }
+
+    #[test]
+    fn detects_chip8_roms() {
+        // CLS; LD I, 0x300; DRW V0, V1, 5; JP 0x200
+        assert_eq!(
+            detect(&[0x00, 0xE0, 0xA3, 0x00, 0xD0, 0x15, 0x12, 0x00]),
+            Chip8Variant::Chip8
+        );
+    }
+
+    #[test]
+    fn detects_superchip_roms() {
+        // HIGH; CLS
+        assert_eq!(detect(&[0x00, 0xFF, 0x00, 0xE0]), Chip8Variant::SuperChip);
+        // SCD 4
+        assert_eq!(detect(&[0x00, 0xC4]), Chip8Variant::SuperChip);
+        // DRW V0, V1, 0 (16x16 sprite)
+        assert_eq!(detect(&[0xD0, 0x10]), Chip8Variant::SuperChip);
+        // LD R, V3
+        assert_eq!(detect(&[0xF3, 0x75]), Chip8Variant::SuperChip);
+    }
+
+    #[test]
+    fn detects_xochip_roms() {
+        // HIGH; LD I, long 0x1234
+        assert_eq!(
+            detect(&[0x00, 0xFF, 0xF0, 0x00, 0x12, 0x34]),
+            Chip8Variant::XoChip
+        );
+        // PLANE 3
+        assert_eq!(detect(&[0xF3, 0x01]), Chip8Variant::XoChip);
+        // SAVE V1 - V4
+        assert_eq!(detect(&[0x51, 0x42]), Chip8Variant::XoChip);
+    }
+
+    #[test]
+    fn ignores_variant_opcodes_in_data() {
+        // JP 0x206; sprite data 0x5253 0x00FF; CLS; JP 0x206
+        assert_eq!(
+            detect(&[0x12, 0x06, 0x52, 0x53, 0x00, 0xFF, 0x00, 0xE0, 0x12, 0x06]),
+            Chip8Variant::Chip8
+        );
+        // SE V0, 0; SCD 4; CALL 0x208; JP 0x200; LD R, V3
+        assert_eq!(
+            detect(&[0x30, 0x00, 0x00, 0xC4, 0x22, 0x08, 0x12, 0x00, 0xF3, 0x75]),
+            Chip8Variant::SuperChip,
+            "Skipped and called opcodes are reachable"
+        );
+    }
+
+    #[test]
+    fn detects_bundled_roms_as_chip8() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("roms");
+        let mut roms = 0;
+
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+
+            if path.extension().is_some() {
+                continue;
+            }
+
+            assert_eq!(
+                Rom::read(&path).unwrap().detect_variant(),
+                Chip8Variant::Chip8,
+                "{}",
+                path.display()
+            );
+            roms += 1;
+        }
+
+        assert_eq!(roms, 24);
+    }
+
+    #[test]
+    fn reports_rom_info() {
+        // CLS; LD VA, 0x02; 0xFFFF; LD V1, 0x00; JP 0x200
+        let rom = Rom::from(vec![
+            0x00, 0xE0, 0x6A, 0x02, 0xFF, 0xFF, 0x61, 0x00, 0x12, 0x00,
+        ]);
+        let info = rom.info();
+
+        assert_eq!(info.size, 10);
+        assert_eq!(info.known_opcodes, 4);
+        assert_eq!(info.unknown_opcodes, 1, "0xFFFF is unknown");
+        assert_eq!(
+            info.mnemonics.into_iter().collect::<Vec<_>>(),
+            vec!["CLS", "JP", "LD"]
+        );
+        assert_eq!(info.variant, Chip8Variant::Chip8);
+    }
+}
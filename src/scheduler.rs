@@ -0,0 +1,206 @@
+use std::time::Duration;
+
+use crate::cpu::FRAME_RATE;
+
+/// Time taken by a single CPU cycle when running at `clock_rate`
+/// instructions per second.
+pub fn cycle_duration(clock_rate: f32) -> Duration {
+    Duration::from_secs_f32(1.0 / clock_rate)
+}
+
+/// Paces CPU cycles to a target clock rate and keeps track of the 60 Hz
+/// frames (vertical blank) independently of it, as well as the display
+/// refreshes, at the frame rate unless configured otherwise.
+///
+/// Instead of sleeping a fixed amount of time per cycle, the `Scheduler` is
+/// told how long each cycle took and sleeps only what's left of the cycle
+/// budget. When a cycle takes longer than its budget the delay is kept as
+/// lag, which following cycles catch up by skipping their sleep.
+#[derive(Debug)]
+pub struct Scheduler {
+    /// Time budget for a single CPU cycle
+    cycle_duration: Duration,
+    /// Time between frames
+    frame_duration: Duration,
+    /// Time elapsed since the last frame
+    frame_elapsed: Duration,
+    /// Time between display refreshes
+    refresh_duration: Duration,
+    /// Time elapsed since the last display refresh
+    refresh_elapsed: Duration,
+    /// Time the scheduler is behind the target clock rate
+    lag: Duration,
+}
+
+impl Scheduler {
+    /// Creates a `Scheduler` targeting `clock_rate` instructions per second
+    pub fn new(clock_rate: f32) -> Self {
+        Self::with_refresh_rate(clock_rate, FRAME_RATE)
+    }
+
+    /// Creates a `Scheduler` as `Scheduler::new` does, refreshing the
+    /// display `refresh_rate` times per second instead of on every frame
+    pub fn with_refresh_rate(clock_rate: f32, refresh_rate: f32) -> Self {
+        Self {
+            cycle_duration: cycle_duration(clock_rate),
+            frame_duration: Duration::from_secs_f32(1.0 / FRAME_RATE),
+            frame_elapsed: Duration::ZERO,
+            refresh_duration: Duration::from_secs_f32(1.0 / refresh_rate),
+            refresh_elapsed: Duration::ZERO,
+            lag: Duration::ZERO,
+        }
+    }
+
+    /// Time budget for a single CPU cycle
+    pub fn cycle_duration(&self) -> Duration {
+        self.cycle_duration
+    }
+
+    /// Given the time taken by the last cycle, returns how long to sleep to
+    /// stay on schedule. Returns `Duration::ZERO` when behind schedule.
+    pub fn sleep_duration(&mut self, last_cycle: Duration) -> Duration {
+        if last_cycle >= self.cycle_duration {
+            // Lag is bounded to a frame so a long stall (e.g. the window
+            // being dragged) doesn't turn into a burst of cycles.
+            self.lag = (self.lag + last_cycle - self.cycle_duration).min(self.frame_duration);
+
+            return Duration::ZERO;
+        }
+
+        let remaining = self.cycle_duration - last_cycle;
+
+        if self.lag >= remaining {
+            self.lag -= remaining;
+
+            return Duration::ZERO;
+        }
+
+        let sleep = remaining - self.lag;
+
+        self.lag = Duration::ZERO;
+        sleep
+    }
+
+    /// Accounts for `elapsed` time and returns the number of frames that
+    /// are due since the last call.
+    pub fn frames_due(&mut self, elapsed: Duration) -> u32 {
+        periods_due(&mut self.frame_elapsed, self.frame_duration, elapsed)
+    }
+
+    /// Accounts for `elapsed` time and returns the number of display
+    /// refreshes that are due since the last call.
+    pub fn refreshes_due(&mut self, elapsed: Duration) -> u32 {
+        periods_due(&mut self.refresh_elapsed, self.refresh_duration, elapsed)
+    }
+}
+
+/// Adds `elapsed` to the time since the last `period` ended and returns how
+/// many periods ended, keeping the remainder
+fn periods_due(since: &mut Duration, period: Duration, elapsed: Duration) -> u32 {
+    let mut periods = 0;
+
+    *since += elapsed;
+
+    while *since >= period {
+        *since -= period;
+        periods += 1;
+    }
+
+    periods
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{cycle_duration, Scheduler};
+
+    #[test]
+    fn converts_clock_rate_to_cycle_duration() {
+        assert_eq!(cycle_duration(500.0), Duration::from_millis(2));
+        assert_eq!(cycle_duration(1000.0), Duration::from_millis(1));
+    }
+
+    #[test]
+    fn sleeps_remaining_cycle_budget() {
+        let mut scheduler = Scheduler::new(500.0);
+
+        assert_eq!(
+            scheduler.sleep_duration(Duration::from_micros(500)),
+            Duration::from_micros(1500)
+        );
+    }
+
+    #[test]
+    fn skips_sleep_when_behind_schedule() {
+        let mut scheduler = Scheduler::new(500.0);
+
+        assert_eq!(
+            scheduler.sleep_duration(Duration::from_millis(5)),
+            Duration::ZERO,
+            "Cycle took longer than its 2ms budget"
+        );
+        for _ in 0..3 {
+            assert_eq!(
+                scheduler.sleep_duration(Duration::from_millis(1)),
+                Duration::ZERO,
+                "Catches up 1ms of the 3ms lag per cycle"
+            );
+        }
+
+        assert_eq!(
+            scheduler.sleep_duration(Duration::from_millis(1)),
+            Duration::from_millis(1),
+            "Back on schedule"
+        );
+    }
+
+    #[test]
+    fn counts_frames_due() {
+        let mut scheduler = Scheduler::new(600.0);
+
+        assert_eq!(scheduler.frames_due(Duration::from_millis(10)), 0);
+        assert_eq!(scheduler.frames_due(Duration::from_millis(10)), 1);
+        assert_eq!(scheduler.frames_due(Duration::from_millis(40)), 2);
+    }
+
+    #[test]
+    fn refreshes_at_the_refresh_rate() {
+        let simulate_second = |clock_rate: f32, refresh_rate: f32| {
+            let mut scheduler = Scheduler::with_refresh_rate(clock_rate, refresh_rate);
+            let mut frames = 0;
+            let mut refreshes = 0;
+
+            for _ in 0..clock_rate as u32 {
+                frames += scheduler.frames_due(scheduler.cycle_duration());
+                refreshes += scheduler.refreshes_due(scheduler.cycle_duration());
+            }
+
+            (frames, refreshes)
+        };
+
+        // Durations are rounded, the last refresh of the second may be off by
+        // a cycle
+        let (frames, refreshes) = simulate_second(600.0, 60.0);
+
+        assert!((59..=60).contains(&frames));
+        assert!((59..=60).contains(&refreshes));
+
+        let (frames, refreshes) = simulate_second(1000.0, 30.0);
+
+        assert!((59..=60).contains(&frames), "Timers keep the frame rate");
+        assert!((29..=30).contains(&refreshes));
+    }
+
+    #[test]
+    fn frame_is_due_on_frame_boundary() {
+        let mut scheduler = Scheduler::new(600.0);
+        let frame = Duration::from_secs_f32(1.0 / 60.0);
+        let almost = frame - Duration::from_micros(1);
+
+        assert_eq!(scheduler.frames_due(almost), 0, "Frame not due yet");
+        assert_eq!(scheduler.frames_due(Duration::from_micros(1)), 1);
+        assert_eq!(scheduler.frames_due(almost), 0, "Next frame not due yet");
+        assert_eq!(scheduler.frames_due(frame * 2), 2, "Lagging frames");
+    }
+}
@@ -1,21 +1,78 @@
+use std::error::Error;
+use std::fmt;
 use std::ops::{Index, IndexMut};
 
-#[derive(Debug, PartialEq, Eq)]
-pub struct Stack(Vec<u16>);
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Levels of nested subroutine calls supported by the stack
+pub const STACK_SIZE: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StackError {
+    /// A value was pushed into a full stack
+    Overflow,
+    /// A value was popped from an empty stack
+    Underflow,
+}
+
+impl fmt::Display for StackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StackError::Overflow => write!(
+                f,
+                "Stack overflow, more than {} nested subroutine calls",
+                STACK_SIZE
+            ),
+            StackError::Underflow => write!(f, "Stack underflow, return outside of a subroutine"),
+        }
+    }
+}
+
+impl Error for StackError {}
+
+/// Return addresses for subroutine calls.
+///
+/// Holds up to `STACK_SIZE` frames, the Stack Pointer (SP) is the number of
+/// frames in use and points to the next free slot.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Stack {
+    frames: [u16; STACK_SIZE],
+    sp: usize,
+}
 
 impl Stack {
-    pub fn pop(&mut self) -> u16 {
-        self.0.pop().expect("Stack out of bounds")
+    /// Return addresses currently in the stack, from the outermost to the
+    /// innermost call.
+    pub fn frames(&self) -> &[u16] {
+        &self.frames[..self.sp]
     }
 
-    pub fn push(&mut self, value: u16) {
-        self.0.push(value);
+    /// Stack Pointer, the number of frames in the stack
+    pub fn sp(&self) -> usize {
+        self.sp
     }
-}
 
-impl Default for Stack {
-    fn default() -> Self {
-        Stack([0; 16].into())
+    pub fn pop(&mut self) -> Result<u16, StackError> {
+        if self.sp == 0 {
+            return Err(StackError::Underflow);
+        }
+
+        self.sp -= 1;
+
+        Ok(std::mem::take(&mut self.frames[self.sp]))
+    }
+
+    pub fn push(&mut self, value: u16) -> Result<(), StackError> {
+        if self.sp >= STACK_SIZE {
+            return Err(StackError::Overflow);
+        }
+
+        self.frames[self.sp] = value;
+        self.sp += 1;
+
+        Ok(())
     }
 }
 
@@ -23,12 +80,40 @@ impl Index<usize> for Stack {
     type Output = u16;
 
     fn index(&self, index: usize) -> &Self::Output {
-        &self.0[index]
+        &self.frames()[index]
     }
 }
 
 impl IndexMut<usize> for Stack {
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        &mut self.0[index]
+        &mut self.frames[..self.sp][index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Stack, StackError, STACK_SIZE};
+
+    #[test]
+    fn push_overflows_past_stack_size() {
+        let mut stack = Stack::default();
+
+        for frame in 0..STACK_SIZE as u16 {
+            stack.push(frame).unwrap();
+        }
+
+        assert_eq!(stack.push(0x0FFF), Err(StackError::Overflow));
+        assert_eq!(stack.frames().len(), STACK_SIZE);
+    }
+
+    #[test]
+    fn pop_underflows_when_empty() {
+        let mut stack = Stack::default();
+
+        stack.push(0x0202).unwrap();
+
+        assert_eq!(stack.pop(), Ok(0x0202));
+        assert_eq!(stack.pop(), Err(StackError::Underflow));
+        assert_eq!(stack, Stack::default());
     }
 }
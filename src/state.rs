@@ -0,0 +1,230 @@
+use std::collections::VecDeque;
+#[cfg(feature = "serde")]
+use std::fs;
+#[cfg(feature = "serde")]
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "serde")]
+use anyhow::Result;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::display::buffer::DisplayBuffer;
+use crate::keypad::KeypadState;
+use crate::memory::Memory;
+use crate::register_set::RegisterSet;
+use crate::stack::Stack;
+
+/// Snapshot of the `Cpu` state.
+///
+/// Taken with `Cpu::save_state` and restored with `Cpu::load_state`.
+/// Settings such as `max_draws_per_frame` are not part of the state, these
+/// belong to the running `Cpu` instead.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SaveState {
+    pub(crate) ram: Memory,
+    pub(crate) pc: u16,
+    pub(crate) i: u16,
+    pub(crate) stack: Stack,
+    pub(crate) registers: RegisterSet,
+    pub(crate) flags: [u8; 16],
+    pub(crate) audio_buffer: Option<[u8; 16]>,
+    pub(crate) pitch: u8,
+    pub(crate) planes: u8,
+    pub(crate) dt: u8,
+    pub(crate) st: u8,
+    pub(crate) display_buffer: DisplayBuffer,
+    pub(crate) keypad_state: KeypadState,
+    pub(crate) keypad_await: Option<usize>,
+    pub(crate) keypad_await_held: KeypadState,
+    pub(crate) keypad_await_pressed: Option<usize>,
+    pub(crate) draws_in_frame: u32,
+}
+
+/// Default number of states kept by a `RewindBuffer`
+pub const REWIND_CAPACITY: usize = 128;
+
+/// Ring buffer with the latest `SaveState`s, used to step back in time.
+///
+/// Once full, pushing a new state drops the oldest one.
+#[derive(Clone, Debug)]
+pub struct RewindBuffer {
+    states: VecDeque<SaveState>,
+    capacity: usize,
+}
+
+impl RewindBuffer {
+    /// Creates an empty buffer keeping up to `capacity` states
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            states: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Stores `state` as the latest one, dropping the oldest state if the
+    /// buffer is full
+    pub fn push(&mut self, state: SaveState) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.states.len() == self.capacity {
+            self.states.pop_front();
+        }
+
+        self.states.push_back(state);
+    }
+
+    /// Removes and returns the latest state
+    pub fn pop(&mut self) -> Option<SaveState> {
+        self.states.pop_back()
+    }
+
+    /// Number of stored states
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Whether there are no states to rewind to
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+}
+
+/// Extension for quick save files, written next to the ROM file
+#[cfg(feature = "serde")]
+pub const STATE_FILE_EXT: &str = "ch8state";
+
+/// Quick save file contents.
+///
+/// Holds the ROM bytes along with the `SaveState` so the state can be
+/// restored without depending on the ROM file that produced it.
+#[cfg(feature = "serde")]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateFile {
+    pub rom: Vec<u8>,
+    pub state: SaveState,
+}
+
+#[cfg(feature = "serde")]
+impl StateFile {
+    /// Path to the quick save file for the ROM at `rom_path`
+    pub fn path_for(rom_path: &Path) -> PathBuf {
+        rom_path.with_extension(STATE_FILE_EXT)
+    }
+
+    /// Reads a `StateFile` from `path`
+    pub fn read(path: &Path) -> Result<Self> {
+        let bytes = fs::read(path)?;
+
+        Ok(bincode::deserialize(&bytes)?)
+    }
+
+    /// Writes the `StateFile` to `path`, replacing any existing file
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, bincode::serialize(self)?)?;
+
+        Ok(())
+    }
+}
+
+/// Serde helpers for byte arrays too big for serde's builtin array support,
+/// to be used with `#[serde(with = "crate::state::bytes")]`.
+#[cfg(feature = "serde")]
+pub(crate) mod bytes {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S, const N: usize>(bytes: &[u8; N], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(bytes)
+    }
+
+    pub fn deserialize<'de, D, const N: usize>(deserializer: D) -> Result<[u8; N], D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes = Vec::<u8>::deserialize(deserializer)?;
+
+        bytes
+            .as_slice()
+            .try_into()
+            .map_err(|_| D::Error::invalid_length(bytes.len(), &format!("{} bytes", N).as_str()))
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use std::path::Path;
+
+    use crate::cpu::Cpu;
+    use crate::keypad::KeypadState;
+
+    use super::{SaveState, StateFile};
+
+    #[test]
+    fn serializes_save_state() {
+        let mut cpu = Cpu::new();
+
+        // LD V1, 0x2A; LD I, 0x300; DRW V1, V1, 5
+        cpu.load(vec![0x61, 0x2A, 0xA3, 0x00, 0xD1, 0x15].into())
+            .unwrap();
+
+        for _ in 0..3 {
+            cpu.cycle(Default::default()).unwrap();
+        }
+
+        let state = cpu.save_state();
+        let bytes = bincode::serialize(&state).unwrap();
+
+        assert_eq!(bincode::deserialize::<SaveState>(&bytes).unwrap(), state);
+    }
+
+    #[test]
+    fn rolls_back_to_state_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = StateFile::path_for(&dir.path().join("counter.ch8"));
+        // ADD V1, 0x01; JP 0x200
+        let rom = vec![0x71, 0x01, 0x12, 0x00];
+        let mut cpu = Cpu::new();
+
+        cpu.load(rom.clone().into()).unwrap();
+
+        for _ in 0..3 {
+            cpu.cycle(KeypadState::default()).unwrap();
+        }
+
+        StateFile {
+            rom,
+            state: cpu.save_state(),
+        }
+        .write(&path)
+        .unwrap();
+
+        for _ in 0..5 {
+            cpu.cycle(KeypadState::default()).unwrap();
+        }
+
+        assert_eq!(cpu.registers[0x1], 4);
+
+        let state_file = StateFile::read(&path).unwrap();
+
+        cpu.load_state(state_file.state);
+
+        assert_eq!(cpu.pc, 0x202);
+        assert_eq!(cpu.registers[0x1], 2);
+        assert_eq!(state_file.rom, vec![0x71, 0x01, 0x12, 0x00]);
+    }
+
+    #[test]
+    fn state_file_lives_next_to_rom() {
+        assert_eq!(
+            StateFile::path_for(Path::new("roms/pong.ch8")),
+            Path::new("roms/pong.ch8state")
+        );
+    }
+}
@@ -1,53 +1,490 @@
-use std::io::{stdin, stdout, Read, Write};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+#[cfg(feature = "screenshot")]
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::vec;
+
+use anyhow::{Context, Error, Result};
+use log::{debug, error, info, warn};
 
 use crate::audio::Audio;
 use crate::config::Config;
-use crate::cpu::Cpu;
+use crate::cpu::{Cpu, FRAME_RATE};
+use crate::debugger::{Debugger, Resume, HELP};
+use crate::display::buffer::DisplayBuffer;
+use crate::display::renderer::Output;
+use crate::display::terminal::{Backend, TerminalDisplay};
 use crate::display::Display;
-use crate::keypad::Keypad;
-use crate::memory::MEMORY_SIZE;
-use crate::rom::Rom;
+use crate::hooks::{BeepCallback, FrameCallback, Hooks};
+use crate::keypad::{Input, Keypad, KeypadState, PollSignal, TerminalKeypad};
+use crate::memory::{Memory, MEMORY_SIZE, USER_SPACE_STR};
+use crate::quirks::Quirks;
+use crate::replay::InputLog;
+use crate::rom::{Rom, STDIN_PATH};
+use crate::scheduler::Scheduler;
+#[cfg(feature = "serde")]
+use crate::state::StateFile;
+use crate::state::REWIND_CAPACITY;
+use crate::trace::FileTracer;
+use crate::watch::{RomWatcher, WATCH_INTERVAL};
+
+/// Volume change on every press of the volume keys
+const VOLUME_STEP: f32 = 0.1;
+
+/// Action to take on a single iteration of the system loop
+#[derive(Debug, PartialEq)]
+enum Step {
+    /// Run a CPU cycle with the provided keypad state
+    Run(KeypadState),
+    /// Emulation is paused, skip the CPU cycle
+    Idle,
+    /// Stop the emulation
+    Quit,
+    /// Restart the loaded ROM
+    Reset,
+    /// Write a quick save file
+    SaveState,
+    /// Restore the quick save file
+    LoadState,
+    /// Save the screen to a PNG file
+    Screenshot,
+    /// Switch between windowed and fullscreen modes
+    ToggleFullscreen,
+    /// Add the provided amount to the volume
+    ChangeVolume(f32),
+}
+
+/// Emulation state driven by the signals raised when polling the keypad
+#[derive(Debug, Default)]
+struct Emulation {
+    paused: bool,
+}
+
+impl Emulation {
+    /// Handles the result of polling the keypad and determines the next
+    /// `Step` for the system loop.
+    fn step(&mut self, poll: Result<KeypadState, PollSignal>) -> Step {
+        match poll {
+            Ok(_) if self.paused => Step::Idle,
+            Ok(pressed_keys) => Step::Run(pressed_keys),
+            Err(PollSignal::Quit) => Step::Quit,
+            Err(PollSignal::Pause) => {
+                self.paused = !self.paused;
+                Step::Idle
+            }
+            Err(PollSignal::Reset) => Step::Reset,
+            Err(PollSignal::SaveState) => Step::SaveState,
+            Err(PollSignal::LoadState) => Step::LoadState,
+            Err(PollSignal::Screenshot) => Step::Screenshot,
+            Err(PollSignal::ToggleFullscreen) => Step::ToggleFullscreen,
+            Err(PollSignal::VolumeDown) => Step::ChangeVolume(-VOLUME_STEP),
+            Err(PollSignal::VolumeUp) => Step::ChangeVolume(VOLUME_STEP),
+        }
+    }
+}
 
 pub struct System {
     audio: Audio,
-    #[allow(dead_code)]
+    /// Beeper volume, changed at runtime with the volume keys
+    volume: f32,
     config: Config,
     cpu: Cpu,
-    display: Display,
-    keypad: Keypad,
+    debugger: Option<Debugger>,
+    display: Output,
+    hooks: Hooks,
+    keypad: Box<dyn Input>,
+    /// Inputs recorded so far when running with `--record`
+    recording: Option<InputLog>,
+    /// Inputs left to replay when running with `--replay`
+    replay: Option<vec::IntoIter<KeypadState>>,
+    /// Cycles between vertical blanks when recording or replaying inputs,
+    /// these follow the cycle count instead of the wall clock
+    cycles_per_frame: Option<u32>,
+    /// Watches the ROM file for changes when running with `--watch`
+    watcher: Option<RomWatcher>,
 }
 
 impl System {
-    pub fn new(config: Config) -> Self {
-        let mut cpu = Cpu::new();
-        let sdl = sdl2::init().unwrap();
-        let event_pump = sdl.event_pump().unwrap();
-        let audio = Audio::new(&sdl);
-        let display = Display::new(&sdl, "Chip8", 12);
-        let keypad = Keypad::new(event_pump);
-        let rom = Rom::from_path(&config.rom);
-
-        cpu.load(rom);
-
-        Self {
+    pub fn new(config: Config) -> Result<Self> {
+        let replay = config
+            .replay
+            .as_ref()
+            .map(|path| {
+                InputLog::read(path)
+                    .with_context(|| format!("Failed to read input log {}", path.display()))
+            })
+            .transpose()?;
+        // Recordings need a known seed to be replayed
+        let seed = match (&replay, config.record.is_some()) {
+            (Some(replay), _) => Some(replay.seed),
+            (None, true) => Some(config.seed.unwrap_or_else(rand::random)),
+            (None, false) => config.seed,
+        };
+        let mut cpu = seed.map_or_else(Cpu::new, Cpu::with_seed);
+        let recording = seed
+            .filter(|_| config.record.is_some())
+            .map(|seed| InputLog::new(seed, cycles_per_frame(config.clock_rate())));
+        let cycles_per_frame = replay
+            .as_ref()
+            .or(recording.as_ref())
+            .map(|log| log.cycles_per_frame);
+        let replay = replay.map(|replay| replay.inputs().collect::<Vec<_>>().into_iter());
+        // Read before the terminal keypad takes over the standard input
+        let rom = Rom::read(&config.rom)
+            .with_context(|| format!("Failed to read ROM {}", config.rom.display()))?;
+        let backend = config.backend.unwrap_or_default();
+        let (display, keypad, audio): (Output, Box<dyn Input>, Audio) = match backend {
+            Backend::Sdl => {
+                let sdl = sdl2::init()
+                    .map_err(Error::msg)
+                    .context("Failed to initialize SDL")?;
+                let event_pump = sdl
+                    .event_pump()
+                    .map_err(Error::msg)
+                    .context("Failed to read SDL events")?;
+                let mut display = Display::new(
+                    &sdl,
+                    "Chip8",
+                    config.scale.unwrap_or_else(|| Display::fit_scale(&sdl)),
+                    config.palette(),
+                );
+
+                display.decay = config.fade;
+
+                if config.fullscreen {
+                    display.set_fullscreen(true);
+                }
+
+                let mut keypad = Keypad::new(event_pump, config.keymap());
+
+                // Controllers are optional, the keyboard is enough to play
+                match sdl.game_controller() {
+                    Ok(subsystem) => keypad.enable_controllers(subsystem, config.controller_map()),
+                    Err(err) => warn!("Game controllers unavailable: {}", err),
+                }
+
+                (
+                    Output::new(Box::new(display)),
+                    Box::new(keypad),
+                    Audio::new(&sdl, config.volume(), config.tone()),
+                )
+            }
+            Backend::Terminal => {
+                // SDL is only used for sound here, hosts without it (e.g.
+                // over SSH) play silently
+                let audio = match sdl2::init() {
+                    Ok(sdl) => Audio::new(&sdl, config.volume(), config.tone()),
+                    Err(err) => {
+                        warn!("SDL unavailable, running without sound: {}", err);
+                        Audio::silent()
+                    }
+                };
+
+                (
+                    Output::new(Box::new(TerminalDisplay::new())),
+                    Box::new(TerminalKeypad::new(config.keymap())),
+                    audio,
+                )
+            }
+        };
+
+        cpu.quirks = Quirks::for_variant(config.variant.unwrap_or_else(|| rom.detect_variant()));
+        cpu.quirks.strict_opcodes = config.strict;
+        cpu.quirks.display_wait = config.display_wait;
+
+        if let Some(font_base) = config.font_base {
+            cpu.ram = Memory::with_font_base(MEMORY_SIZE, USER_SPACE_STR, font_base as usize);
+        }
+
+        if let Some(start) = config.start {
+            cpu.set_start(start);
+        }
+
+        debug!(
+            "Loaded {} ({} bytes) as {}",
+            config.rom.display(),
+            rom.bytes().len(),
+            cpu.quirks.variant
+        );
+        cpu.load(rom)?;
+        cpu.max_draws_per_frame = config.max_draws_per_frame;
+        cpu.key_debounce = config.key_debounce.unwrap_or_default();
+
+        for instr in config.deny.iter().flatten() {
+            cpu.deny(instr);
+        }
+
+        if let Some(path) = config.trace.as_ref() {
+            match FileTracer::create(path) {
+                Ok(tracer) => cpu.set_tracer(tracer),
+                Err(err) => error!("Failed to create trace file: {}", err),
+            }
+        }
+
+        let debugger = config.debug.then(Debugger::new);
+        let watcher = match config.watch {
+            true if config.rom == Path::new(STDIN_PATH) => {
+                warn!("ROMs read from the standard input can't be watched");
+                None
+            }
+            true => Some(RomWatcher::new(config.rom.clone(), WATCH_INTERVAL)),
+            false => None,
+        };
+
+        if debugger.is_some() {
+            cpu.enable_rewind(REWIND_CAPACITY);
+            cpu.record_collisions(true);
+        }
+
+        Ok(Self {
             audio,
+            volume: config.volume(),
             config,
             cpu,
+            debugger,
             display,
+            hooks: Hooks::default(),
             keypad,
+            recording,
+            replay,
+            cycles_per_frame,
+            watcher,
+        })
+    }
+
+    /// Current display output, borrowed from the CPU without copying it
+    pub fn display_buffer(&self) -> &DisplayBuffer {
+        &self.cpu.display_buffer
+    }
+
+    /// Registers a callback invoked on every frame with the display output
+    pub fn on_frame(&mut self, callback: FrameCallback) {
+        self.hooks.set_on_frame(callback);
+    }
+
+    /// Registers a callback invoked when the beeper starts or stops
+    pub fn on_beep(&mut self, callback: BeepCallback) {
+        self.hooks.set_on_beep(callback);
+    }
+
+    /// Writes the CPU state and ROM to a quick save file next to the ROM
+    #[cfg(feature = "serde")]
+    fn save_state(&self) {
+        let path = StateFile::path_for(&self.config.rom);
+        let state_file = StateFile {
+            rom: self.cpu.rom.bytes().to_vec(),
+            state: self.cpu.save_state(),
+        };
+
+        match state_file.write(&path) {
+            Ok(()) => info!("State saved to {}", path.display()),
+            Err(err) => error!("Failed to save state: {}", err),
+        }
+    }
+
+    /// Restores the CPU state from the quick save file, if any
+    #[cfg(feature = "serde")]
+    fn load_state(&mut self) {
+        let path = StateFile::path_for(&self.config.rom);
+
+        if !path.exists() {
+            warn!("No saved state found at {}", path.display());
+            return;
         }
+
+        match StateFile::read(&path) {
+            Ok(state_file) => {
+                self.cpu.rom = state_file.rom.into();
+                self.cpu.load_state(state_file.state);
+                self.display.render(&self.cpu.display_buffer);
+                info!("State loaded from {}", path.display());
+            }
+            Err(err) => error!("Failed to load state: {}", err),
+        }
+    }
+
+    /// Saves the screen to a PNG file next to the ROM, named after the ROM
+    /// and the current time
+    #[cfg(feature = "screenshot")]
+    fn screenshot(&self) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let stem = self
+            .config
+            .rom
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy();
+        let path = self
+            .config
+            .rom
+            .with_file_name(format!("{}-{}.png", stem, timestamp));
+
+        match self.display.renderer.screenshot(&path) {
+            Ok(()) => info!("Screenshot saved to {}", path.display()),
+            Err(err) => error!("Failed to save screenshot: {}", err),
+        }
+    }
+
+    #[cfg(not(feature = "screenshot"))]
+    fn screenshot(&self) {
+        warn!("Screenshots require the \"screenshot\" feature");
+    }
+
+    #[cfg(not(feature = "serde"))]
+    fn save_state(&self) {
+        warn!("Save states require the \"serde\" feature");
+    }
+
+    #[cfg(not(feature = "serde"))]
+    fn load_state(&mut self) {
+        warn!("Save states require the \"serde\" feature");
     }
 
     pub fn start(mut self) {
-        while let Ok(pressed_keys) = self.keypad.poll() {
-            if self.cpu.pc as usize >= MEMORY_SIZE {
-                panic!("EOF");
+        let mut scheduler =
+            Scheduler::with_refresh_rate(self.config.clock_rate(), self.config.refresh_rate());
+        let mut emulation = Emulation::default();
+        let mut audio_pattern = None;
+
+        if self.debugger.is_some() {
+            println!("{}", HELP);
+        }
+
+        loop {
+            if let Some(rom) = self.watcher.as_mut().and_then(RomWatcher::poll) {
+                match self.cpu.reload(rom) {
+                    Ok(()) => {
+                        info!("Reloaded {}", self.config.rom.display());
+                        self.display.clear();
+                    }
+                    Err(err) => error!("Failed to reload ROM: {}", err),
+                }
+            }
+
+            let pressed_keys = match emulation.step(self.keypad.poll()) {
+                Step::Run(pressed_keys) => match self.replay.as_mut() {
+                    Some(replay) => match replay.next() {
+                        Some(replayed_keys) => replayed_keys,
+                        None => {
+                            info!("Replay finished");
+                            break;
+                        }
+                    },
+                    None => pressed_keys,
+                },
+                Step::Idle => {
+                    // Timers are not ticked while paused, the last frame is
+                    // rendered to keep the window responsive
+                    self.audio.stop();
+                    self.hooks.beep(false);
+                    self.display.render(&self.cpu.display_buffer);
+                    thread::sleep(Duration::from_secs_f32(1.0 / FRAME_RATE));
+                    continue;
+                }
+                Step::Quit => break,
+                Step::Reset if self.cycles_per_frame.is_some() => {
+                    warn!("Restarting the ROM is disabled while recording or replaying inputs");
+                    continue;
+                }
+                Step::Reset => {
+                    self.cpu.reset();
+                    self.display.clear();
+                    continue;
+                }
+                Step::SaveState => {
+                    self.save_state();
+                    continue;
+                }
+                Step::LoadState if self.cycles_per_frame.is_some() => {
+                    warn!("Loading states is disabled while recording or replaying inputs");
+                    continue;
+                }
+                Step::LoadState => {
+                    self.load_state();
+                    continue;
+                }
+                Step::Screenshot => {
+                    self.screenshot();
+                    continue;
+                }
+                Step::ToggleFullscreen => {
+                    self.display.renderer.toggle_fullscreen();
+                    continue;
+                }
+                Step::ChangeVolume(change) => {
+                    self.volume = (self.volume + change).clamp(0.0, 1.0);
+                    self.audio.set_volume(self.volume);
+                    info!("Volume set to {:.0}%", self.volume * 100.0);
+                    continue;
+                }
+            };
+            let mut stepping = false;
+
+            if let Some(debugger) = self.debugger.as_mut() {
+                if debugger.should_break(self.cpu.pc) {
+                    // Undone instructions may have changed the display
+                    self.display.invalidate();
+
+                    match debugger.prompt(&mut self.cpu) {
+                        Resume::Step => stepping = true,
+                        Resume::Continue => {}
+                        Resume::Quit => {
+                            println!("Exiting...");
+                            break;
+                        }
+                    }
+                }
+            }
+
+            if let Some(recording) = self.recording.as_mut() {
+                recording.push(pressed_keys);
             }
 
-            let cycle_output = self.cpu.cycle(pressed_keys);
+            let cycle_start = Instant::now();
+
+            let cycle_output = match self.cpu.cycle(pressed_keys) {
+                Ok(cycle_output) => cycle_output,
+                Err(err) => {
+                    error!("{}", err);
+                    break;
+                }
+            };
+
+            if self.cpu.is_halted() {
+                info!("Program exited");
+                break;
+            }
+
+            if let Some(debugger) = self.debugger.as_mut() {
+                for hit in self.cpu.take_watch_hits() {
+                    println!("{}", hit);
+                    debugger.pause();
+                }
+
+                let collisions = self.cpu.take_collisions();
+
+                // Only shown when stepping, these would flood the output
+                if stepping && !collisions.is_empty() {
+                    let pixels: Vec<String> = collisions
+                        .iter()
+                        .map(|(x, y)| format!("({}, {})", x, y))
+                        .collect();
 
+                    println!("Pixels erased at {}", pixels.join(" "));
+                }
+            }
+
+            // Display changes are accumulated and flushed on the next refresh
             if cycle_output.display_update {
-                self.display.render(&cycle_output.display_buffer);
+                self.display.invalidate();
+            }
+
+            if self.cpu.audio_pattern() != audio_pattern {
+                audio_pattern = self.cpu.audio_pattern();
+                self.audio.set_pattern(audio_pattern);
             }
 
             if cycle_output.beep {
@@ -56,26 +493,128 @@ impl System {
                 self.audio.stop();
             }
 
-            if self.config.debug {
-                let mut stdout = stdout();
+            self.hooks.beep(cycle_output.beep);
 
-                stdout
-                    .write(b"Debugging Mode. Press ENTER to run next cycle.\nPress: q followed by ENTER to Quit\n")
-                    .expect("Failed to write to stdout.");
-                stdout.flush().expect("Failed to flush stdout.");
+            if stepping {
+                // Time spent waiting for input doesn't count, each step
+                // accounts for a single cycle. The display is rendered right
+                // away to show the effects of the stepped instruction.
+                let cycle_duration = scheduler.cycle_duration();
+                let frames = self.frames_due(&mut scheduler, cycle_duration);
 
-                if let Some(i) = stdin().bytes().next().and_then(|res| res.ok()) {
-                    // 113 - q
-                    if i == 113 {
-                        println!("Exiting...");
-                        break;
-                    }
-                }
+                self.vblank(frames);
+                scheduler.refreshes_due(cycle_duration);
+
+                self.display.flush(&self.cpu.display_buffer);
 
                 continue;
-            } else {
-                std::thread::sleep(std::time::Duration::from_millis(2));
+            }
+
+            thread::sleep(scheduler.sleep_duration(cycle_start.elapsed()));
+
+            let elapsed = cycle_start.elapsed();
+
+            let frames = self.frames_due(&mut scheduler, elapsed);
+
+            self.vblank(frames);
+
+            if scheduler.refreshes_due(elapsed) > 0 {
+                self.display.flush(&self.cpu.display_buffer);
+            }
+        }
+
+        self.audio.stop();
+        self.hooks.beep(false);
+
+        if let (Some(recording), Some(path)) =
+            (self.recording.as_ref(), self.config.record.as_ref())
+        {
+            match recording.write(path) {
+                Ok(()) => info!("Inputs recorded to {}", path.display()),
+                Err(err) => error!("Failed to write input log: {}", err),
+            }
+        }
+
+        if let Some(mut tracer) = self.cpu.take_tracer() {
+            if let Err(err) = tracer.flush() {
+                error!("Failed to write trace file: {}", err);
+            }
+        }
+    }
+
+    /// Vertical blanks due after a cycle that took `elapsed`.
+    ///
+    /// Recorded and replayed runs count frames by cycles instead, so timers
+    /// tick and draw limits reset on the same cycles on every run.
+    fn frames_due(&self, scheduler: &mut Scheduler, elapsed: Duration) -> u32 {
+        match self.cycles_per_frame {
+            Some(cycles) => self.cpu.cycle_count().is_multiple_of(cycles.into()) as u32,
+            None => scheduler.frames_due(elapsed),
+        }
+    }
+
+    /// Signals `frames` vertical blanks to the CPU.
+    ///
+    /// The display isn't rendered here but on the display refreshes, at
+    /// most once per refresh no matter how many draws happened since the
+    /// last one. Fading pixels change on every refresh, so these are
+    /// rendered even if the buffer didn't change.
+    fn vblank(&mut self, frames: u32) {
+        for _ in 0..frames {
+            self.cpu.vblank();
+            self.hooks.frame(&self.cpu.display_buffer);
+        }
+    }
+}
+
+/// Cycles run on every frame at `clock_rate`, at least one
+fn cycles_per_frame(clock_rate: f32) -> u32 {
+    (clock_rate / FRAME_RATE).round().max(1.0) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keypad::{KeypadState, PollSignal};
+
+    use super::{Emulation, Step, VOLUME_STEP};
+
+    #[test]
+    fn paused_emulation_runs_no_cycles() {
+        let polls = [
+            Ok(KeypadState::default()),
+            Err(PollSignal::Pause),
+            Ok(KeypadState::default()),
+            Ok(KeypadState::default()),
+            Err(PollSignal::Pause),
+            Ok(KeypadState::default()),
+            Err(PollSignal::Quit),
+            Ok(KeypadState::default()),
+        ];
+        let mut emulation = Emulation::default();
+        let mut cycles = Vec::new();
+
+        for (n, poll) in polls.into_iter().enumerate() {
+            match emulation.step(poll) {
+                Step::Run(_) => cycles.push(n),
+                Step::Quit => break,
+                _ => {}
             }
         }
+
+        assert_eq!(cycles, vec![0, 5]);
+    }
+
+    #[test]
+    fn volume_keys_change_the_volume() {
+        let mut emulation = Emulation::default();
+
+        assert_eq!(
+            emulation.step(Err(PollSignal::VolumeUp)),
+            Step::ChangeVolume(VOLUME_STEP)
+        );
+        assert_eq!(
+            emulation.step(Err(PollSignal::VolumeDown)),
+            Step::ChangeVolume(-VOLUME_STEP)
+        );
     }
 }
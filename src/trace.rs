@@ -0,0 +1,127 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::opcode::{Instruction, Opcode};
+use crate::register_set::RegisterSet;
+
+/// Receives every instruction executed by the `Cpu`.
+///
+/// Tracers are installed with `Cpu::set_tracer` and called right before the
+/// instruction is executed, so registers hold the values the instruction
+/// operates on.
+pub trait Tracer {
+    /// Traces the instruction fetched from `addr`
+    fn trace(
+        &mut self,
+        addr: u16,
+        opcode: &Opcode,
+        instr: &Instruction,
+        registers: &RegisterSet,
+        i: u16,
+    );
+
+    /// Flushes any buffered output, reporting errors found while tracing
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes one line per executed instruction to a file.
+///
+/// Each line holds the instruction address, the raw opcode, the decoded
+/// mnemonic and a snapshot of the registers:
+///
+/// ```text
+/// 0x0200  0x6a02  LD VA, 0x02     0: 0x00 1: 0x00 ... F: 0x00  I: 0x0000
+/// ```
+pub struct FileTracer {
+    writer: BufWriter<File>,
+    /// First error found writing the trace, no more lines are written after
+    /// it happens.
+    error: Option<io::Error>,
+}
+
+impl FileTracer {
+    /// Creates or truncates the trace file at `path`
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+            error: None,
+        })
+    }
+}
+
+impl Tracer for FileTracer {
+    fn trace(
+        &mut self,
+        addr: u16,
+        opcode: &Opcode,
+        instr: &Instruction,
+        registers: &RegisterSet,
+        i: u16,
+    ) {
+        if self.error.is_some() {
+            return;
+        }
+
+        if let Err(err) = writeln!(
+            self.writer,
+            "{:#06x}  {}  {:<14}  {}  I: {:#06x}",
+            addr,
+            opcode,
+            instr.to_string(),
+            registers,
+            i
+        ) {
+            self.error = Some(err);
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::cpu::Cpu;
+    use crate::keypad::KeypadState;
+
+    use super::FileTracer;
+
+    #[test]
+    fn writes_a_line_per_instruction() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pong.trace");
+        let mut cpu = Cpu::new();
+
+        cpu.load(vec![0x6A, 0x02, 0xA2, 0x2A, 0x7A, 0x01].into())
+            .unwrap();
+        cpu.set_tracer(FileTracer::create(&path).unwrap());
+        cpu.run_cycles(3, KeypadState::default()).unwrap();
+        cpu.take_tracer().unwrap().flush().unwrap();
+
+        let trace = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = trace.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            [
+                "0x0200  0x6a02  LD VA, 0x02     ",
+                "0: 0x00 1: 0x00 2: 0x00 3: 0x00 4: 0x00 5: 0x00 6: 0x00 7: 0x00 ",
+                "8: 0x00 9: 0x00 A: 0x00 B: 0x00 C: 0x00 D: 0x00 E: 0x00 F: 0x00  ",
+                "I: 0x0000",
+            ]
+            .concat()
+        );
+        assert!(lines[2].contains("A: 0x02"), "Registers before executing");
+    }
+}
@@ -0,0 +1,115 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::rom::Rom;
+
+/// Time between checks of the ROM file, see `RomWatcher::poll`
+pub const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Modification time and length of a file, telling whether it changed
+type Stamp = (SystemTime, u64);
+
+/// Watches a ROM file for changes by polling its modification time, to
+/// reload it as it's rebuilt.
+pub struct RomWatcher {
+    path: PathBuf,
+    interval: Duration,
+    /// Stamp of the file when it was last read
+    loaded: Option<Stamp>,
+    /// Stamp of a change not read yet, waiting for writes to settle
+    pending: Option<Stamp>,
+    last_check: Option<Instant>,
+}
+
+impl RomWatcher {
+    /// Watches the file at `path`, checking it at most once per `interval`.
+    /// The current contents are taken as loaded.
+    pub fn new(path: PathBuf, interval: Duration) -> Self {
+        Self {
+            loaded: stamp(&path),
+            path,
+            interval,
+            pending: None,
+            last_check: None,
+        }
+    }
+
+    /// Returns the new ROM if the file changed since it was last read.
+    ///
+    /// A change is only read once the file stayed the same for a whole
+    /// interval, so a ROM still being written isn't loaded halfway. Files
+    /// failing to be read are retried on the following polls.
+    pub fn poll(&mut self) -> Option<Rom> {
+        if self
+            .last_check
+            .is_some_and(|last_check| last_check.elapsed() < self.interval)
+        {
+            return None;
+        }
+
+        self.last_check = Some(Instant::now());
+
+        let stamp = stamp(&self.path)?;
+
+        if self.loaded == Some(stamp) {
+            self.pending = None;
+            return None;
+        }
+
+        if self.pending != Some(stamp) {
+            self.pending = Some(stamp);
+            return None;
+        }
+
+        let rom = Rom::read(&self.path).ok()?;
+
+        self.loaded = Some(stamp);
+        self.pending = None;
+
+        Some(rom)
+    }
+}
+
+/// Stamp of the file at `path`, `None` if it can't be read (e.g. while it's
+/// being replaced)
+fn stamp(path: &Path) -> Option<Stamp> {
+    let metadata = fs::metadata(path).ok()?;
+
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::time::{Duration, SystemTime};
+
+    use super::RomWatcher;
+
+    #[test]
+    fn reads_changes_once_settled() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("game.ch8");
+
+        fs::write(&path, [0x12, 0x00]).unwrap();
+
+        let mut watcher = RomWatcher::new(path.clone(), Duration::ZERO);
+
+        assert!(watcher.poll().is_none(), "Unchanged file");
+
+        fs::write(&path, [0x00, 0xE0, 0x12, 0x02]).unwrap();
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(1))
+            .unwrap();
+
+        assert!(watcher.poll().is_none(), "Waits for writes to settle");
+        assert_eq!(
+            watcher.poll().map(|rom| rom.bytes().to_vec()),
+            Some(vec![0x00, 0xE0, 0x12, 0x02])
+        );
+        assert!(watcher.poll().is_none(), "Read only once");
+    }
+}
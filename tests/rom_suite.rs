@@ -0,0 +1,113 @@
+//! Runs the test ROMs in `tests/roms` headlessly and compares the display
+//! output against the golden files next to them.
+//!
+//! Each ROM `tests/roms/<name>.ch8` is paired with `tests/roms/<name>.txt`,
+//! holding the display rendered with `DisplayBuffer::to_ascii` after running
+//! `CYCLES` cycles. Adding a ROM to the suite only takes adding both files.
+//!
+//! Golden files are (re)written from the current output by running the suite
+//! with the `UPDATE_GOLDEN` environment variable set:
+//!
+//! ```text
+//! UPDATE_GOLDEN=1 cargo test --test rom_suite
+//! ```
+//!
+//! Golden files written this way only catch regressions, the goldens of
+//! `maze` and `test` were produced by this emulator. The `flags` ROM, built
+//! from `flags.asm` with `chip8 asm`, checks the 8xy_ results and flags
+//! itself, its golden is the screen of every case passing, drawn from the
+//! font rather than recorded.
+//!
+//! Quirks are pinned per ROM in `ROM_QUIRKS` instead of detected, so a
+//! detection change can't shift the goldens.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ch8::display::{HIRES_SCREEN_HEIGHT, HIRES_SCREEN_WIDTH};
+use ch8::headless::HeadlessSystem;
+use ch8::quirks::{Chip8Variant, Quirks};
+use ch8::rom::Rom;
+
+/// Cycles to run each ROM for before taking the display output
+const CYCLES: u64 = 6000;
+
+/// Seed for ROMs drawing random numbers, keeps their output reproducible
+const SEED: u64 = 0xC8;
+
+/// Variant each ROM is run with, by file stem
+const ROM_QUIRKS: [(&str, Chip8Variant); 3] = [
+    ("flags", Chip8Variant::Chip8),
+    ("maze", Chip8Variant::Chip8),
+    ("test", Chip8Variant::Chip8),
+];
+
+/// Runs the ROM at `path` and renders its display output
+fn run(path: &Path) -> String {
+    let rom = Rom::from_path(&path.to_path_buf());
+    let stem = path.file_stem().unwrap_or_default();
+    let quirks = ROM_QUIRKS
+        .iter()
+        .find(|(name, _)| stem == *name)
+        .map(|(_, variant)| Quirks::for_variant(*variant))
+        .unwrap_or_else(|| panic!("Pin the quirks of {} in ROM_QUIRKS", path.display()));
+    let mut system = HeadlessSystem::with_seed(rom, quirks, SEED)
+        .unwrap_or_else(|err| panic!("{} failed to load: {}", path.display(), err));
+
+    if let Err(err) = system.run_cycles(CYCLES) {
+        panic!("{} failed: {}", path.display(), err);
+    }
+
+    system
+        .display_buffer()
+        .to_ascii(HIRES_SCREEN_WIDTH, HIRES_SCREEN_HEIGHT)
+}
+
+/// Drops trailing spaces, these are easily lost when editing golden files
+fn normalize(output: &str) -> String {
+    output
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn roms_match_golden_output() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/roms");
+    let update = env::var_os("UPDATE_GOLDEN").is_some();
+    let mut roms: Vec<PathBuf> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ch8"))
+        .collect();
+    let mut mismatches = Vec::new();
+
+    roms.sort();
+    assert!(!roms.is_empty(), "No test ROMs found in {}", dir.display());
+
+    for rom in roms.iter() {
+        let golden = rom.with_extension("txt");
+        let output = run(rom);
+
+        if update {
+            fs::write(&golden, &output).unwrap();
+            continue;
+        }
+
+        let expected = fs::read_to_string(&golden)
+            .unwrap_or_else(|_| panic!("Missing golden output {}", golden.display()));
+
+        if normalize(&output) != normalize(&expected) {
+            mismatches.push(format!(
+                "{}:\n--- expected\n{}\n--- found\n{}",
+                rom.display(),
+                expected,
+                output
+            ));
+        }
+    }
+
+    assert!(mismatches.is_empty(), "{}", mismatches.join("\n"));
+}
@@ -0,0 +1,174 @@
+; Checks the results and VF flags of the 8xy_ arithmetic and logic opcodes,
+; drawing the number of each case that passes: 0 to 7 on the first row and
+; 8 to F on the second. A missing digit is a failed case.
+;
+; Run with the CHIP-8 quirks, case E expects OR to reset VF. Shifts use the
+; same register as Vx and Vy so both shift behaviors agree.
+;
+; Each case leaves the result in V1 and the flag in V3, expected in V5 and V6.
+; V4 holds the case number and V7, V8 where its digit goes.
+
+        LD V4, 0
+        LD V7, 0
+        LD V8, 0
+
+        ; 0: ADD without carry
+        LD V1, 0x10
+        LD V2, 0x20
+        ADD V1, V2
+        LD V3, VF
+        LD V5, 0x30
+        LD V6, 0
+        CALL check
+
+        ; 1: ADD with carry
+        LD V1, 0xFF
+        LD V2, 0x02
+        ADD V1, V2
+        LD V3, VF
+        LD V5, 0x01
+        LD V6, 1
+        CALL check
+
+        ; 2: SUB without borrow
+        LD V1, 0x30
+        LD V2, 0x10
+        SUB V1, V2
+        LD V3, VF
+        LD V5, 0x20
+        LD V6, 1
+        CALL check
+
+        ; 3: SUB with borrow
+        LD V1, 0x10
+        LD V2, 0x30
+        SUB V1, V2
+        LD V3, VF
+        LD V5, 0xE0
+        LD V6, 0
+        CALL check
+
+        ; 4: SUB of equal values doesn't borrow
+        LD V1, 0x10
+        LD V2, 0x10
+        SUB V1, V2
+        LD V3, VF
+        LD V5, 0x00
+        LD V6, 1
+        CALL check
+
+        ; 5: SUBN without borrow
+        LD V1, 0x10
+        LD V2, 0x30
+        SUBN V1, V2
+        LD V3, VF
+        LD V5, 0x20
+        LD V6, 1
+        CALL check
+
+        ; 6: SUBN with borrow
+        LD V1, 0x30
+        LD V2, 0x10
+        SUBN V1, V2
+        LD V3, VF
+        LD V5, 0xE0
+        LD V6, 0
+        CALL check
+
+        ; 7: SHR shifting out a 1
+        LD V1, 0x05
+        SHR V1, V1
+        LD V3, VF
+        LD V5, 0x02
+        LD V6, 1
+        CALL check
+
+        ; 8: SHR shifting out a 0
+        LD V1, 0x04
+        SHR V1, V1
+        LD V3, VF
+        LD V5, 0x02
+        LD V6, 0
+        CALL check
+
+        ; 9: SHL shifting out a 1
+        LD V1, 0x81
+        SHL V1, V1
+        LD V3, VF
+        LD V5, 0x02
+        LD V6, 1
+        CALL check
+
+        ; A: SHL shifting out a 0
+        LD V1, 0x41
+        SHL V1, V1
+        LD V3, VF
+        LD V5, 0x82
+        LD V6, 0
+        CALL check
+
+        ; B: ADD into VF keeps the carry
+        LD VF, 0xFF
+        LD V2, 0x02
+        ADD VF, V2
+        LD V1, VF
+        LD V3, VF
+        LD V5, 1
+        LD V6, 1
+        CALL check
+
+        ; C: SUB into VF keeps the borrow flag
+        LD VF, 0x10
+        LD V2, 0x30
+        SUB VF, V2
+        LD V1, VF
+        LD V3, VF
+        LD V5, 0
+        LD V6, 0
+        CALL check
+
+        ; D: SHR of VF keeps the shifted out bit
+        LD VF, 0x03
+        SHR VF, VF
+        LD V1, VF
+        LD V3, VF
+        LD V5, 1
+        LD V6, 1
+        CALL check
+
+        ; E: OR resets VF
+        LD VF, 1
+        LD V1, 0x0F
+        LD V2, 0xF0
+        OR V1, V2
+        LD V3, VF
+        LD V5, 0xFF
+        LD V6, 0
+        CALL check
+
+        ; F: XOR
+        LD V1, 0xFF
+        LD V2, 0x0F
+        XOR V1, V2
+        LD V3, VF
+        LD V5, 0xF0
+        LD V6, 0
+        CALL check
+
+end:    JP end
+
+; Draws the case number if V1 and V3 match V5 and V6, then moves to the next
+; case
+check:  SE V1, V5
+        JP next
+        SE V3, V6
+        JP next
+        LD F, V4
+        DRW V7, V8, 5
+next:   ADD V4, 1
+        ADD V7, 5
+        SE V7, 40
+        RET
+        LD V7, 0
+        ADD V8, 6
+        RET